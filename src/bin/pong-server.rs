@@ -0,0 +1,202 @@
+//! Headless dedicated server, hosts a room without a window or a local player.
+//!
+//! `pong-server --name "Office" --port 5000 --mode classic --shape two-side`
+
+use avian2d::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use lightyear::avian2d::plugin::AvianReplicationMode;
+use lightyear::avian2d::prelude::LightyearAvianPlugin;
+use pong_enhanced::models::game::area::AreaShapeKind;
+use pong_enhanced::models::game::gameplay::GameMode;
+use pong_enhanced::networking::conditioner::{NetworkConditionerPlugin, NetworkConditions, CONDITIONER_USAGE};
+use pong_enhanced::networking::protocol::{GameProtocolPlugin, LobbyConfig};
+use pong_enhanced::networking::server::{start_server, GameServerPlugin, LobbyEntity};
+use pong_enhanced::plugins::gameplay::GameplayPlugin;
+use pong_enhanced::resources::OnlineGameConfig;
+use std::process::ExitCode;
+use std::time::Duration;
+
+const TICK_RATE: f64 = 60.0;
+
+const USAGE: &str = "\
+Usage: pong-server [OPTIONS]
+
+Options:
+  --port <PORT>          Game port, 0 picks a free one [default: 0]
+  --name <NAME>          Room name shown in the server browser [default: Pong Server]
+  --password <PASS>      Room password, empty for an open room
+  --mode <MODE>          classic | upside-down | modern | blackout | twisted [default: classic]
+  --shape <SHAPE>        two-side | triangular | cuboid [default: two-side]
+  --win-score <POINTS>   Points needed to win [default: 10]
+  --max-players <COUNT>  Player limit [default: 4]
+  --input-delay <TICKS>  Input delay clients play with [default: 0]
+  --max-prediction <TICKS>
+                         Ticks clients predict beyond the input delay [default: 100]
+  --interp-delay <MS>    How far behind interpolated entities are shown [default: 100]
+  -h, --help             Print this help
+
+Debugging:
+{CONDITIONER_USAGE}";
+
+fn main() -> ExitCode {
+    let (config, lobby, conditions) = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(parsed)) => parsed,
+        Ok(None) => {
+            println!("{}", usage());
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{}", usage());
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut app = App::new();
+
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+            1.0 / TICK_RATE,
+        ))),
+        LogPlugin::default(),
+        TransformPlugin,
+        AssetPlugin::default(),
+        bevy::input::InputPlugin,
+        PhysicsPlugins::default()
+            .build()
+            .disable::<PhysicsTransformPlugin>()
+            .disable::<PhysicsInterpolationPlugin>(),
+        GameplayPlugin,
+        GameProtocolPlugin,
+        GameServerPlugin,
+        NetworkConditionerPlugin,
+    ))
+    .add_plugins(LightyearAvianPlugin {
+        replication_mode: AvianReplicationMode::Transform,
+        ..default()
+    })
+    // matches are spawned with meshes even though nothing renders them
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .insert_resource(config)
+    .insert_resource(conditions)
+    .add_systems(Startup, start_dedicated_server);
+
+    app.world_mut().spawn((lobby, LobbyEntity));
+
+    match app.run() {
+        AppExit::Success => ExitCode::SUCCESS,
+        AppExit::Error(code) => ExitCode::from(code.get()),
+    }
+}
+
+fn start_dedicated_server(
+    mut commands: Commands,
+    config: Res<OnlineGameConfig>,
+    lobby: Option<Single<(Entity, &LobbyConfig)>>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    if let Some((_, lobby)) = lobby.as_deref() {
+        info!(
+            "Hosting {:?} on {:?}, first to {} points, up to {} players{}",
+            lobby.game_mode,
+            lobby.area_shape,
+            lobby.points_to_win,
+            lobby.max_players,
+            if config.pass.is_some() { ", password protected" } else { "" },
+        );
+    }
+
+    if start_server(&mut commands, &config, lobby.map(|lobby| lobby.0)).is_none() {
+        exit.write(AppExit::error());
+    }
+}
+
+fn usage() -> String {
+    USAGE.replace("{CONDITIONER_USAGE}", CONDITIONER_USAGE)
+}
+
+/// Returns `None` when only the help text was asked for.
+fn parse_args(
+    mut args: impl Iterator<Item = String>,
+) -> Result<Option<(OnlineGameConfig, LobbyConfig, NetworkConditions)>, String> {
+    let mut config = OnlineGameConfig {
+        server_name: "Pong Server".to_owned(),
+        dedicated: true,
+        ..default()
+    };
+    let mut lobby = LobbyConfig::default();
+    let mut conditions = NetworkConditions::default();
+
+    while let Some(flag) = args.next() {
+        if flag == "-h" || flag == "--help" {
+            return Ok(None);
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{flag} expects a value"))?;
+
+        if conditions.parse_flag(&flag, &value)? {
+            continue;
+        }
+
+        match flag.as_str() {
+            "--port" => config.port = parse_number(&flag, &value)?,
+            "--name" => {
+                if value.trim().is_empty() {
+                    return Err("--name must not be empty".to_owned());
+                }
+                config.server_name = value;
+            }
+            "--password" => config.pass = Some(value).filter(|pass| !pass.is_empty()),
+            "--mode" => lobby.game_mode = parse_game_mode(&value)?,
+            "--shape" => lobby.area_shape = parse_area_shape(&value)?,
+            "--win-score" => {
+                lobby.points_to_win = parse_number(&flag, &value)?;
+                if lobby.points_to_win == 0 {
+                    return Err("--win-score must be at least 1".to_owned());
+                }
+            }
+            "--max-players" => {
+                lobby.max_players = parse_number(&flag, &value)?;
+                if lobby.max_players == 0 {
+                    return Err("--max-players must be at least 1".to_owned());
+                }
+            }
+            "--input-delay" => lobby.netcode.input_delay_ticks = parse_number(&flag, &value)?,
+            "--max-prediction" => lobby.netcode.max_prediction_ticks = parse_number(&flag, &value)?,
+            "--interp-delay" => lobby.netcode.interpolation_delay_ms = parse_number(&flag, &value)?,
+            _ => return Err(format!("unknown option {flag}")),
+        }
+    }
+
+    Ok(Some((config, lobby, conditions)))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{flag} expects a number, got \"{value}\""))
+}
+
+fn parse_game_mode(value: &str) -> Result<GameMode, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "classic" => Ok(GameMode::Classic),
+        "upside-down" | "upsidedown" => Ok(GameMode::UpsideDown),
+        "modern" => Ok(GameMode::Modern),
+        "blackout" => Ok(GameMode::Blackout),
+        "twisted" => Ok(GameMode::Twisted),
+        _ => Err(format!("unknown game mode \"{value}\"")),
+    }
+}
+
+fn parse_area_shape(value: &str) -> Result<AreaShapeKind, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "two-side" | "twoside" => Ok(AreaShapeKind::TwoSide),
+        "triangular" => Ok(AreaShapeKind::Triangular),
+        "cuboid" => Ok(AreaShapeKind::Cuboid),
+        _ => Err(format!("unknown area shape \"{value}\"")),
+    }
+}
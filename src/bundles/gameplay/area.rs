@@ -2,6 +2,7 @@ use crate::bundles::paddle::PaddleBundle;
 use crate::bundles::wall::WallBundle;
 use crate::bundles::{BallBundle, GoalBundle};
 use crate::components::area::Area;
+use crate::components::{MatchEntity, MatchScore, TeamTint};
 use crate::components::game_modes::{FlashyLight, PaddleTilt};
 use crate::models::game::gameplay::GameMode;
 use crate::resources::GameModeConfig;
@@ -26,12 +27,15 @@ impl AreaBundle {
         materials: &mut Assets<ColorMaterial>,
     ) {
         commands
-            .spawn(BallBundle::new(
-                meshes,
-                materials,
-                Vec3::ZERO,
-                Vec2::new(-300.0, 300.0),
-                BALL_RADIUS,
+            .spawn((
+                BallBundle::new(
+                    meshes,
+                    materials,
+                    Vec3::ZERO,
+                    Vec2::new(-300.0, 300.0),
+                    BALL_RADIUS,
+                ),
+                MatchEntity,
             ))
             .observe(handle_scoring);
 
@@ -40,6 +44,7 @@ impl AreaBundle {
             GameMode::Modern => {}
             GameMode::Blackout => {
                 commands.spawn((
+                    MatchEntity,
                     Transform::from_translation(Vec3::ZERO),
                     FlashyLight,
                     PointLight2d {
@@ -54,40 +59,55 @@ impl AreaBundle {
             GameMode::UpsideDown => {}
         }
 
+        commands.spawn((
+            MatchScore::new(&config.area_shape, config.win_score),
+            MatchEntity,
+        ));
+
         let teams = config.area_shape.get_teams();
 
         for team in teams {
-            let goal = commands.spawn(GoalBundle::new(team)).id();
+            let goal = commands.spawn((GoalBundle::new(team), MatchEntity)).id();
             let positions = team.get_positions();
+            let paddle_size = if team.area_side.is_horizontal() {
+                Vec2::new(PADDLE_SIZE.y, PADDLE_SIZE.x)
+            } else {
+                PADDLE_SIZE
+            };
 
             for i in 0..team.players.len() {
-                let mut paddle = commands.spawn(PaddleBundle::new(
-                    meshes,
-                    materials,
-                    positions[i],
-                    PADDLE_SIZE,
-                    goal,
-                    team.players[i],
+                let mut paddle = commands.spawn((
+                    PaddleBundle::new(
+                        meshes,
+                        materials,
+                        positions[i],
+                        paddle_size,
+                        goal,
+                        team.players[i],
+                    ),
+                    TeamTint::new(team.area_side),
+                    MatchEntity,
                 ));
 
                 if matches!(config.game_mode, GameMode::Twisted) {
                     paddle.insert(PaddleTilt { tilt: 0.0 });
                 }
             }
-
-            team.area_side.spawn_score_text(commands);
         }
 
         let walls = config.area_shape.get_wall_sides();
         for side in walls {
-            commands.spawn(WallBundle::new(*side));
+            commands.spawn((WallBundle::new(*side), MatchEntity));
         }
 
-        commands.spawn(AreaBundle {
-            transform: Transform::from_translation(Vec3::new(0.0, 0.0, -1.0)),
-            area: Area,
-            mesh: Mesh2d(meshes.add(Rectangle::new(FIXED_DIMENSIONS.x, FIXED_DIMENSIONS.y))),
-            material: MeshMaterial2d(materials.add(Color::srgb(0.05, 0.05, 0.05))),
-        });
+        commands.spawn((
+            AreaBundle {
+                transform: Transform::from_translation(Vec3::new(0.0, 0.0, -1.0)),
+                area: Area,
+                mesh: Mesh2d(meshes.add(Rectangle::new(FIXED_DIMENSIONS.x, FIXED_DIMENSIONS.y))),
+                material: MeshMaterial2d(materials.add(Color::srgb(0.05, 0.05, 0.05))),
+            },
+            MatchEntity,
+        ));
     }
 }
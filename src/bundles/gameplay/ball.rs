@@ -1,5 +1,5 @@
 use crate::utils::screen::ZERO_DAMPING;
-use crate::Ball;
+use crate::components::Ball;
 use avian2d::prelude::*;
 use bevy::asset::Assets;
 use bevy::color::Color;
@@ -1,5 +1,5 @@
 use crate::models::game::gameplay::PlayerId;
-use crate::Paddle;
+use crate::components::Paddle;
 use avian2d::prelude::*;
 use bevy::prelude::*;
 
@@ -1,9 +1,10 @@
-use crate::components::Player;
+use crate::components::{GamepadNumber, Player, PlayerConnection};
 use crate::models::game::area::LocalPlayerID;
 use crate::models::game::gameplay::PlayerId;
 use crate::networking::protocol::RemotePlayerId;
-use crate::resources::PlayerAction;
-use bevy::prelude::Bundle;
+use crate::resources::{ControlBindings, PlayerAction};
+use bevy::prelude::{Bundle, Entity};
+use leafwing_input_manager::action_state::ActionState;
 use leafwing_input_manager::input_map::InputMap;
 use lightyear::prelude::{NetworkTarget, Replicate};
 
@@ -14,19 +15,41 @@ pub struct PlayerBundle {
 }
 
 impl PlayerBundle {
-    pub fn new(id: LocalPlayerID) -> Self {
+    pub fn new(id: LocalPlayerID, bindings: &ControlBindings) -> Self {
         Self {
             player: Player {
                 id: PlayerId::Local(id),
             },
-            bindings: Player::get_input_map(id),
+            bindings: Player::get_input_map(id, None, bindings),
         }
     }
 
+    /// Gamepad player on the layout saved for its number.
+    pub fn new_gamepad(gamepad: Entity, number: u8, bindings: &ControlBindings) -> impl Bundle {
+        let id = LocalPlayerID::Gamepad(gamepad);
+
+        (
+            Self {
+                player: Player {
+                    id: PlayerId::Local(id),
+                },
+                bindings: Player::get_input_map(id, Some(GamepadNumber(number)), bindings),
+            },
+            GamepadNumber(number),
+        )
+    }
+
+    /// Server side player driven by a client's replicated inputs, so no
+    /// local bindings are attached.
     pub fn new_network(id: RemotePlayerId) -> impl Bundle {
         (
             Replicate::to_clients(NetworkTarget::All),
-            PlayerBundle::new(id.1),
+            Player {
+                id: PlayerId::Network(id),
+            },
+            id,
+            PlayerConnection::Connected,
+            ActionState::<PlayerAction>::default(),
         )
     }
 }
@@ -0,0 +1,12 @@
+use bevy::prelude::Component;
+
+/// Volume slider a playing sound follows.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SoundChannel {
+    Effects,
+    Music,
+}
+
+/// The looping background track.
+#[derive(Component)]
+pub struct MusicTrack;
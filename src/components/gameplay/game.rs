@@ -1,6 +1,91 @@
 use crate::bundles::{App, Entity, Vec2};
+use crate::models::game::area::{AreaShape, AreaSide};
 use crate::models::game::gameplay::PlayerId;
-use bevy::prelude::Component;
+use bevy::prelude::{Component, Timer};
+use serde::{Deserialize, Serialize};
+
+/// Everything spawned for a running match, despawned together when it ends.
+#[derive(Component)]
+pub struct MatchEntity;
+
+/// Runs from the end of a match to its teardown on the simulating peer.
+#[derive(Component)]
+pub struct MatchTeardown(pub Timer);
+
+/// What a replicated ball or paddle looks like, clients only receive its
+/// position and build the mesh from this.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum MatchVisual {
+    Ball { radius: f32 },
+    Paddle { size: Vec2, side: AreaSide },
+}
+
+/// Side of the paddle that last hit the ball. Set by the simulating peer
+/// and replicated, so every peer can tint the ball the same.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LastTouch(pub AreaSide);
+
+/// Drawn in the colour of this side's team from the player's own palette,
+/// and repainted whenever they pick another one. Meshes keep `alpha`.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct TeamTint {
+    pub side: AreaSide,
+    pub alpha: f32,
+}
+
+impl TeamTint {
+    #[inline]
+    pub fn new(side: AreaSide) -> Self {
+        Self { side, alpha: 1.0 }
+    }
+
+    #[inline]
+    pub fn with_alpha(self, alpha: f32) -> Self {
+        Self { alpha, ..self }
+    }
+}
+
+/// Score of the running match. Only the simulating peer (offline game or
+/// server) changes it, clients receive it through replication.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MatchScore {
+    pub scores: Vec<(AreaSide, u32)>,
+    pub win_score: u32,
+    pub winner: Option<AreaSide>,
+}
+
+impl MatchScore {
+    pub fn new(area_shape: &AreaShape, win_score: u32) -> Self {
+        Self {
+            scores: area_shape
+                .get_teams()
+                .iter()
+                .map(|team| (team.area_side, 0))
+                .collect(),
+            win_score,
+            winner: None,
+        }
+    }
+
+    pub fn get(&self, side: AreaSide) -> u32 {
+        self.scores
+            .iter()
+            .find(|(s, _)| *s == side)
+            .map_or(0, |(_, score)| *score)
+    }
+
+    /// Returns the new score of `side`, or `None` if it is not playing.
+    pub fn award_point(&mut self, side: AreaSide) -> Option<u32> {
+        let (_, score) = self.scores.iter_mut().find(|(s, _)| *s == side)?;
+        *score += 1;
+
+        if *score >= self.win_score && self.winner.is_none() {
+            self.winner = Some(side);
+        }
+
+        Some(*score)
+    }
+}
 
 #[derive(Component)]
 pub struct Ball {
@@ -19,3 +104,11 @@ pub struct Paddle {
     pub goal: Entity,
     pub id: PlayerId,
 }
+
+/// Paddle whose gamepad was unplugged mid-match. The match stays paused
+/// until that pad comes back or another device takes the paddle over.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct OrphanedPaddle {
+    pub gamepad: Entity,
+    pub number: u8,
+}
@@ -1,6 +1,8 @@
+pub mod audio;
 pub mod gameplay;
 pub mod player;
 pub mod ui;
 
+pub use audio::*;
 pub use gameplay::*;
 pub use player::*;
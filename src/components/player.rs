@@ -1,23 +1,85 @@
-use crate::bundles::{Entity, GamepadAxis};
+use crate::bundles::GamepadAxis;
 use crate::models::game::area::LocalPlayerID;
 use crate::models::game::gameplay::PlayerId;
 use crate::resources::controls::PlayerAction;
-use bevy::prelude::{Component, GamepadButton, KeyCode};
-use leafwing_input_manager::prelude::{InputMap, VirtualAxis};
+use crate::resources::{BindingSlot, ControlBindings};
+use bevy::prelude::{warn, Color, Component, GamepadButton, KeyCode, MouseButton};
+use leafwing_input_manager::prelude::{InputMap, MouseScrollAxis, VirtualAxis};
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
 pub struct Player {
     pub id: PlayerId,
 }
 
+/// Number of a gamepad player, the lowest one free when its pad connected.
+/// Picks which of the saved per-player gamepad settings apply.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GamepadNumber(pub u8);
+
+/// Colours a player can pick for their profile, their name tag is drawn in it.
+pub const PLAYER_COLORS: [Color; 8] = [
+    Color::srgb(0.95, 0.95, 0.95),
+    Color::srgb(0.9, 0.3, 0.3),
+    Color::srgb(0.3, 0.55, 0.95),
+    Color::srgb(0.35, 0.85, 0.4),
+    Color::srgb(0.95, 0.8, 0.25),
+    Color::srgb(0.75, 0.4, 0.9),
+    Color::srgb(0.95, 0.55, 0.2),
+    Color::srgb(0.3, 0.85, 0.85),
+];
+
+/// Names of the profiles made on first start.
+pub const PLAYER_NAMES: [&str; 8] = ["Ace", "Blitz", "Comet", "Drift", "Echo", "Flash", "Ghost", "Hawk"];
+
+/// What [`PLAYER_COLORS`] are called on the profiles page.
+pub const PLAYER_COLOR_NAMES: [&str; 8] = ["White", "Red", "Blue", "Green", "Yellow", "Purple", "Orange", "Teal"];
+
+/// Profile a local player picked in the lobby, kept between matches. Indexes
+/// [`Profiles`](crate::resources::Profiles).
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActiveProfile(pub usize);
+
+/// Name and colour a player goes by. Local players take it from their
+/// profile, online players send it with their join request, and the server
+/// copies it onto their paddles so every peer can show it.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PlayerIdentity {
+    pub name: String,
+    /// Index into [`PLAYER_COLORS`].
+    pub color: u8,
+}
+
+impl PlayerIdentity {
+    #[inline]
+    pub fn color(&self) -> Color {
+        PLAYER_COLORS[self.color as usize % PLAYER_COLORS.len()]
+    }
+}
+
+/// Replicated connection state of an online player. While `Reconnecting` the
+/// server keeps the player's slot and pilots their paddle.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayerConnection {
+    Connected,
+    Reconnecting { remaining_secs: u32 },
+}
+
+impl PlayerConnection {
+    #[inline]
+    pub fn is_reconnecting(self) -> bool {
+        matches!(self, PlayerConnection::Reconnecting { .. })
+    }
+}
+
 impl Player {
-    pub fn get_gamepad_input_map(gamepad: Entity) -> InputMap<PlayerAction> {
+    /// Default gamepad layout, not yet tied to any gamepad.
+    pub fn get_gamepad_input_map() -> InputMap<PlayerAction> {
         let mut map = InputMap::new([
             (PlayerAction::Dash, GamepadButton::LeftTrigger2),
             (PlayerAction::Push, GamepadButton::RightTrigger2),
             (PlayerAction::Pause, GamepadButton::Start),
-        ])
-        .with_gamepad(gamepad);
+        ]);
 
         map.insert_axis(PlayerAction::Move, GamepadAxis::LeftStickX)
             .insert_axis(PlayerAction::Move, GamepadAxis::LeftStickY)
@@ -27,40 +89,73 @@ impl Player {
         map
     }
 
+    /// Default layout of keyboard player `id`, all four fit on one keyboard.
+    /// Ids without a layout get an empty map rather than taking down the game.
     pub fn get_keyboard_input_map(id: u8) -> InputMap<PlayerAction> {
-        match id {
-            1 => {
-                let mut map = InputMap::new([
-                    (PlayerAction::Dash, KeyCode::ControlLeft),
-                    (PlayerAction::Push, KeyCode::Space),
-                    (PlayerAction::Pause, KeyCode::Escape),
-                ]);
+        let (buttons, movement, tilt) = match id {
+            1 => (
+                [KeyCode::ControlLeft, KeyCode::Space, KeyCode::Escape],
+                VirtualAxis::ws(),
+                VirtualAxis::ad(),
+            ),
+            2 => (
+                [KeyCode::ControlRight, KeyCode::Enter, KeyCode::End],
+                VirtualAxis::vertical_arrow_keys(),
+                VirtualAxis::horizontal_arrow_keys(),
+            ),
+            3 => (
+                [KeyCode::KeyU, KeyCode::KeyO, KeyCode::KeyP],
+                VirtualAxis::new(KeyCode::KeyK, KeyCode::KeyI),
+                VirtualAxis::new(KeyCode::KeyJ, KeyCode::KeyL),
+            ),
+            4 => (
+                [KeyCode::Numpad7, KeyCode::Numpad0, KeyCode::NumpadEnter],
+                VirtualAxis::new(KeyCode::Numpad5, KeyCode::Numpad8),
+                VirtualAxis::new(KeyCode::Numpad4, KeyCode::Numpad6),
+            ),
+            _ => {
+                warn!("No keyboard layout for player {id}, leaving it unbound");
+                return InputMap::default();
+            }
+        };
 
-                map.insert_axis(PlayerAction::Move, VirtualAxis::ws())
-                    .insert_axis(PlayerAction::Tilt, VirtualAxis::ad());
+        let [dash, push, pause] = buttons;
+        let mut map = InputMap::new([
+            (PlayerAction::Dash, dash),
+            (PlayerAction::Push, push),
+            (PlayerAction::Pause, pause),
+        ]);
 
-                map
-            }
-            2 => {
-                let mut map = InputMap::new([
-                    (PlayerAction::Dash, KeyCode::ControlRight),
-                    (PlayerAction::Push, KeyCode::Enter),
-                    (PlayerAction::Pause, KeyCode::End),
-                ]);
+        map.insert_axis(PlayerAction::Move, movement)
+            .insert_axis(PlayerAction::Tilt, tilt);
+
+        map
+    }
 
-                map.insert_axis(PlayerAction::Move, VirtualAxis::vertical_arrow_keys())
-                    .insert_axis(PlayerAction::Tilt, VirtualAxis::horizontal_arrow_keys());
+    /// Movement is left out, it follows the mouse itself rather than an axis
+    /// the input map could read.
+    pub fn get_mouse_input_map() -> InputMap<PlayerAction> {
+        let mut map = InputMap::new([
+            (PlayerAction::Dash, MouseButton::Left),
+            (PlayerAction::Push, MouseButton::Right),
+            (PlayerAction::Pause, MouseButton::Middle),
+        ]);
 
-                map
-            }
-            _ => panic!("Invalid id: {}", id),
-        }
+        map.insert_axis(PlayerAction::Tilt, MouseScrollAxis::Y);
+
+        map
     }
 
-    pub fn get_input_map(id: LocalPlayerID) -> InputMap<PlayerAction> {
+    /// The player's layout from the settings, falling back to the defaults.
+    /// Gamepad players get the layout saved for their number.
+    pub fn get_input_map(id: LocalPlayerID, number: Option<GamepadNumber>, bindings: &ControlBindings) -> InputMap<PlayerAction> {
         match id {
-            LocalPlayerID::Gamepad(id) => Self::get_gamepad_input_map(id),
-            LocalPlayerID::Keyboard(id) => Self::get_keyboard_input_map(id),
+            LocalPlayerID::Gamepad(gamepad) => {
+                let number = number.map_or(1, |number| number.0);
+                bindings.input_map(BindingSlot::Gamepad(number)).with_gamepad(gamepad)
+            }
+            LocalPlayerID::Keyboard(id) => bindings.input_map(BindingSlot::Keyboard(id)),
+            LocalPlayerID::Mouse => Self::get_mouse_input_map(),
         }
     }
 }
@@ -5,3 +5,39 @@ use crate::models::game::area::AreaSide;
 pub struct ScoreText {
     pub area_side: AreaSide,
 }
+
+/// Names of the players on a side, shown under its score.
+#[derive(Component)]
+pub struct TeamRosterText {
+    pub area_side: AreaSide,
+}
+
+/// Floating name over a paddle, a child of it.
+#[derive(Component)]
+pub struct PaddleNameTag;
+
+/// Lists online players whose slot is being held while they reconnect.
+#[derive(Component)]
+pub struct ReconnectStatusText;
+
+/// Names the controllers whose paddles wait for a new owner.
+#[derive(Component)]
+pub struct ControllerDisconnectedText;
+
+/// Shows the last `0` lines of the chat log.
+#[derive(Component)]
+pub struct ChatLogNode(pub usize);
+
+#[derive(Component)]
+pub struct ChatOverlay;
+
+/// Debug panel with the measured link's recent network stats.
+#[derive(Component)]
+pub struct NetStatsOverlay;
+
+#[derive(Component)]
+pub struct NetStatsText;
+
+/// Round trip graph bar for the `0`th oldest sample.
+#[derive(Component)]
+pub struct NetStatsBar(pub usize);
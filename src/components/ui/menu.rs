@@ -1,4 +1,9 @@
 use crate::bundles::Component;
+use std::net::SocketAddr;
+use crate::models::game::area::AreaSide;
+use crate::networking::lobby::LobbySeat;
+use crate::networking::moderation::HostCommand;
+use crate::resources::{BindingSlot, BindingTarget};
 
 #[derive(Component)]
 pub struct SettingsMenu;
@@ -11,10 +16,28 @@ pub struct OnlinePlayMenu;
 #[derive(Component)]
 pub struct PauseMenu;
 #[derive(Component)]
-pub struct PlayerJoinInMenu(pub u8);
+pub struct LocalLobbyMenu;
 #[derive(Component)]
 pub struct OnlineCreateMenu;
 #[derive(Component)]
+pub struct DirectConnectMenu;
+#[derive(Component)]
+pub struct ConnectingMenu;
+#[derive(Component)]
+pub struct ConnectionErrorMenu(pub SocketAddr);
+/// Asks for the password of the room at this address.
+#[derive(Component)]
+pub struct ServerPasswordMenu(pub SocketAddr);
+/// Controls page, showing the layout of the gamepad player with this number.
+#[derive(Component)]
+pub struct ControlsMenu(pub u8);
+/// Profiles page, showing the profile at this index.
+#[derive(Component)]
+pub struct ProfilesMenu(pub usize);
+/// Analog settings page of the gamepad player with this number.
+#[derive(Component)]
+pub struct AnalogMenu(pub u8);
+#[derive(Component)]
 pub struct Menu;
 
 #[derive(Component)]
@@ -27,4 +50,53 @@ pub struct LobbyMenu;
 pub struct LobbyPlayerListNode;
 
 #[derive(Component)]
-pub struct LobbySettingsDisplay;
\ No newline at end of file
+pub struct LobbySettingsDisplay;
+
+#[derive(Component)]
+pub struct LobbyStartButton;
+
+/// First of the lobby's own buttons, the host's player list leads down to it.
+#[derive(Component)]
+pub struct LobbyTeamButton;
+
+/// Player list button sending its command for the room's host.
+#[derive(Component)]
+pub struct HostCommandButton(pub HostCommand);
+
+/// Says why the match cannot start yet.
+#[derive(Component)]
+pub struct LobbyStatusText;
+
+#[derive(Component)]
+pub struct DirectConnectStatus;
+
+#[derive(Component)]
+pub struct RecentAddress(pub String);
+
+/// Controls page button showing, and capturing, one binding.
+#[derive(Component)]
+pub struct RebindButton {
+    pub slot: BindingSlot,
+    pub target: BindingTarget,
+}
+
+/// Prompts for the press being captured, or reports how it went.
+#[derive(Component)]
+pub struct RebindStatusText;
+
+/// Lists the local players who picked this side of the arena.
+#[derive(Component)]
+pub struct LocalLobbyTeamList(pub AreaSide);
+
+/// Says how to join, or what keeps the local match from starting.
+#[derive(Component)]
+pub struct LocalLobbyStatusText;
+
+/// Local player who joined the local lobby. Axes act once per push, so
+/// they are remembered as held until let go.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct LocalLobbyPlayer {
+    pub seat: LobbySeat,
+    pub tilt_held: bool,
+    pub move_held: bool,
+}
@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use derive_more::{From, Into};
 use std::any::Any;
 use std::fmt::Debug;
-use std::net::SocketAddrV4;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 pub enum SourceHandle<T: 'static + ?Sized> {
@@ -126,7 +126,15 @@ pub struct ShowFPSSelector;
 pub struct ServerList;
 
 #[derive(Component)]
-pub struct ServerEntry(pub SocketAddrV4);
+pub struct ServerEntry(pub SocketAddr);
+#[derive(Component)]
+pub struct ServerPingText(pub SocketAddr);
+#[derive(Component)]
+pub struct ServerSortSelector;
+#[derive(Component)]
+pub struct ServerFilterSelector;
+#[derive(Component)]
+pub struct JoinRoleSelector;
 #[derive(Component)]
 pub struct RemoveInteractionDisabledTimer(pub Timer);
 
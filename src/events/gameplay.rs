@@ -1,6 +1,8 @@
 ﻿use bevy::ecs::schedule::graph::Direction;
 use bevy::math::CompassOctant;
-use bevy::prelude::{Entity, Event};
+use crate::models::game::area::AreaSide;
+use bevy::prelude::{Entity, Event, Message, Vec2};
+use serde::{Deserialize, Serialize};
 
 #[derive(Event)]
 pub struct GoalScored {
@@ -14,6 +16,42 @@ pub struct BallBounced {
     pub ball: Entity,
 }
 
+/// Authoritative match events. The simulating peer triggers them locally
+/// and the server forwards them to every client, where they are triggered
+/// again.
+#[derive(Event, Message, Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct PointAwarded {
+    pub side: AreaSide,
+    pub score: u32,
+}
+
+#[derive(Event, Message, Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct MatchOver {
+    pub winner: AreaSide,
+}
+
+/// The result has been on screen long enough, the match is torn down and
+/// everyone goes back to the lobby.
+#[derive(Event, Message, Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct MatchEnded;
+
+#[derive(Event, Message, Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct BallServed {
+    pub velocity: Vec2,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HitSurface {
+    Paddle,
+    Wall,
+}
+
+/// The ball bounced off something other than a goal.
+#[derive(Event, Message, Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct BallHit {
+    pub surface: HitSurface,
+}
+
 #[derive(Event, Copy, Clone)]
 pub struct UINavigated {
     pub direction: CompassOctant,
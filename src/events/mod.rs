@@ -1,2 +1,3 @@
 pub mod gameplay;
+pub mod network;
 pub mod widgets;
@@ -0,0 +1,67 @@
+use crate::networking::client::AddressError;
+use crate::networking::discovery::DISCOVERY_PROTOCOL_VERSION;
+use bevy::prelude::Event;
+use std::net::SocketAddr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionFailure {
+    Refused,
+    TimedOut,
+    VersionMismatch { server_version: u16 },
+    Disconnected { reason: Option<String> },
+    /// Kicked, banned or turned away by the room's host.
+    Removed { reason: String },
+    /// The room is password protected, `attempted` when a password was sent.
+    WrongPassword { attempted: bool },
+}
+
+impl ConnectionFailure {
+    pub fn title(&self) -> &'static str {
+        match self {
+            ConnectionFailure::Refused => "Connection Refused",
+            ConnectionFailure::TimedOut => "Connection Timed Out",
+            ConnectionFailure::VersionMismatch { .. } => "Version Mismatch",
+            ConnectionFailure::Disconnected { .. } => "Disconnected",
+            ConnectionFailure::Removed { .. } => "Removed From Room",
+            ConnectionFailure::WrongPassword { .. } => "Password Required",
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            ConnectionFailure::Refused => "The server refused the connection.".to_string(),
+            ConnectionFailure::TimedOut => "The server did not answer in time.".to_string(),
+            ConnectionFailure::VersionMismatch { server_version } => format!(
+                "The server runs protocol version {server_version}, you run version {DISCOVERY_PROTOCOL_VERSION}."
+            ),
+            ConnectionFailure::Disconnected { reason: Some(reason) } => {
+                format!("Lost connection to the server: {reason}")
+            }
+            ConnectionFailure::Disconnected { reason: None } => {
+                "Lost connection to the server.".to_string()
+            }
+            ConnectionFailure::Removed { reason } => format!("{reason}."),
+            ConnectionFailure::WrongPassword { attempted: true } => "The password was not accepted.".to_string(),
+            ConnectionFailure::WrongPassword { attempted: false } => "This room needs a password.".to_string(),
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct ConnectionEstablished {
+    pub address: SocketAddr,
+}
+
+#[derive(Event)]
+pub struct ConnectionFailed {
+    pub address: SocketAddr,
+    pub failure: ConnectionFailure,
+}
+
+/// A direct connect address finished resolving, see
+/// [`resolve_server_address`](crate::networking::client::resolve_server_address).
+#[derive(Event, Debug, Clone)]
+pub struct AddressResolved {
+    pub input: String,
+    pub result: Result<SocketAddr, AddressError>,
+}
@@ -0,0 +1,10 @@
+pub mod bundles;
+pub mod components;
+pub mod events;
+pub mod models;
+pub mod networking;
+pub mod plugins;
+pub mod resources;
+pub mod systems;
+pub mod traits;
+pub mod utils;
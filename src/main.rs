@@ -1,20 +1,10 @@
-mod bundles;
-mod components;
-mod events;
-mod models;
-mod networking;
-mod plugins;
-mod resources;
-mod systems;
-mod traits;
-mod utils;
-
-use crate::networking::shared::GameNetworking;
-use crate::plugins::game_ui::GameUIPlugin;
-use crate::plugins::GameCorePlugin;
-use crate::resources::MenuAction;
-use crate::systems::settings::persistence::load_settings;
-use crate::utils::DEFAULT_FONT;
+use pong_enhanced::networking::conditioner::NetworkConditions;
+use pong_enhanced::networking::shared::GameNetworking;
+use pong_enhanced::plugins::game_ui::GameUIPlugin;
+use pong_enhanced::plugins::GameCorePlugin;
+use pong_enhanced::resources::{client_id_from_args, MenuAction, SpectatorAction};
+use pong_enhanced::systems::settings::persistence::{load_profiles, load_settings};
+use pong_enhanced::utils::DEFAULT_FONT;
 use avian2d::prelude::*;
 use bevy::input_focus::directional_navigation::DirectionalNavigationPlugin;
 use bevy::input_focus::InputDispatchPlugin;
@@ -23,13 +13,20 @@ use bevy::ui_widgets::UiWidgetsPlugins;
 use bevy::window::WindowResolution;
 use bevy_simple_text_input::TextInputPlugin;
 use bevy_tweening::TweeningPlugin;
-use components::*;
 use leafwing_input_manager::plugin::InputManagerPlugin;
 
 fn main() {
     let mut app = App::new();
 
-    let settings = load_settings();
+    let mut settings = load_settings();
+    let conditions = NetworkConditions::from_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        std::process::exit(2);
+    });
+    settings.client_id_override = client_id_from_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        std::process::exit(2);
+    });
     let mut window_resolution: WindowResolution = WindowResolution::default();
 
     if let Some(res) = settings.window_resolution {
@@ -56,6 +53,7 @@ fn main() {
             .disable::<PhysicsTransformPlugin>()
             .disable::<PhysicsInterpolationPlugin>(),
         InputManagerPlugin::<MenuAction>::default(),
+        InputManagerPlugin::<SpectatorAction>::default(),
         UiWidgetsPlugins,
         InputDispatchPlugin,
         TweeningPlugin,
@@ -67,7 +65,9 @@ fn main() {
         GameUIPlugin,
         GameNetworking,
     ))
-    .insert_resource(settings);
+    .insert_resource(settings)
+    .insert_resource(load_profiles())
+    .insert_resource(conditions);
     
     let world = app.world_mut();
 
@@ -1,6 +1,7 @@
 use crate::bundles::widgets::LabelBundle;
 use crate::bundles::{default, Entity, Transform, Vec3};
-use crate::components::ui::{ScoreText, UIOptionString};
+use crate::components::ui::{ScoreText, TeamRosterText, UIOptionString};
+use crate::components::{MatchEntity, TeamTint};
 use crate::models::game::gameplay::PlayerId;
 use crate::utils::{
     FIXED_DIMENSIONS, HALF_HEIGHT, HALF_WALL_THICKNESS, HALF_WIDTH, WALL_THICKNESS,
@@ -21,6 +22,12 @@ pub enum AreaSide {
 }
 
 impl AreaSide {
+    /// Paddles on this side slide along the x axis instead of y.
+    #[inline]
+    pub fn is_horizontal(self) -> bool {
+        matches!(self, AreaSide::Top | AreaSide::Bottom)
+    }
+
     pub fn opposite(self) -> AreaSide {
         match self {
             AreaSide::Left => AreaSide::Right,
@@ -30,7 +37,7 @@ impl AreaSide {
         }
     }
 
-    pub fn spawn_score_text(self, commands: &mut Commands) {
+    pub fn spawn_score_text(self, score: u32, commands: &mut Commands) {
         let position = match self {
             AreaSide::Left => Vec2::new(FIXED_DIMENSIONS.x - (FIXED_DIMENSIONS.x / 3.0), 0.0),
             AreaSide::Right => Vec2::new(FIXED_DIMENSIONS.x / 3.0, 0.0),
@@ -45,8 +52,23 @@ impl AreaSide {
                 top: Val::Px(position.y),
                 ..default()
             },
-            LabelBundle::custom("0", Color::WHITE.into(), 80.0),
+            LabelBundle::custom(&score.to_string(), Color::WHITE.into(), 80.0),
             ScoreText { area_side: self },
+            TeamTint::new(self),
+            MatchEntity,
+        ));
+
+        commands.spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(position.x),
+                top: Val::Px(position.y + 90.0),
+                ..default()
+            },
+            LabelBundle::custom("", Color::WHITE, 24.0),
+            TeamRosterText { area_side: self },
+            TeamTint::new(self),
+            MatchEntity,
         ));
     }
 
@@ -60,7 +82,6 @@ impl AreaSide {
 
 #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
 pub struct TeamInfo {
-    pub current_score: u32,
     pub area_side: AreaSide,
     pub players: Vec<PlayerId>,
 }
@@ -75,6 +96,7 @@ impl TeamInfo {
 pub enum LocalPlayerID {
     Keyboard(u8),
     Gamepad(Entity),
+    Mouse,
 }
 
 #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
@@ -88,12 +110,10 @@ impl AreaShape {
     pub fn default() -> AreaShape {
         TwoSide([
             TeamInfo {
-                current_score: 0,
                 area_side: AreaSide::Left,
                 players: Vec::new(),
             },
             TeamInfo {
-                current_score: 0,
                 area_side: AreaSide::Right,
                 players: Vec::new(),
             },
@@ -101,6 +121,57 @@ impl AreaShape {
     }
 }
 
+#[derive(Clone, Copy, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, Default)]
+pub enum AreaShapeKind {
+    #[default]
+    TwoSide,
+    Triangular,
+    Cuboid,
+}
+
+impl AreaShapeKind {
+    pub fn team_count(self) -> usize {
+        match self {
+            AreaShapeKind::TwoSide => 2,
+            AreaShapeKind::Triangular => 3,
+            AreaShapeKind::Cuboid => 4,
+        }
+    }
+}
+
+impl AreaShapeKind {
+    /// Sides with a goal, in the order teams are listed.
+    pub fn sides(self) -> &'static [AreaSide] {
+        match self {
+            AreaShapeKind::TwoSide => &[AreaSide::Left, AreaSide::Right],
+            AreaShapeKind::Triangular => &[AreaSide::Left, AreaSide::Right, AreaSide::Bottom],
+            AreaShapeKind::Cuboid => &[AreaSide::Left, AreaSide::Right, AreaSide::Top, AreaSide::Bottom],
+        }
+    }
+}
+
+impl UIOptionString for AreaSide {
+    fn push_ui_option_string(&self, string: &mut String) {
+        string.push_str(match self {
+            AreaSide::Left => "Left",
+            AreaSide::Right => "Right",
+            AreaSide::Top => "Top",
+            AreaSide::Bottom => "Bottom",
+        });
+    }
+}
+
+impl UIOptionString for AreaShapeKind {
+    fn push_ui_option_string(&self, string: &mut String) {
+        let s = match self {
+            AreaShapeKind::TwoSide => "Two Side",
+            AreaShapeKind::Triangular => "Triangular",
+            AreaShapeKind::Cuboid => "Cuboid",
+        };
+        string.push_str(s);
+    }
+}
+
 impl UIOptionString for AreaShape {
     fn push_ui_option_string(&self, string: &mut String) {
         let s = match self {
@@ -113,6 +184,28 @@ impl UIOptionString for AreaShape {
 }
 
 impl AreaShape {
+    /// Arena of the given kind with no players on any team yet.
+    pub fn new(kind: AreaShapeKind) -> AreaShape {
+        let team = |i: usize| TeamInfo {
+            area_side: kind.sides()[i],
+            players: Vec::new(),
+        };
+
+        match kind {
+            AreaShapeKind::TwoSide => TwoSide([team(0), team(1)]),
+            AreaShapeKind::Triangular => Triangular([team(0), team(1), team(2)]),
+            AreaShapeKind::Cuboid => Cuboid([team(0), team(1), team(2), team(3)]),
+        }
+    }
+
+    pub fn kind(&self) -> AreaShapeKind {
+        match self {
+            TwoSide(_) => AreaShapeKind::TwoSide,
+            Triangular(_) => AreaShapeKind::Triangular,
+            Cuboid(_) => AreaShapeKind::Cuboid,
+        }
+    }
+
     pub fn get_wall_sides(&self) -> &[AreaSide] {
         match self {
             TwoSide(_) => &[AreaSide::Top, AreaSide::Bottom],
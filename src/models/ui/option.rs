@@ -1,4 +1,5 @@
 use crate::components::ui::{SourceHandle, UIOptionProvider, UIOptionString};
+use crate::resources::TeamPalette;
 use bevy::window::PresentMode;
 use std::fmt::Write;
 
@@ -12,6 +13,37 @@ pub const FPS_LOCK_OPTIONS: SourceHandle<dyn UIOptionProvider> =
 
 pub const FPS_LOCK_OPTIONS_RAW: [u16; 3] = [30, 60, 120];
 
+pub const TOGGLE_OPTIONS: SourceHandle<dyn UIOptionProvider> =
+    SourceHandle::Static(&TOGGLE_OPTIONS_RAW);
+
+pub const TOGGLE_OPTIONS_RAW: [bool; 2] = [false, true];
+
+pub const TEAM_PALETTE_OPTIONS: SourceHandle<dyn UIOptionProvider> =
+    SourceHandle::Static(&TEAM_PALETTE_OPTIONS_RAW);
+
+pub const TEAM_PALETTE_OPTIONS_RAW: [TeamPalette; 3] = [TeamPalette::Standard, TeamPalette::OkabeIto, TeamPalette::TolBright];
+
+impl UIOptionString for bool {
+    #[inline]
+    fn push_ui_option_string(&self, string: &mut String) {
+        string.push_str(if *self { "On" } else { "Off" });
+    }
+}
+
+impl UIOptionString for &'static str {
+    #[inline]
+    fn push_ui_option_string(&self, string: &mut String) {
+        string.push_str(self);
+    }
+}
+
+impl UIOptionString for String {
+    #[inline]
+    fn push_ui_option_string(&self, string: &mut String) {
+        string.push_str(self);
+    }
+}
+
 impl UIOptionString for u16 {
     #[inline]
     fn push_ui_option_string(&self, string: &mut String) {
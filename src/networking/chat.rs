@@ -0,0 +1,164 @@
+use crate::networking::protocol::{peer_display_name, ChatChannel};
+use bevy::prelude::*;
+use lightyear::prelude::server::{ClientOf, Server, ServerMultiMessageSender};
+use lightyear::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+pub const MAX_CHAT_LEN: usize = 200;
+pub const MAX_CHAT_LOG: usize = 50;
+
+/// Messages a client may send back to back before the rate limit kicks in.
+const CHAT_BURST: f32 = 5.0;
+/// One more message is allowed every this many seconds.
+const CHAT_REFILL_SECS: f32 = 2.0;
+
+pub const HOST_CHAT_NAME: &str = "Host";
+
+/// Chat line typed by a client, the server decides whether it is posted.
+#[derive(Message, Serialize, Deserialize, Clone, Debug)]
+pub struct ChatSend {
+    pub text: String,
+}
+
+/// Posted chat line. A missing author marks a system message.
+#[derive(Event, Message, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChatMessage {
+    pub author: Option<String>,
+    pub text: String,
+}
+
+impl ChatMessage {
+    pub fn system(text: impl Into<String>) -> Self {
+        Self {
+            author: None,
+            text: text.into(),
+        }
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct ChatLog {
+    pub lines: VecDeque<ChatMessage>,
+}
+
+/// Token bucket kept on every client link by the server.
+#[derive(Component)]
+pub struct ChatRateLimit {
+    tokens: f32,
+    last_refill: f32,
+}
+
+impl ChatRateLimit {
+    pub fn new(now: f32) -> Self {
+        Self {
+            tokens: CHAT_BURST,
+            last_refill: now,
+        }
+    }
+
+    fn try_take(&mut self, now: f32) -> bool {
+        self.tokens = (self.tokens + (now - self.last_refill) / CHAT_REFILL_SECS).min(CHAT_BURST);
+        self.last_refill = now;
+
+        if self.tokens < 1.0 {
+            return false;
+        }
+
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+/// Strips control characters and caps the length, `None` if nothing is left.
+pub fn sanitize_chat(text: &str) -> Option<String> {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_CHAT_LEN)
+        .collect();
+
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+/// Sends a line typed on this machine. Clients hand it to the server, a
+/// listen server's host posts it directly.
+pub fn send_chat(
+    text: &str,
+    client: Option<&mut MessageSender<ChatSend>>,
+    hosting: bool,
+    commands: &mut Commands,
+) {
+    let Some(text) = sanitize_chat(text) else { return };
+
+    if let Some(sender) = client {
+        sender.send::<ChatChannel>(ChatSend { text });
+    } else if hosting {
+        commands.trigger(ChatMessage {
+            author: Some(HOST_CHAT_NAME.to_owned()),
+            text,
+        });
+    }
+}
+
+pub fn s_receive_chat(
+    mut links: Query<
+        (
+            &RemoteId,
+            &mut MessageReceiver<ChatSend>,
+            &mut MessageSender<ChatMessage>,
+            &mut ChatRateLimit,
+        ),
+        With<ClientOf>,
+    >,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let now = time.elapsed_secs();
+
+    for (remote, mut receiver, mut sender, mut limit) in links.iter_mut() {
+        for message in receiver.receive() {
+            let Some(text) = sanitize_chat(&message.text) else { continue };
+
+            if !limit.try_take(now) {
+                sender.send::<ChatChannel>(ChatMessage::system(
+                    "You are sending messages too quickly.",
+                ));
+                continue;
+            }
+
+            commands.trigger(ChatMessage {
+                author: Some(peer_display_name(remote.0)),
+                text,
+            });
+        }
+    }
+}
+
+/// Every posted line, including the host's own and system messages, goes
+/// out to all clients.
+pub fn t_broadcast_chat(
+    message: On<ChatMessage>,
+    server: Option<Single<&Server>>,
+    mut sender: ServerMultiMessageSender,
+) {
+    let Some(server) = server else { return };
+
+    match &message.author {
+        Some(author) => info!("[chat] {author}: {}", message.text),
+        None => info!("[chat] {}", message.text),
+    }
+
+    if let Err(e) = sender.send::<ChatMessage, ChatChannel>(message.event(), *server, &NetworkTarget::All) {
+        warn!("Could not broadcast chat message: {e:?}");
+    }
+}
+
+pub fn t_log_chat(message: On<ChatMessage>, mut log: ResMut<ChatLog>) {
+    log.lines.push_back(message.event().clone());
+
+    while log.lines.len() > MAX_CHAT_LOG {
+        log.lines.pop_front();
+    }
+}
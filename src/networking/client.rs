@@ -1,38 +1,119 @@
-use std::collections::{HashMap, HashSet};
-use std::hash::{Hash, Hasher};
-use std::io::BufRead;
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 use bevy::prelude::*;
+use bevy::tasks::futures_lite::future;
+use bevy::tasks::{block_on, IoTaskPool, Task};
 use lightyear::link::Link;
+use lightyear::netcode::client::ClientState;
 use lightyear::netcode::{Key, NetcodeClient, NetcodeServer};
-use lightyear::prelude::{Authentication, Client, Connect, LocalAddr, PeerAddr, ReplicationReceiver, UdpIo};
+use lightyear::prelude::{Authentication, Client, Connect, Connected, Connecting, Disconnected, LocalAddr, MessageReceiver, MessageSender, PeerAddr, ReplicationReceiver, UdpIo};
 use lightyear::prelude::client::{ClientPlugins, NetcodeConfig};
 use socket2::{Domain, Protocol, Socket, Type};
-use crate::networking::protocol::{make_reusable_udp_socket, DISCOVERY_ADDR, DISCOVERY_CLIENT_MAGIC, DISCOVERY_ADDR_LOCAL, UNSPECIFIED_ADDR};
+use crate::networking::discovery::{encode_request, peek_response_port, DiscoveryError, DiscoveryResponse, DISCOVERY_PROTOCOL_VERSION};
+use crate::events::gameplay::{BallHit, BallServed, MatchEnded, MatchOver, PointAwarded};
+use crate::networking::chat::ChatMessage;
+use crate::networking::moderation::{receive_removal_notice, RemovedFromRoom};
+use crate::events::network::{AddressResolved, ConnectionEstablished, ConnectionFailed, ConnectionFailure};
+use crate::networking::protocol::{JoinRequest, JoinRole, LobbyChannel, DISCOVERY_ADDR, DISCOVERY_ADDR_LOCAL, DISCOVERY_ADDR_V6, DISCOVERY_PORT, NETWORK_PROTOCOL_ID, UNSPECIFIED_ADDR, UNSPECIFIED_ADDR_V6};
+use crate::components::ui::ServerList;
+use crate::components::PlayerIdentity;
+use crate::resources::Profiles;
 use crate::networking::server::{BroadcastTimer, ServerName};
 
+/// Servers that have not answered for this long are dropped from the browser.
+pub const SERVER_EXPIRY_SECS: f32 = 6.0;
+
+/// Only changes to the set of servers or their advertised info mark this
+/// resource as changed, ping and last seen updates bypass change detection.
 #[derive(Resource, Default, Deref)]
 pub struct DiscoveredServers {
-    pub servers: HashSet<DiscoveredServer>,
+    pub servers: HashMap<SocketAddr, DiscoveredServer>,
 }
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug)]
 pub struct DiscoveredServer {
-    pub address: SocketAddrV4,
-    pub name: String
+    pub address: SocketAddr,
+    pub version: u16,
+    /// `None` when the server speaks an incompatible protocol version.
+    pub info: Option<DiscoveryResponse>,
+    pub ping: Option<Duration>,
+    pub last_seen: Instant,
 }
 
-impl Hash for DiscoveredServer {
+impl DiscoveredServer {
+    #[inline]
+    pub fn is_compatible(&self) -> bool {
+        self.info.is_some()
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.info
+            .as_ref()
+            .is_some_and(|info| info.current_players >= info.max_players)
+    }
+
+    #[inline]
+    pub fn is_locked(&self) -> bool {
+        self.info.as_ref().is_some_and(|info| info.locked)
+    }
+
     #[inline]
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.address.hash(state);
+    pub fn is_closed(&self) -> bool {
+        self.info.as_ref().is_some_and(|info| info.closed)
     }
+
+    #[inline]
+    pub fn is_joinable(&self) -> bool {
+        self.is_compatible() && !self.is_full() && !self.is_closed()
+    }
+
+    /// Full rooms still take spectators, closed ones take nobody.
+    #[inline]
+    pub fn is_joinable_as(&self, role: JoinRole) -> bool {
+        match role {
+            JoinRole::Player => self.is_joinable(),
+            JoinRole::Spectator => self.is_compatible() && !self.is_closed(),
+        }
+    }
+}
+
+pub const CONNECT_TIMEOUT_SECS: f32 = 10.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConnectionPhase {
+    Idle,
+    Connecting,
+    Connected,
+}
+
+/// Marks the client entity spawned by [`connect_to_server`] and tracks how far
+/// the connection got, so a disconnect can be reported appropriately.
+#[derive(Component, Debug)]
+pub struct GameClient {
+    pub address: SocketAddr,
+    pub phase: ConnectionPhase,
+    pub started: Instant,
+    pub role: JoinRole,
+    /// Room password, sent with every [`JoinRequest`].
+    pub password: Option<String>,
+    /// Notice sent by the server right before it kicks this client.
+    pub removal: Option<RemovedFromRoom>,
 }
 
 #[derive(Resource)]
 pub struct ClientDiscoverySocket {
     pub socket: UdpSocket,
+    pub socket_v6: Option<UdpSocket>,
+    pub last_sent: Option<Instant>,
+}
+
+impl ClientDiscoverySocket {
+    pub fn sockets(&self) -> impl Iterator<Item = &UdpSocket> {
+        std::iter::once(&self.socket).chain(self.socket_v6.as_ref())
+    }
 }
 
 pub struct GameClientPlugin;
@@ -42,10 +123,25 @@ impl Plugin for GameClientPlugin {
         app.add_plugins(ClientPlugins::default());
         
         app.add_systems(Update, (
-            lan_discovery_sender,
-            lan_discovery_receiver.run_if(|server: Option<Single<&ServerName>>| server.is_none())
+            lan_discovery_sender.run_if(|list: Option<Single<&ServerList>>| list.is_some()),
+            lan_discovery_receiver.run_if(|server: Option<Single<&ServerName>>| server.is_none()),
+            expire_discovered_servers,
+            probe_connecting_server,
+            u_connection_watchdog,
+            u_address_lookups,
+            receive_server_events::<PointAwarded>,
+            receive_server_events::<MatchOver>,
+            receive_server_events::<MatchEnded>,
+            receive_server_events::<BallServed>,
+            receive_server_events::<BallHit>,
+            receive_server_events::<ChatMessage>,
+            receive_removal_notice,
         ));
 
+        app.add_observer(on_client_connecting)
+            .add_observer(on_client_connected)
+            .add_observer(on_client_disconnected);
+
         app.insert_resource(DiscoveredServers::default());
 
         let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP));
@@ -55,7 +151,19 @@ impl Plugin for GameClientPlugin {
                 _ = socket.set_nonblocking(true);
                 _ = socket.set_broadcast(true);
 
-                app.insert_resource(ClientDiscoverySocket { socket: socket.into() });
+                let socket_v6 = match make_client_socket_v6() {
+                    Ok(socket) => Some(socket),
+                    Err(e) => {
+                        warn!("IPv6 LAN discovery unavailable: {e}");
+                        None
+                    }
+                };
+
+                app.insert_resource(ClientDiscoverySocket {
+                    socket: socket.into(),
+                    socket_v6,
+                    last_sent: None,
+                });
             }
             Err(e) => {
                 error!(
@@ -67,63 +175,241 @@ impl Plugin for GameClientPlugin {
     }
 }
 
+fn make_client_socket_v6() -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+
+    socket.set_only_v6(true)?;
+    socket.set_nonblocking(true)?;
+
+    Ok(socket.into())
+}
+
 pub fn lan_discovery_receiver(
-    socket: Res<ClientDiscoverySocket>,
+    discovery: Res<ClientDiscoverySocket>,
     mut servers: ResMut<DiscoveredServers>,
 ) {
     let mut buf = [0u8; 256];
-    
-    loop {
-        match socket.socket.recv_from(&mut buf) {
-            Ok((len, SocketAddr::V4(addr))) => {
-                
-                info!("Received Discovery response {:?}", str::from_utf8(&buf[..(len - 3)]));
-                
-                let mut read = &buf[..(len - 3)]; // - 3 cause 3 new lines at the end
-
-                let map: HashMap<String, String> = read
-                    .split(|b| *b == b'\n')
-                    .filter_map(|line| {
-                        let mut parts = line.splitn(2, |b| *b == b' ');
-                        Some((
-                            String::from_utf8_lossy(parts.next()?).into_owned(),
-                            String::from_utf8_lossy(parts.next()?).into_owned(),
-                        ))
-                    })
-                    .collect();
-
-                if let Some(name) = map.get("NAME") {
-                    if let Some(addr) = map.get("IP") {
-                        if let Ok(addr) = SocketAddrV4::from_str(addr.as_str()) {
-                            servers.servers.insert(DiscoveredServer {
-                                name: name.clone(),
-                                address: addr,
-                            });
-                        }
-                    }
+
+    for socket in discovery.sockets() {
+        loop {
+            let Ok((len, addr)) = socket.recv_from(&mut buf) else { break };
+
+            let (version, info) = match DiscoveryResponse::decode(&buf[..len]) {
+                Ok(info) => (info.version, Some(info)),
+                Err(DiscoveryError::VersionMismatch(version)) => {
+                    warn!(
+                        "Server at {addr} speaks discovery protocol v{version}, \
+                         expected v{DISCOVERY_PROTOCOL_VERSION}"
+                    );
+                    (version, None)
                 }
-            },
-            _ => break,
+                Err(e) => {
+                    debug!("Ignoring malformed discovery packet from {addr}: {e:?}");
+                    continue;
+                }
+            };
+
+            let now = Instant::now();
+
+            // keeps the scope id of link-local IPv6 senders intact
+            let mut address = addr;
+            if let Some(port) = peek_response_port(&buf[..len]) {
+                address.set_port(port);
+            }
+
+            // a dual-stack server answers on both families, it is listed
+            // once under whichever address was heard first
+            if let Some(info) = &info {
+                let twin = servers
+                    .servers
+                    .values()
+                    .filter(|known| known.address != address)
+                    .find(|known| known.info.as_ref().is_some_and(|known| known.instance == info.instance));
+
+                if let Some(twin) = twin {
+                    address = twin.address;
+                }
+            }
+
+            let changed = match servers.servers.get(&address) {
+                Some(known) => known.version != version || known.info != info,
+                None => true,
+            };
+
+            servers.bypass_change_detection().servers.insert(address, DiscoveredServer {
+                address,
+                version,
+                info,
+                ping: discovery.last_sent.map(|sent| now.duration_since(sent)),
+                last_seen: now,
+            });
+
+            if changed {
+                servers.set_changed();
+            }
         }
     }
 }
 
+pub fn expire_discovered_servers(mut servers: ResMut<DiscoveredServers>) {
+    let before = servers.servers.len();
+
+    servers
+        .bypass_change_detection()
+        .servers
+        .retain(|_, server| server.last_seen.elapsed().as_secs_f32() < SERVER_EXPIRY_SECS);
+
+    if servers.servers.len() != before {
+        servers.set_changed();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressError {
+    Empty,
+    MissingPort,
+    InvalidPort,
+    UnbracketedIpv6,
+    Unresolved,
+}
+
+impl AddressError {
+    pub fn message(self) -> &'static str {
+        match self {
+            AddressError::Empty => "Enter an address",
+            AddressError::MissingPort => "Missing port, use host:port",
+            AddressError::InvalidPort => "Port must be between 1 and 65535",
+            AddressError::UnbracketedIpv6 => "IPv6 addresses must be written as [addr]:port",
+            AddressError::Unresolved => "Could not resolve host",
+        }
+    }
+}
+
+/// An address typed by the player, hostnames still need looking up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsedAddress {
+    Ip(SocketAddr),
+    Host(String, u16),
+}
+
+/// Accepts `ipv4:port`, `[ipv6]:port` and `hostname:port`, without
+/// touching the network.
+pub fn parse_server_address(input: &str) -> Result<ParsedAddress, AddressError> {
+    let input = input.trim();
+
+    if input.is_empty() {
+        return Err(AddressError::Empty);
+    }
+
+    if let Ok(addr) = SocketAddr::from_str(input) {
+        return match addr.port() {
+            0 => Err(AddressError::InvalidPort),
+            _ => Ok(ParsedAddress::Ip(addr)),
+        };
+    }
+
+    let Some((host, port)) = input.rsplit_once(':') else {
+        return Err(AddressError::MissingPort);
+    };
+
+    if host.contains(':') {
+        return Err(AddressError::UnbracketedIpv6);
+    }
+
+    if host.is_empty() {
+        return Err(AddressError::Empty);
+    }
+
+    let port = match port.parse::<u16>() {
+        Ok(0) | Err(_) => return Err(AddressError::InvalidPort),
+        Ok(port) => port,
+    };
+
+    Ok(ParsedAddress::Host(host.to_owned(), port))
+}
+
+/// Hostname lookup running on the IO pool, the system resolver blocks.
+#[derive(Component)]
+pub struct AddressLookup {
+    input: String,
+    task: Task<Result<SocketAddr, AddressError>>,
+}
+
+/// Parses `input` and resolves its host off the main thread. The outcome
+/// is triggered as [`AddressResolved`], right away when there is nothing
+/// to look up.
+pub fn resolve_server_address(input: &str, commands: &mut Commands) {
+    let input = input.trim().to_owned();
+
+    let (host, port) = match parse_server_address(&input) {
+        Ok(ParsedAddress::Host(host, port)) => (host, port),
+        Ok(ParsedAddress::Ip(addr)) => {
+            commands.trigger(AddressResolved { input, result: Ok(addr) });
+            return;
+        }
+        Err(e) => {
+            commands.trigger(AddressResolved { input, result: Err(e) });
+            return;
+        }
+    };
+
+    let task = IoTaskPool::get().spawn(async move {
+        (host.as_str(), port)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or(AddressError::Unresolved)
+    });
+
+    commands.spawn(AddressLookup { input, task });
+}
+
+pub fn u_address_lookups(mut lookups: Query<(Entity, &mut AddressLookup)>, mut commands: Commands) {
+    for (entity, mut lookup) in lookups.iter_mut() {
+        let Some(result) = block_on(future::poll_once(&mut lookup.task)) else { continue };
+
+        commands.entity(entity).despawn();
+        commands.trigger(AddressResolved {
+            input: std::mem::take(&mut lookup.input),
+            result,
+        });
+    }
+}
+
+/// `client_id` should be [`GameSettings::client_id`](crate::resources::GameSettings::client_id),
+/// servers use it to hand a held slot back after a drop.
 pub fn connect_to_server(
-    server_addr: SocketAddrV4,
+    server_addr: SocketAddr,
+    client_id: u64,
+    role: JoinRole,
+    password: Option<String>,
     commands: &mut Commands) {
 
     let auth = Authentication::Manual {
-        server_addr: SocketAddr::V4(server_addr),
-        client_id: 42,
+        server_addr,
+        client_id,
         private_key: Key::default(),
-        protocol_id: 0,
+        protocol_id: NETWORK_PROTOCOL_ID,
+    };
+
+    let local_addr = match server_addr {
+        SocketAddr::V4(_) => UNSPECIFIED_ADDR,
+        SocketAddr::V6(_) => UNSPECIFIED_ADDR_V6,
     };
 
     let client = commands
         .spawn((
+            GameClient {
+                address: server_addr,
+                phase: ConnectionPhase::Idle,
+                started: Instant::now(),
+                role,
+                password,
+                removal: None,
+            },
             Client::default(),
-            LocalAddr(UNSPECIFIED_ADDR),
-            PeerAddr(SocketAddr::V4(server_addr)),
+            LocalAddr(local_addr),
+            PeerAddr(server_addr),
             Link::new(None),
             ReplicationReceiver::default(),
             NetcodeClient::new(auth, NetcodeConfig::default()).unwrap(),
@@ -136,35 +422,212 @@ pub fn connect_to_server(
     })
 }
 
+/// Asks the server for another role, it is applied between matches only.
+pub fn request_role(
+    client: &mut GameClient,
+    sender: &mut MessageSender<JoinRequest>,
+    role: JoinRole,
+    identity: Option<PlayerIdentity>,
+) {
+    client.role = role;
+    sender.send::<LobbyChannel>(JoinRequest {
+        role,
+        identity,
+        password: client.password.clone(),
+    });
+}
+
+pub fn disconnect_from_server(commands: &mut Commands, clients: &Query<Entity, With<GameClient>>) {
+    for client in clients.iter() {
+        commands.entity(client).despawn();
+    }
+}
+
+fn on_client_connecting(add: On<Add, Connecting>, mut clients: Query<&mut GameClient>) {
+    if let Ok(mut client) = clients.get_mut(add.entity) {
+        client.phase = ConnectionPhase::Connecting;
+        client.started = Instant::now();
+    }
+}
+
+fn on_client_connected(
+    add: On<Add, Connected>,
+    mut clients: Query<(&mut GameClient, Option<&mut MessageSender<JoinRequest>>)>,
+    profiles: Option<Res<Profiles>>,
+    mut commands: Commands,
+) {
+    if let Ok((mut client, sender)) = clients.get_mut(add.entity) {
+        info!("Connected to {} as {:?}", client.address, client.role);
+
+        if let Some(mut sender) = sender {
+            sender.send::<LobbyChannel>(JoinRequest {
+                role: client.role,
+                identity: profiles.and_then(|profiles| profiles.online_identity()),
+                password: client.password.clone(),
+            });
+        }
+
+        client.phase = ConnectionPhase::Connected;
+        commands.trigger(ConnectionEstablished {
+            address: client.address,
+        });
+    }
+}
+
+fn on_client_disconnected(
+    insert: On<Insert, Disconnected>,
+    clients: Query<(&GameClient, &Disconnected, Option<&NetcodeClient>)>,
+    mut commands: Commands,
+) {
+    let Ok((client, disconnected, netcode)) = clients.get(insert.entity) else { return };
+
+    let failure = match client.phase {
+        // the client starts out disconnected before it ever tried to connect
+        ConnectionPhase::Idle => return,
+        ConnectionPhase::Connecting => match netcode.map(|netcode| netcode.state()) {
+            Some(
+                ClientState::ConnectionRequestTimedOut
+                | ClientState::ChallengeResponseTimedOut
+                | ClientState::ConnectionTimedOut
+                | ClientState::ConnectTokenExpired,
+            ) => ConnectionFailure::TimedOut,
+            _ => ConnectionFailure::Refused,
+        },
+        ConnectionPhase::Connected => match &client.removal {
+            Some(notice) if notice.wrong_password => ConnectionFailure::WrongPassword {
+                attempted: client.password.is_some(),
+            },
+            Some(notice) => ConnectionFailure::Removed { reason: notice.reason.clone() },
+            None => ConnectionFailure::Disconnected {
+                reason: disconnected.reason.clone(),
+            },
+        },
+    };
+
+    warn!("Connection to {} ended: {:?}", client.address, failure);
+
+    fail_connection(&mut commands, insert.entity, client.address, failure);
+}
+
+fn fail_connection(
+    commands: &mut Commands,
+    client: Entity,
+    address: SocketAddr,
+    failure: ConnectionFailure,
+) {
+    commands.entity(client).despawn();
+    commands.trigger(ConnectionFailed { address, failure });
+}
+
+/// Netcode drops handshakes with a foreign protocol id silently, so the
+/// discovery probe sent on connect is what tells a version mismatch apart
+/// from a plain timeout.
+pub fn u_connection_watchdog(
+    clients: Query<(Entity, &GameClient)>,
+    servers: Res<DiscoveredServers>,
+    mut commands: Commands,
+) {
+    for (entity, client) in clients.iter() {
+        if client.phase == ConnectionPhase::Connected {
+            continue;
+        }
+
+        let mismatch = servers
+            .servers
+            .values()
+            .find(|server| !server.is_compatible() && server.address == client.address);
+
+        let failure = if let Some(server) = mismatch {
+            ConnectionFailure::VersionMismatch { server_version: server.version }
+        } else if client.started.elapsed().as_secs_f32() > CONNECT_TIMEOUT_SECS {
+            ConnectionFailure::TimedOut
+        } else {
+            continue;
+        };
+
+        fail_connection(&mut commands, entity, client.address, failure);
+    }
+}
+
+pub fn probe_connecting_server(
+    clients: Query<&GameClient, Added<GameClient>>,
+    socket: Option<ResMut<ClientDiscoverySocket>>,
+) {
+    let Some(mut discovery) = socket else { return };
+
+    for client in clients.iter() {
+        let probe = SocketAddr::new(client.address.ip(), DISCOVERY_PORT);
+        let socket = match probe {
+            SocketAddr::V4(_) => Some(&discovery.socket),
+            SocketAddr::V6(_) => discovery.socket_v6.as_ref(),
+        };
+
+        if let Some(socket) = socket {
+            if let Err(e) = socket.send_to(&encode_request(), probe) {
+                debug!("Could not probe {probe} for its protocol version: {e}");
+            }
+        }
+
+        discovery.last_sent = Some(Instant::now());
+    }
+}
+
 pub fn lan_discovery_sender(
     mut timer: ResMut<BroadcastTimer>,
-    socket: Option<Res<ClientDiscoverySocket>>,
+    socket: Option<ResMut<ClientDiscoverySocket>>,
+    opened: Query<(), Added<ServerList>>,
     time: Res<Time>,
 ) {
-    let Some(socket) = socket else { return };
+    let Some(mut socket) = socket else { return };
 
-    if !timer.0.tick(time.delta()).just_finished() {
+    if !opened.is_empty() {
+        timer.0.reset();
+    } else if !timer.0.tick(time.delta()).just_finished() {
         return;
     }
-    
-    send_discovery_message(&socket);
+
+    send_discovery_message(&mut socket);
 }
 
 #[inline]
-pub fn send_discovery_message(disc_soc: &ClientDiscoverySocket) {
-    if let Err(e) = disc_soc.socket.send_to(DISCOVERY_CLIENT_MAGIC, DISCOVERY_ADDR) {
+pub fn send_discovery_message(disc_soc: &mut ClientDiscoverySocket) {
+    disc_soc.last_sent = Some(Instant::now());
+    let request = encode_request();
+
+    if let Err(e) = disc_soc.socket.send_to(&request, DISCOVERY_ADDR) {
         send_discovery_message_local(disc_soc);
     } else {
         info!("Sent discovery broadcast");
     }
+
+    if let Some(socket_v6) = &disc_soc.socket_v6 {
+        if let Err(e) = socket_v6.send_to(&request, DISCOVERY_ADDR_V6) {
+            debug!("Failed to send IPv6 discovery multicast: {e}");
+        } else {
+            info!("Sent IPv6 discovery multicast");
+        }
+    }
 }
 
 #[inline]
 pub fn send_discovery_message_local(disc_soc: &ClientDiscoverySocket){
     info!("Network unavailable sending discovery message to loopback");
-    if let Err(e) = disc_soc.socket.send_to(DISCOVERY_CLIENT_MAGIC, DISCOVERY_ADDR_LOCAL) {
+    if let Err(e) = disc_soc.socket.send_to(&encode_request(), DISCOVERY_ADDR_LOCAL) {
         warn!("Failed to send discovery broadcast: {e}");
     } else {
         info!("Sent discovery broadcast");
     }
-}
\ No newline at end of file
+}
+
+/// Replays events sent by the server as local triggers, the same way the
+/// server raises them.
+pub fn receive_server_events<M: Event + Message>(
+    mut receivers: Query<&mut MessageReceiver<M>, With<GameClient>>,
+    mut commands: Commands,
+) {
+    for mut receiver in receivers.iter_mut() {
+        for message in receiver.receive() {
+            commands.trigger(message);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Debug link conditioner for testing netcode on a single machine, where
+//! loopback never delays or drops anything.
+//!
+//! `PongEnhanced --net-latency 80 --net-jitter 15 --net-loss 3 --net-dup 1`
+
+use bevy::prelude::*;
+use lightyear::link::Link;
+use lightyear::prelude::{LinkConditionerConfig, LinkSystems, RecvLinkConditioner};
+use std::time::Duration;
+
+pub const CONDITIONER_USAGE: &str = "\
+  --net-latency <MS>     Simulated one way latency on every link [default: 0]
+  --net-jitter <MS>      Random extra delay of up to this much [default: 0]
+  --net-loss <PERCENT>   Share of packets dropped [default: 0]
+  --net-dup <PERCENT>    Share of packets delivered twice [default: 0]";
+
+/// Applied to what every link receives, so a client connected to a listen
+/// server on the same box sees the round trip affected in both directions.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetworkConditions {
+    pub latency_ms: u32,
+    pub jitter_ms: u32,
+    /// Between 0 and 1.
+    pub loss: f32,
+    /// Between 0 and 1.
+    pub duplication: f32,
+}
+
+impl NetworkConditions {
+    pub fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Reads the `--net-*` flags out of the command line, anything else is
+    /// left to the caller.
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut conditions = Self::default();
+
+        while let Some(flag) = args.next() {
+            if !flag.starts_with("--net-") {
+                continue;
+            }
+
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{flag} expects a value"))?;
+
+            conditions.parse_flag(&flag, &value)?;
+        }
+
+        Ok(conditions)
+    }
+
+    /// Returns `false` for flags that are not about network conditions.
+    pub fn parse_flag(&mut self, flag: &str, value: &str) -> Result<bool, String> {
+        match flag {
+            "--net-latency" => self.latency_ms = parse_millis(flag, value)?,
+            "--net-jitter" => self.jitter_ms = parse_millis(flag, value)?,
+            "--net-loss" => self.loss = parse_percent(flag, value)?,
+            "--net-dup" => self.duplication = parse_percent(flag, value)?,
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn recv_conditioner(&self) -> Option<RecvLinkConditioner> {
+        if self.latency_ms == 0 && self.jitter_ms == 0 && self.loss == 0.0 {
+            return None;
+        }
+
+        Some(RecvLinkConditioner::new(LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(self.latency_ms as u64),
+            incoming_jitter: Duration::from_millis(self.jitter_ms as u64),
+            incoming_loss: self.loss,
+        }))
+    }
+}
+
+fn parse_millis(flag: &str, value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{flag} expects milliseconds, got \"{value}\""))
+}
+
+fn parse_percent(flag: &str, value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(percent / 100.0),
+        _ => Err(format!("{flag} expects a percentage between 0 and 100, got \"{value}\"")),
+    }
+}
+
+pub struct NetworkConditionerPlugin;
+
+impl Plugin for NetworkConditionerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkConditions>()
+            .add_systems(Startup, log_network_conditions)
+            .add_systems(PreUpdate, s_duplicate_packets.after(LinkSystems::Receive))
+            .add_observer(t_condition_link);
+    }
+}
+
+fn log_network_conditions(conditions: Res<NetworkConditions>) {
+    if conditions.is_active() {
+        warn!(
+            "Simulating {}ms latency, {}ms jitter, {:.1}% loss and {:.1}% duplication on every link",
+            conditions.latency_ms,
+            conditions.jitter_ms,
+            conditions.loss * 100.0,
+            conditions.duplication * 100.0,
+        );
+    }
+}
+
+/// Client links and the server's per-client links alike get the conditioner
+/// as soon as they are created, before anything went through them.
+fn t_condition_link(
+    added: On<Add, Link>,
+    conditions: Res<NetworkConditions>,
+    mut commands: Commands,
+) {
+    if let Some(conditioner) = conditions.recv_conditioner() {
+        commands.entity(added.entity).insert(Link::new(Some(conditioner)));
+    }
+}
+
+/// Lightyear's conditioner can delay and drop but never duplicates, so
+/// packets that made it through are queued a second time here.
+fn s_duplicate_packets(
+    mut links: Query<&mut Link>,
+    conditions: Res<NetworkConditions>,
+    mut rng: Local<u64>,
+) {
+    if conditions.duplication <= 0.0 {
+        return;
+    }
+
+    for mut link in links.iter_mut() {
+        if link.recv.is_empty() {
+            continue;
+        }
+
+        let received: Vec<_> = link.recv.drain().collect();
+
+        for payload in received {
+            if next_unit(&mut rng) < conditions.duplication {
+                link.recv.push_raw(payload.clone());
+            }
+            link.recv.push_raw(payload);
+        }
+    }
+}
+
+/// Xorshift, good enough for deciding which packets to duplicate.
+fn next_unit(state: &mut u64) -> f32 {
+    if *state == 0 {
+        *state = 0x2545_F491_4F6C_DD1D;
+    }
+
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+
+    (*state >> 40) as f32 / (1u64 << 24) as f32
+}
@@ -0,0 +1,494 @@
+//! LAN discovery packets. All integers are big endian:
+//!
+//! `magic[4] kind:u8 version:u16` followed, for responses, by
+//! `instance:u64 port:u16 current:u8 max:u8 spectators:u8 mode:u8 shape:u8 flags:u8 name_len:u8 name[name_len]`.
+
+use crate::models::game::area::AreaShapeKind;
+use crate::models::game::gameplay::GameMode;
+
+pub const DISCOVERY_MAGIC: &[u8; 4] = b"PNGE";
+pub const DISCOVERY_PROTOCOL_VERSION: u16 = 3;
+pub const MAX_SERVER_NAME_LEN: usize = 64;
+
+const KIND_REQUEST: u8 = 1;
+const KIND_RESPONSE: u8 = 2;
+const HEADER_LEN: usize = DISCOVERY_MAGIC.len() + 1 + 2;
+
+const FLAG_LOCKED: u8 = 1 << 0;
+const FLAG_CLOSED: u8 = 1 << 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscoveryError {
+    TooShort,
+    BadMagic,
+    UnexpectedKind(u8),
+    VersionMismatch(u16),
+    InvalidGameMode(u8),
+    InvalidAreaShape(u8),
+    InvalidName,
+    TrailingBytes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveryResponse {
+    pub version: u16,
+    /// Random for each run of a server. A dual-stack server answers on
+    /// IPv4 and IPv6 alike, this tells the answers apart from two servers.
+    pub instance: u64,
+    pub name: String,
+    pub port: u16,
+    pub current_players: u8,
+    pub max_players: u8,
+    pub spectators: u8,
+    pub game_mode: GameMode,
+    pub area_shape: AreaShapeKind,
+    /// Password protected.
+    pub locked: bool,
+    /// The host stopped taking new players.
+    pub closed: bool,
+}
+
+pub fn encode_request() -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_LEN);
+    write_header(&mut buf, KIND_REQUEST, DISCOVERY_PROTOCOL_VERSION);
+    buf
+}
+
+/// Returns the version the requesting client speaks.
+pub fn decode_request(packet: &[u8]) -> Result<u16, DiscoveryError> {
+    let mut reader = PacketReader::new(packet);
+    let version = read_header(&mut reader, KIND_REQUEST)?;
+    reader.finish()?;
+
+    Ok(version)
+}
+
+impl DiscoveryResponse {
+    pub fn encode(&self) -> Vec<u8> {
+        let name = truncate_name(&self.name);
+        let mut buf = Vec::with_capacity(HEADER_LEN + 17 + name.len());
+
+        write_header(&mut buf, KIND_RESPONSE, self.version);
+        buf.extend_from_slice(&self.instance.to_be_bytes());
+        buf.extend_from_slice(&self.port.to_be_bytes());
+        buf.push(self.current_players);
+        buf.push(self.max_players);
+        buf.push(self.spectators);
+        buf.push(game_mode_to_wire(self.game_mode));
+        buf.push(area_shape_to_wire(self.area_shape));
+        let mut flags = 0;
+        if self.locked {
+            flags |= FLAG_LOCKED;
+        }
+        if self.closed {
+            flags |= FLAG_CLOSED;
+        }
+        buf.push(flags);
+        buf.push(name.len() as u8);
+        buf.extend_from_slice(name.as_bytes());
+
+        buf
+    }
+
+    /// Servers speaking another version are reported as
+    /// [`DiscoveryError::VersionMismatch`] without reading their body,
+    /// since its layout is not known.
+    pub fn decode(packet: &[u8]) -> Result<Self, DiscoveryError> {
+        let mut reader = PacketReader::new(packet);
+        let version = read_header(&mut reader, KIND_RESPONSE)?;
+
+        if version != DISCOVERY_PROTOCOL_VERSION {
+            return Err(DiscoveryError::VersionMismatch(version));
+        }
+
+        let instance = reader.u64()?;
+        let port = reader.u16()?;
+        let current_players = reader.u8()?;
+        let max_players = reader.u8()?;
+        let spectators = reader.u8()?;
+        let game_mode = game_mode_from_wire(reader.u8()?)?;
+        let area_shape = area_shape_from_wire(reader.u8()?)?;
+        let flags = reader.u8()?;
+        let name_len = reader.u8()? as usize;
+
+        if name_len > MAX_SERVER_NAME_LEN {
+            return Err(DiscoveryError::InvalidName);
+        }
+
+        let name = std::str::from_utf8(reader.bytes(name_len)?)
+            .map_err(|_| DiscoveryError::InvalidName)?
+            .to_owned();
+
+        reader.finish()?;
+
+        Ok(Self {
+            version,
+            instance,
+            name,
+            port,
+            current_players,
+            max_players,
+            spectators,
+            game_mode,
+            area_shape,
+            locked: flags & FLAG_LOCKED != 0,
+            closed: flags & FLAG_CLOSED != 0,
+        })
+    }
+}
+
+/// Game port of a response, whatever its version. Every version from 3 on
+/// starts its body with `instance` and `port`, so a server speaking another
+/// version can still be matched against the address a client dials.
+pub fn peek_response_port(packet: &[u8]) -> Option<u16> {
+    let mut reader = PacketReader::new(packet);
+    let version = read_header(&mut reader, KIND_RESPONSE).ok()?;
+
+    if version < 3 {
+        return None;
+    }
+
+    reader.u64().ok()?;
+    reader.u16().ok()
+}
+
+fn write_header(buf: &mut Vec<u8>, kind: u8, version: u16) {
+    buf.extend_from_slice(DISCOVERY_MAGIC);
+    buf.push(kind);
+    buf.extend_from_slice(&version.to_be_bytes());
+}
+
+fn read_header(reader: &mut PacketReader, expected_kind: u8) -> Result<u16, DiscoveryError> {
+    if reader.bytes(DISCOVERY_MAGIC.len())? != DISCOVERY_MAGIC {
+        return Err(DiscoveryError::BadMagic);
+    }
+
+    let kind = reader.u8()?;
+    if kind != expected_kind {
+        return Err(DiscoveryError::UnexpectedKind(kind));
+    }
+
+    reader.u16()
+}
+
+fn truncate_name(name: &str) -> &str {
+    if name.len() <= MAX_SERVER_NAME_LEN {
+        return name;
+    }
+
+    let mut end = MAX_SERVER_NAME_LEN;
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    &name[..end]
+}
+
+fn game_mode_to_wire(mode: GameMode) -> u8 {
+    match mode {
+        GameMode::Classic => 0,
+        GameMode::UpsideDown => 1,
+        GameMode::Modern => 2,
+        GameMode::Blackout => 3,
+        GameMode::Twisted => 4,
+    }
+}
+
+fn game_mode_from_wire(value: u8) -> Result<GameMode, DiscoveryError> {
+    match value {
+        0 => Ok(GameMode::Classic),
+        1 => Ok(GameMode::UpsideDown),
+        2 => Ok(GameMode::Modern),
+        3 => Ok(GameMode::Blackout),
+        4 => Ok(GameMode::Twisted),
+        _ => Err(DiscoveryError::InvalidGameMode(value)),
+    }
+}
+
+fn area_shape_to_wire(shape: AreaShapeKind) -> u8 {
+    match shape {
+        AreaShapeKind::TwoSide => 0,
+        AreaShapeKind::Triangular => 1,
+        AreaShapeKind::Cuboid => 2,
+    }
+}
+
+fn area_shape_from_wire(value: u8) -> Result<AreaShapeKind, DiscoveryError> {
+    match value {
+        0 => Ok(AreaShapeKind::TwoSide),
+        1 => Ok(AreaShapeKind::Triangular),
+        2 => Ok(AreaShapeKind::Cuboid),
+        _ => Err(DiscoveryError::InvalidAreaShape(value)),
+    }
+}
+
+struct PacketReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> PacketReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], DiscoveryError> {
+        if self.data.len() < len {
+            return Err(DiscoveryError::TooShort);
+        }
+
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, DiscoveryError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DiscoveryError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, DiscoveryError> {
+        let bytes = self.bytes(8)?;
+        Ok(u64::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn finish(&self) -> Result<(), DiscoveryError> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(DiscoveryError::TrailingBytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> DiscoveryResponse {
+        DiscoveryResponse {
+            version: DISCOVERY_PROTOCOL_VERSION,
+            instance: 0x0123_4567_89AB_CDEF,
+            name: "Living room".to_owned(),
+            port: 5888,
+            current_players: 3,
+            max_players: 4,
+            spectators: 2,
+            game_mode: GameMode::Twisted,
+            area_shape: AreaShapeKind::Cuboid,
+            locked: true,
+            closed: false,
+        }
+    }
+
+    /// Offset of `port` in an encoded response, the fields after it are one byte each.
+    const PORT_AT: usize = HEADER_LEN + 8;
+    const MODE_AT: usize = PORT_AT + 5;
+    const SHAPE_AT: usize = PORT_AT + 6;
+    const NAME_LEN_AT: usize = PORT_AT + 8;
+
+    #[test]
+    fn request_round_trips() {
+        assert_eq!(decode_request(&encode_request()), Ok(DISCOVERY_PROTOCOL_VERSION));
+    }
+
+    #[test]
+    fn response_round_trips() {
+        let response = response();
+        assert_eq!(DiscoveryResponse::decode(&response.encode()), Ok(response));
+    }
+
+    #[test]
+    fn response_round_trips_every_mode_shape_and_flag() {
+        let modes = [GameMode::Classic, GameMode::UpsideDown, GameMode::Modern, GameMode::Blackout, GameMode::Twisted];
+        let shapes = [AreaShapeKind::TwoSide, AreaShapeKind::Triangular, AreaShapeKind::Cuboid];
+
+        for game_mode in modes {
+            for area_shape in shapes {
+                for (locked, closed) in [(false, false), (true, false), (false, true), (true, true)] {
+                    let response = DiscoveryResponse {
+                        game_mode,
+                        area_shape,
+                        locked,
+                        closed,
+                        name: String::new(),
+                        ..response()
+                    };
+
+                    assert_eq!(DiscoveryResponse::decode(&response.encode()), Ok(response));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn long_names_are_cut_on_a_char_boundary() {
+        let response = DiscoveryResponse {
+            name: "é".repeat(MAX_SERVER_NAME_LEN),
+            ..response()
+        };
+
+        let decoded = DiscoveryResponse::decode(&response.encode()).unwrap();
+        assert!(decoded.name.len() <= MAX_SERVER_NAME_LEN);
+        assert!(response.name.starts_with(&decoded.name));
+        assert_eq!(decoded.name.chars().count(), MAX_SERVER_NAME_LEN / 2);
+    }
+
+    #[test]
+    fn every_truncated_request_is_too_short() {
+        let packet = encode_request();
+
+        for len in 0..packet.len() {
+            assert_eq!(decode_request(&packet[..len]), Err(DiscoveryError::TooShort), "prefix of {len} bytes");
+        }
+    }
+
+    #[test]
+    fn every_truncated_response_is_too_short() {
+        let packet = response().encode();
+
+        for len in 0..packet.len() {
+            assert_eq!(DiscoveryResponse::decode(&packet[..len]), Err(DiscoveryError::TooShort), "prefix of {len} bytes");
+        }
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        let mut packet = response().encode();
+        packet[0] = b'X';
+        assert_eq!(DiscoveryResponse::decode(&packet), Err(DiscoveryError::BadMagic));
+
+        let mut packet = encode_request();
+        packet[3] = 0;
+        assert_eq!(decode_request(&packet), Err(DiscoveryError::BadMagic));
+    }
+
+    #[test]
+    fn wrong_kind_is_rejected() {
+        assert_eq!(
+            DiscoveryResponse::decode(&encode_request()),
+            Err(DiscoveryError::UnexpectedKind(KIND_REQUEST)),
+        );
+        assert_eq!(
+            decode_request(&response().encode()),
+            Err(DiscoveryError::UnexpectedKind(KIND_RESPONSE)),
+        );
+    }
+
+    #[test]
+    fn other_versions_are_reported_without_reading_the_body() {
+        let other = DISCOVERY_PROTOCOL_VERSION + 1;
+        let mut packet = Vec::new();
+        write_header(&mut packet, KIND_RESPONSE, other);
+        packet.extend_from_slice(b"some future layout");
+
+        assert_eq!(DiscoveryResponse::decode(&packet), Err(DiscoveryError::VersionMismatch(other)));
+    }
+
+    #[test]
+    fn port_is_peeked_from_any_later_version() {
+        let mut packet = response().encode();
+        assert_eq!(peek_response_port(&packet), Some(5888));
+
+        packet[DISCOVERY_MAGIC.len() + 1..HEADER_LEN].copy_from_slice(&(DISCOVERY_PROTOCOL_VERSION + 1).to_be_bytes());
+        packet.truncate(PORT_AT + 2);
+        assert_eq!(peek_response_port(&packet), Some(5888));
+        assert_eq!(peek_response_port(&packet[..PORT_AT + 1]), None);
+
+        // version 2 put the port elsewhere
+        packet[DISCOVERY_MAGIC.len() + 1..HEADER_LEN].copy_from_slice(&2u16.to_be_bytes());
+        assert_eq!(peek_response_port(&packet), None);
+    }
+
+    #[test]
+    fn requests_of_other_versions_still_decode() {
+        let mut packet = Vec::new();
+        write_header(&mut packet, KIND_REQUEST, 1);
+
+        assert_eq!(decode_request(&packet), Ok(1));
+    }
+
+    #[test]
+    fn invalid_mode_is_rejected() {
+        let mut packet = response().encode();
+        packet[MODE_AT] = 200;
+        assert_eq!(DiscoveryResponse::decode(&packet), Err(DiscoveryError::InvalidGameMode(200)));
+    }
+
+    #[test]
+    fn invalid_shape_is_rejected() {
+        let mut packet = response().encode();
+        packet[SHAPE_AT] = 3;
+        assert_eq!(DiscoveryResponse::decode(&packet), Err(DiscoveryError::InvalidAreaShape(3)));
+    }
+
+    #[test]
+    fn invalid_utf8_name_is_rejected() {
+        let mut packet = response().encode();
+        let last = packet.len() - 1;
+        packet[last] = 0xFF;
+        assert_eq!(DiscoveryResponse::decode(&packet), Err(DiscoveryError::InvalidName));
+    }
+
+    #[test]
+    fn oversized_name_len_is_rejected() {
+        let mut packet = response().encode();
+        packet.truncate(NAME_LEN_AT);
+        packet.push(MAX_SERVER_NAME_LEN as u8 + 1);
+        packet.extend(std::iter::repeat_n(b'a', MAX_SERVER_NAME_LEN + 1));
+
+        assert_eq!(DiscoveryResponse::decode(&packet), Err(DiscoveryError::InvalidName));
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        let mut packet = response().encode();
+        packet.push(0);
+        assert_eq!(DiscoveryResponse::decode(&packet), Err(DiscoveryError::TrailingBytes));
+
+        let mut packet = encode_request();
+        packet.push(0);
+        assert_eq!(decode_request(&packet), Err(DiscoveryError::TrailingBytes));
+    }
+
+    /// Random and mutated packets must only ever be rejected, never panic,
+    /// and whatever does decode has to survive another round trip.
+    #[test]
+    fn fuzzed_packets_never_panic() {
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let valid = response().encode();
+
+        for _ in 0..20_000 {
+            let mut packet = if next() % 2 == 0 {
+                let len = (next() % 96) as usize;
+                (0..len).map(|_| next() as u8).collect()
+            } else {
+                valid.clone()
+            };
+
+            for _ in 0..=next() % 4 {
+                if packet.is_empty() {
+                    break;
+                }
+                let at = (next() as usize) % packet.len();
+                packet[at] = next() as u8;
+            }
+
+            let _ = decode_request(&packet);
+
+            if let Ok(decoded) = DiscoveryResponse::decode(&packet) {
+                assert_eq!(DiscoveryResponse::decode(&decoded.encode()), Ok(decoded));
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use crate::bundles::area::AreaBundle;
+use crate::components::MatchEntity;
+use crate::models::game::area::{AreaShape, AreaShapeKind, AreaSide, LocalPlayerID};
+use crate::models::game::gameplay::PlayerId;
+use crate::networking::chat::ChatMessage;
+use crate::networking::protocol::{LobbyChannel, LobbyConfig, RemotePlayerId};
+use crate::resources::{GameModeConfig, OnlineGameConfig};
+use bevy::prelude::*;
+use lightyear::prelude::server::{ClientOf, Server};
+use lightyear::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A player's team and ready state before a match, kept on every seated
+/// player and on the listen server's host.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct LobbySeat {
+    pub side: Option<AreaSide>,
+    pub ready: bool,
+}
+
+/// Seat of the listen server's host, who plays locally and has no link.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HostSeat;
+
+/// The listen server's host steers their paddle as the first keyboard player.
+pub const HOST_PLAYER_ID: PlayerId = PlayerId::Local(LocalPlayerID::Keyboard(1));
+
+#[derive(Message, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeatRequest {
+    /// Moves to the next side of the arena, which also clears ready.
+    CycleSide,
+    ToggleReady,
+}
+
+impl LobbySeat {
+    pub fn apply(&mut self, request: SeatRequest, kind: AreaShapeKind) {
+        match request {
+            SeatRequest::CycleSide => self.step_side(true, kind),
+            // nobody can be ready without a team to play for
+            SeatRequest::ToggleReady => self.ready = !self.ready && self.side.is_some(),
+        }
+    }
+
+    /// Moves to the next or previous side of the arena, which also clears
+    /// ready. Without a side yet both start at the first one.
+    pub fn step_side(&mut self, forward: bool, kind: AreaShapeKind) {
+        let sides = kind.sides();
+        let next = match self.side.and_then(|side| sides.iter().position(|s| *s == side)) {
+            Some(i) if forward => sides[(i + 1) % sides.len()],
+            Some(i) => sides[(i + sides.len() - 1) % sides.len()],
+            None => sides[0],
+        };
+
+        self.side = Some(next);
+        self.ready = false;
+    }
+}
+
+/// What keeps the host from starting, `None` once everyone is ready and
+/// every side of the arena has a player with no team two players ahead.
+pub fn lobby_start_blocker<'a>(
+    seats: impl IntoIterator<Item = &'a LobbySeat>,
+    kind: AreaShapeKind,
+) -> Option<&'static str> {
+    let sides = kind.sides();
+    let mut team_sizes = vec![0usize; sides.len()];
+    let mut any = false;
+
+    for seat in seats {
+        any = true;
+
+        let Some(side) = seat.side else { return Some("Everyone needs to pick a team") };
+        if !seat.ready {
+            return Some("Waiting for everyone to be ready");
+        }
+
+        match sides.iter().position(|s| *s == side) {
+            Some(i) => team_sizes[i] += 1,
+            None => return Some("Everyone needs to pick a team"),
+        }
+    }
+
+    if !any {
+        return Some("Waiting for players");
+    }
+
+    let smallest = team_sizes.iter().copied().min().unwrap_or(0);
+    let largest = team_sizes.iter().copied().max().unwrap_or(0);
+
+    if smallest == 0 {
+        Some("Every side needs a player")
+    } else if largest - smallest > 1 {
+        Some("Teams are uneven")
+    } else {
+        None
+    }
+}
+
+/// Sends a seat change typed on this machine. Clients ask the server, a
+/// listen server's host changes its own seat directly.
+pub fn request_seat(
+    request: SeatRequest,
+    client: Option<&mut MessageSender<SeatRequest>>,
+    host_seat: Option<&mut LobbySeat>,
+    kind: AreaShapeKind,
+) {
+    if let Some(sender) = client {
+        sender.send::<LobbyChannel>(request);
+    } else if let Some(seat) = host_seat {
+        seat.apply(request, kind);
+    }
+}
+
+/// Spreads everyone evenly over the arena's sides. Players are dealt out in
+/// their current team order, so most of them stay where they were, and
+/// anyone who moves has to ready up again.
+pub fn auto_balance<'a>(seats: impl IntoIterator<Item = Mut<'a, LobbySeat>>, kind: AreaShapeKind) {
+    let sides = kind.sides();
+    let mut seats: Vec<_> = seats.into_iter().collect();
+
+    seats.sort_by_key(|seat| {
+        seat.side
+            .and_then(|side| sides.iter().position(|s| *s == side))
+            .unwrap_or(usize::MAX)
+    });
+
+    for (i, seat) in seats.iter_mut().enumerate() {
+        let side = Some(sides[i % sides.len()]);
+
+        if seat.side != side {
+            seat.side = side;
+            seat.ready = false;
+        }
+    }
+}
+
+pub fn s_handle_seat_requests(
+    mut links: Query<(&RemoteId, &mut MessageReceiver<SeatRequest>), With<ClientOf>>,
+    mut seats: Query<(&RemotePlayerId, &mut LobbySeat)>,
+    lobby: Option<Single<&LobbyConfig>>,
+    match_entities: Query<(), With<MatchEntity>>,
+) {
+    let kind = lobby.map_or(AreaShapeKind::default(), |lobby| lobby.area_shape);
+    let in_match = !match_entities.is_empty();
+
+    for (remote, mut receiver) in links.iter_mut() {
+        for request in receiver.receive() {
+            if in_match {
+                continue;
+            }
+
+            if let Some((_, mut seat)) = seats.iter_mut().find(|(id, _)| id.0 == remote.0) {
+                seat.apply(request, kind);
+            }
+        }
+    }
+}
+
+/// Any change to the lobby settings asks everyone to ready up again, and a
+/// new arena drops teams it has no side for.
+pub fn s_reset_seats_on_lobby_change(
+    lobby: Option<Single<Ref<LobbyConfig>>>,
+    server: Option<Single<(), With<Server>>>,
+    mut seats: Query<&mut LobbySeat>,
+) {
+    let Some(lobby) = lobby else { return };
+    if server.is_none() || !lobby.is_changed() || lobby.is_added() {
+        return;
+    }
+
+    let sides = lobby.area_shape.sides();
+
+    for mut seat in seats.iter_mut() {
+        let side = seat.side.filter(|side| sides.contains(side));
+
+        if seat.side != side || seat.ready {
+            seat.side = side;
+            seat.ready = false;
+        }
+    }
+}
+
+/// Seats every player on their team and spawns the match. Returns `false`
+/// without touching anything while the lobby is not ready to start.
+pub fn start_online_match<'a>(
+    lobby: &LobbyConfig,
+    seats: impl IntoIterator<Item = (Mut<'a, LobbySeat>, Option<&'a RemotePlayerId>)>,
+    game_config: &mut GameModeConfig,
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) -> bool {
+    let mut seats: Vec<_> = seats.into_iter().collect();
+
+    if lobby_start_blocker(seats.iter().map(|(seat, _)| &**seat), lobby.area_shape).is_some() {
+        return false;
+    }
+
+    let mut area_shape = AreaShape::new(lobby.area_shape);
+
+    for (seat, remote) in seats.iter_mut() {
+        let Some(team) = seat.side.and_then(|side| area_shape.get_team_mut(side)) else { continue };
+
+        team.players.push(match remote {
+            Some(id) => PlayerId::Network(**id),
+            None => HOST_PLAYER_ID,
+        });
+
+        // the next match is readied up for from scratch
+        seat.ready = false;
+    }
+
+    game_config.game_mode = lobby.game_mode.clone();
+    game_config.area_shape = area_shape;
+    game_config.win_score = lobby.points_to_win;
+
+    AreaBundle::spawn(game_config, commands, meshes, materials);
+    commands.trigger(ChatMessage::system("Match started"));
+
+    true
+}
+
+/// Dedicated servers have no host to press Start, so the match begins as
+/// soon as the lobby is ready.
+pub fn s_auto_start_dedicated(
+    config: Res<OnlineGameConfig>,
+    lobby: Option<Single<&LobbyConfig>>,
+    mut seats: Query<(&mut LobbySeat, Option<&RemotePlayerId>)>,
+    match_entities: Query<(), With<MatchEntity>>,
+    mut game_config: ResMut<GameModeConfig>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    let Some(lobby) = lobby else { return };
+    if !config.dedicated || !match_entities.is_empty() {
+        return;
+    }
+
+    start_online_match(
+        &lobby,
+        seats.iter_mut(),
+        &mut game_config,
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+    );
+}
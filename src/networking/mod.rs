@@ -1,4 +1,10 @@
+pub mod chat;
 pub mod client;
+pub mod conditioner;
+pub mod discovery;
+pub mod lobby;
+pub mod moderation;
 pub mod protocol;
 pub mod server;
 pub mod shared;
+pub mod stats;
@@ -0,0 +1,252 @@
+use crate::networking::chat::ChatMessage;
+use crate::networking::client::GameClient;
+use crate::networking::lobby::HostSeat;
+use crate::networking::protocol::{peer_display_name, LobbyChannel, LobbyConfig, RemotePlayerId, Spectator};
+use crate::networking::server::LobbyEntity;
+use bevy::prelude::*;
+use lightyear::prelude::server::{ClientOf, Server};
+use lightyear::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+/// Time given to a removal notice to reach the client before its link is closed.
+const REMOVAL_DELAY_SECS: f32 = 0.5;
+
+/// Held by whoever moderates the room, the listen server's own seat or,
+/// after a transfer or on a dedicated server, one of the remote players.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoomHost;
+
+/// Host-only requests. Clients send them to the server, which drops any
+/// that do not come from the current [`RoomHost`].
+#[derive(Message, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HostCommand {
+    Kick(PeerId),
+    /// Keeps the player's identity and address out until the server stops.
+    Ban(PeerId),
+    SetLocked(bool),
+    TransferHost(PeerId),
+}
+
+/// A [`HostCommand`] reaching the server, `by` is `None` when it was issued
+/// on the listen server itself.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct HostCommandIssued {
+    pub command: HostCommand,
+    pub by: Option<PeerId>,
+}
+
+/// Tells a client why it is about to be disconnected.
+#[derive(Message, Serialize, Deserialize, Clone, Debug)]
+pub struct RemovedFromRoom {
+    pub reason: String,
+    /// Turned away for a missing or wrong room password, the client asks
+    /// for it instead of only showing the reason.
+    pub wrong_password: bool,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct RoomBans {
+    pub peers: HashSet<PeerId>,
+    pub addresses: HashSet<IpAddr>,
+}
+
+impl RoomBans {
+    pub fn is_banned(&self, peer: PeerId, address: Option<IpAddr>) -> bool {
+        self.peers.contains(&peer) || address.is_some_and(|ip| self.addresses.contains(&ip))
+    }
+}
+
+/// Link closing once its removal notice had time to arrive.
+#[derive(Component)]
+pub struct PendingRemoval(pub Timer);
+
+/// Sends a command issued on this machine. Clients hand it to the server,
+/// a listen server's host applies it directly.
+pub fn send_host_command(
+    command: HostCommand,
+    client: Option<&mut MessageSender<HostCommand>>,
+    commands: &mut Commands,
+) {
+    if let Some(sender) = client {
+        sender.send::<LobbyChannel>(command);
+    } else {
+        commands.trigger(HostCommandIssued { command, by: None });
+    }
+}
+
+/// Sends the notice and schedules the disconnect. The player or spectator
+/// goes at once, so a kicked player's slot is never held for a reconnect.
+pub fn remove_from_room(
+    commands: &mut Commands,
+    link: Entity,
+    sender: &mut MessageSender<RemovedFromRoom>,
+    occupants: impl IntoIterator<Item = Entity>,
+    reason: &str,
+) {
+    sender.send::<LobbyChannel>(RemovedFromRoom {
+        reason: reason.to_owned(),
+        wrong_password: false,
+    });
+
+    schedule_removal(commands, link);
+
+    for occupant in occupants {
+        commands.entity(occupant).despawn();
+    }
+}
+
+/// Turns away a client that has not been admitted yet because it sent no
+/// password or the wrong one.
+pub fn refuse_password(commands: &mut Commands, link: Entity, sender: &mut MessageSender<RemovedFromRoom>, attempted: bool) {
+    sender.send::<LobbyChannel>(RemovedFromRoom {
+        reason: if attempted { "Wrong password" } else { "This room needs a password" }.to_owned(),
+        wrong_password: true,
+    });
+
+    schedule_removal(commands, link);
+}
+
+fn schedule_removal(commands: &mut Commands, link: Entity) {
+    commands
+        .entity(link)
+        .insert(PendingRemoval(Timer::from_seconds(REMOVAL_DELAY_SECS, TimerMode::Once)));
+}
+
+pub fn s_receive_host_commands(
+    mut links: Query<(&RemoteId, &mut MessageReceiver<HostCommand>), With<ClientOf>>,
+    mut commands: Commands,
+) {
+    for (remote, mut receiver) in links.iter_mut() {
+        for command in receiver.receive() {
+            commands.trigger(HostCommandIssued { command, by: Some(remote.0) });
+        }
+    }
+}
+
+/// Applies a command from whoever holds [`RoomHost`], a remote player or
+/// the listen server's own seat, and drops it from anyone else.
+pub fn t_apply_host_command(
+    issued: On<HostCommandIssued>,
+    server: Option<Single<(), With<Server>>>,
+    hosts: Query<(Option<&RemotePlayerId>, Has<HostSeat>), With<RoomHost>>,
+    mut links: Query<(Entity, &RemoteId, Option<&PeerAddr>, &mut MessageSender<RemovedFromRoom>), With<ClientOf>>,
+    players: Query<(Entity, &RemotePlayerId)>,
+    spectators: Query<(Entity, &Spectator)>,
+    current_hosts: Query<Entity, With<RoomHost>>,
+    lobby: Option<Single<&mut LobbyConfig, With<LobbyEntity>>>,
+    mut bans: ResMut<RoomBans>,
+    mut commands: Commands,
+) {
+    if server.is_none() {
+        return;
+    }
+
+    let is_host = hosts.iter().any(|(id, host_seat)| match issued.by {
+        Some(peer) => id.is_some_and(|id| id.0 == peer),
+        None => host_seat,
+    });
+
+    if !is_host {
+        warn!("Ignoring {:?} from {:?}, who is not the host", issued.command, issued.by);
+        return;
+    }
+
+    match issued.command {
+        HostCommand::Kick(peer) | HostCommand::Ban(peer) => {
+            let banned = matches!(issued.command, HostCommand::Ban(_));
+            let Some((link, _, addr, mut sender)) = links.iter_mut().find(|(_, id, _, _)| id.0 == peer) else { return };
+
+            if banned {
+                bans.peers.insert(peer);
+                if let Some(addr) = addr {
+                    bans.addresses.insert(addr.0.ip());
+                }
+            }
+
+            let occupants = players
+                .iter()
+                .filter(|(_, id)| id.0 == peer)
+                .map(|(e, _)| e)
+                .chain(spectators.iter().filter(|(_, s)| s.0 == peer).map(|(e, _)| e));
+
+            let (reason, notice) = if banned {
+                ("You were banned from this room", "was banned")
+            } else {
+                ("You were kicked from this room", "was kicked")
+            };
+
+            remove_from_room(&mut commands, link, &mut sender, occupants, reason);
+            commands.trigger(ChatMessage::system(format!("{} {notice}", peer_display_name(peer))));
+        }
+        HostCommand::SetLocked(locked) => {
+            let Some(mut lobby) = lobby else { return };
+            if lobby.locked == locked {
+                return;
+            }
+
+            lobby.locked = locked;
+            commands.trigger(ChatMessage::system(if locked {
+                "The room is locked to new players"
+            } else {
+                "The room is open again"
+            }));
+        }
+        HostCommand::TransferHost(peer) => {
+            let Some((target, _)) = players.iter().find(|(_, id)| id.0 == peer) else { return };
+
+            for host in current_hosts.iter() {
+                commands.entity(host).remove::<RoomHost>();
+            }
+
+            commands.entity(target).insert(RoomHost);
+            commands.trigger(ChatMessage::system(format!("{} is now the host", peer_display_name(peer))));
+        }
+    }
+}
+
+/// Keeps somebody in charge: the listen server's own seat when it has one,
+/// otherwise the first remaining player.
+pub fn s_assign_room_host(
+    server: Option<Single<(), With<Server>>>,
+    hosts: Query<(), With<RoomHost>>,
+    host_seat: Option<Single<Entity, With<HostSeat>>>,
+    players: Query<(Entity, &RemotePlayerId)>,
+    mut commands: Commands,
+) {
+    if server.is_none() || !hosts.is_empty() {
+        return;
+    }
+
+    if let Some(seat) = host_seat {
+        commands.entity(*seat).insert(RoomHost);
+    } else if let Some((player, id)) = players.iter().min_by_key(|(_, id)| id.display_name()) {
+        commands.entity(player).insert(RoomHost);
+        commands.trigger(ChatMessage::system(format!("{} is now the host", id.display_name())));
+    }
+}
+
+pub fn s_finish_removals(
+    time: Res<Time>,
+    mut links: Query<(Entity, &mut PendingRemoval)>,
+    mut commands: Commands,
+) {
+    for (link, mut removal) in links.iter_mut() {
+        removal.0.tick(time.delta());
+
+        if removal.0.just_finished() {
+            commands.trigger(Disconnect { entity: link });
+        }
+    }
+}
+
+/// Remembers why the server is about to close the connection, so the
+/// disconnect can be reported with it.
+pub fn receive_removal_notice(mut clients: Query<(&mut GameClient, &mut MessageReceiver<RemovedFromRoom>)>) {
+    for (mut client, mut receiver) in clients.iter_mut() {
+        for notice in receiver.receive() {
+            client.removal = Some(notice);
+        }
+    }
+}
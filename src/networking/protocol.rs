@@ -1,5 +1,5 @@
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
-use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket};
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
 use crate::networking::client::{DiscoveredServers, ClientDiscoverySocket};
 use crate::models::game::area::LocalPlayerID;
 use crate::resources::{OnlineGameConfig, PlayerAction};
@@ -10,7 +10,20 @@ use lightyear::input::config::InputConfig;
 use lightyear::prelude::input::leafwing;
 use lightyear::prelude::*;
 use serde::{Deserialize, Serialize};
+use crate::components::ui::UIOptionString;
+use crate::components::{LastTouch, MatchScore, MatchVisual, PlayerConnection, PlayerIdentity};
+use crate::events::gameplay::{BallHit, BallServed, MatchEnded, MatchOver, PointAwarded};
+use crate::models::game::area::AreaShapeKind;
 use crate::models::game::gameplay::GameMode;
+use crate::networking::chat::{ChatMessage, ChatSend};
+use crate::networking::discovery::DISCOVERY_PROTOCOL_VERSION;
+use crate::networking::stats::ROLLBACK_TRIGGERED;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use crate::networking::client::GameClient;
+use lightyear::prelude::client::InputDelayConfig;
+use crate::networking::lobby::{HostSeat, LobbySeat, SeatRequest};
+use crate::networking::moderation::{HostCommand, RemovedFromRoom, RoomHost};
 use crate::networking::server::LobbyEntity;
 
 pub const DISCOVERY_ADDR: SocketAddrV4 =
@@ -18,23 +31,68 @@ pub const DISCOVERY_ADDR: SocketAddrV4 =
 
 pub const DISCOVERY_ADDR_LOCAL: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, DISCOVERY_PORT);
 pub const DISCOVERY_PORT: u16 = 6000;
-pub const DISCOVERY_CLIENT_MAGIC: &[u8] = b"SEND_NUDESI";
+/// Netcode protocol id, peers built with a different one cannot connect.
+pub const NETWORK_PROTOCOL_ID: u64 = DISCOVERY_PROTOCOL_VERSION as u64;
 pub const UNSPECIFIED_ADDR: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
+pub const UNSPECIFIED_ADDR_V6: SocketAddr = SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, 0, 0, 0));
+
+/// Link-local multicast group IPv6 discovery requests are sent to.
+pub const DISCOVERY_MULTICAST_V6: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0x7067);
+pub const DISCOVERY_ADDR_V6: SocketAddrV6 = SocketAddrV6::new(DISCOVERY_MULTICAST_V6, DISCOVERY_PORT, 0, 0);
 
 
 #[derive(Component, Message, Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct LobbyConfig {
     pub game_mode: GameMode,
+    pub area_shape: AreaShapeKind,
     pub points_to_win: u32,
     pub max_players: u8,
+    /// How long a dropped player's slot is held during a match, zero frees it immediately.
+    pub reconnect_grace_secs: u32,
+    /// Turns away new joins, players holding a slot may still reconnect.
+    pub locked: bool,
+    pub netcode: NetcodeSettings,
+}
+
+/// Latency against smoothness trade-offs picked by the host and applied by
+/// every peer once the lobby config reaches it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct NetcodeSettings {
+    /// Ticks a client holds its own inputs back, hiding that much latency
+    /// without predicting.
+    pub input_delay_ticks: u16,
+    /// Latency beyond the input delay is predicted up to this many ticks.
+    pub max_prediction_ticks: u16,
+    /// World units a predicted position may be off before rolling back.
+    pub position_rollback_threshold: f32,
+    /// Radians a predicted rotation may be off before rolling back.
+    pub rotation_rollback_threshold: f32,
+    /// How far behind the server interpolated entities are shown.
+    pub interpolation_delay_ms: u32,
+}
+
+impl Default for NetcodeSettings {
+    fn default() -> Self {
+        Self {
+            input_delay_ticks: 0,
+            max_prediction_ticks: 100,
+            position_rollback_threshold: 0.01,
+            rotation_rollback_threshold: 0.01,
+            interpolation_delay_ms: 100,
+        }
+    }
 }
 
 impl Default for LobbyConfig {
     fn default() -> Self {
         Self {
             game_mode: GameMode::Classic,
+            area_shape: AreaShapeKind::TwoSide,
             points_to_win: 10,
             max_players: 4,
+            reconnect_grace_secs: 60,
+            locked: false,
+            netcode: NetcodeSettings::default(),
         }
     }
 }
@@ -54,6 +112,66 @@ pub struct LobbyPlayerList {
 #[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Reflect, Eq, Hash)]
 pub struct RemotePlayerId(pub PeerId, pub LocalPlayerID);
 
+/// A connected client watching without a player slot.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spectator(pub PeerId);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum JoinRole {
+    #[default]
+    Player,
+    Spectator,
+}
+
+impl UIOptionString for JoinRole {
+    fn push_ui_option_string(&self, string: &mut String) {
+        string.push_str(match self {
+            JoinRole::Player => "Player",
+            JoinRole::Spectator => "Spectator",
+        });
+    }
+}
+
+/// Sent after connecting and again to switch roles between matches. Players
+/// asking for a slot that is not free are seated as spectators. The identity
+/// is the client's online profile, the server cleans it up before use. The
+/// password only matters on the first request, when the client is admitted.
+#[derive(Message, Serialize, Deserialize, Clone, Debug)]
+pub struct JoinRequest {
+    pub role: JoinRole,
+    pub identity: Option<PlayerIdentity>,
+    pub password: Option<String>,
+}
+
+impl RemotePlayerId {
+    pub fn display_name(&self) -> String {
+        peer_display_name(self.0)
+    }
+
+    /// The name the player picked, if they sent one.
+    pub fn name_with(&self, identity: Option<&PlayerIdentity>) -> String {
+        identity.map_or_else(|| self.display_name(), |identity| identity.name.clone())
+    }
+}
+
+/// Short label for peers that sent no name of their own.
+pub fn peer_display_name(peer: PeerId) -> String {
+    match peer {
+        PeerId::Netcode(id) => format!("Player {:04}", id % 10_000),
+        other => format!("{other:?}"),
+    }
+}
+
+/// Reliable, ordered channel for authoritative match events.
+pub struct MatchEventsChannel;
+
+/// Reliable, ordered channel for lobby requests from clients and the
+/// server's answers to them.
+pub struct LobbyChannel;
+
+/// Reliable, ordered channel for chat in both directions.
+pub struct ChatChannel;
+
 pub struct GameProtocolPlugin;
 
 impl Plugin for GameProtocolPlugin {
@@ -86,13 +204,79 @@ impl Plugin for GameProtocolPlugin {
            .add_prediction();
 
         app.register_component::<LobbyConfig>();
+        app.add_systems(Update, s_apply_netcode_settings);
         app.register_component::<LobbyEntity>();
+        app.register_component::<RemotePlayerId>();
+        app.register_component::<PlayerConnection>();
+        app.register_component::<PlayerIdentity>();
 
         app.register_message::<ChangeLobbySettings>()
            .add_direction(NetworkDirection::ClientToServer);
 
         app.register_message::<LobbyPlayerList>()
            .add_direction(NetworkDirection::ServerToClient);
+
+        app.register_component::<MatchScore>();
+        app.register_component::<Spectator>();
+
+        app.register_component::<MatchVisual>();
+        app.register_component::<LastTouch>();
+
+        app.add_channel::<LobbyChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        })
+        .add_direction(NetworkDirection::Bidirectional);
+
+        app.register_message::<JoinRequest>()
+           .add_direction(NetworkDirection::ClientToServer);
+
+        app.register_component::<LobbySeat>();
+        app.register_component::<HostSeat>();
+
+        app.register_message::<SeatRequest>()
+           .add_direction(NetworkDirection::ClientToServer);
+
+        app.register_component::<RoomHost>();
+
+        app.register_message::<HostCommand>()
+           .add_direction(NetworkDirection::ClientToServer);
+
+        app.register_message::<RemovedFromRoom>()
+           .add_direction(NetworkDirection::ServerToClient);
+
+        app.add_channel::<ChatChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        })
+        .add_direction(NetworkDirection::Bidirectional);
+
+        app.register_message::<ChatSend>()
+           .add_direction(NetworkDirection::ClientToServer);
+
+        app.register_message::<ChatMessage>()
+           .add_direction(NetworkDirection::ServerToClient);
+
+        app.add_channel::<MatchEventsChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        })
+        .add_direction(NetworkDirection::ServerToClient);
+
+        app.register_message::<PointAwarded>()
+           .add_direction(NetworkDirection::ServerToClient);
+
+        app.register_message::<MatchOver>()
+           .add_direction(NetworkDirection::ServerToClient);
+
+        app.register_message::<MatchEnded>()
+           .add_direction(NetworkDirection::ServerToClient);
+
+        app.register_message::<BallServed>()
+           .add_direction(NetworkDirection::ServerToClient);
+
+        app.register_message::<BallHit>()
+           .add_direction(NetworkDirection::ServerToClient);
     }
 }
 
@@ -112,14 +296,125 @@ pub fn make_reusable_udp_socket(port: u16) -> std::io::Result<UdpSocket> {
     Ok(socket.into())
 }
 
+/// IPv6 only counterpart of [`make_reusable_udp_socket`] that also joins the
+/// discovery multicast group on the default interface.
+pub fn make_reusable_udp_socket_v6(port: u16) -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+
+    socket.set_only_v6(true)?;
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+
+    let addr = SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, port, 0, 0);
+    socket.bind(&SocketAddr::V6(addr).into())?;
+    socket.join_multicast_v6(&DISCOVERY_MULTICAST_V6, 0)?;
+
+    Ok(socket.into())
+}
+
+/// Picks the game server address, a `port` of zero takes any free one.
+///
+/// lightyear opens its own socket on this address with the system's default
+/// options, so `[::]` is only picked where those default to dual-stack and
+/// IPv4 clients still get in. Systems defaulting to IPv6-only sockets, such
+/// as Linux with `net.ipv6.bindv6only=1`, the BSDs or Windows, host on IPv4.
+/// The probe is closed right before the server starts in the same frame, a
+/// port picked by the system is only up for grabs in between.
+pub fn pick_server_addr(port: u16) -> std::io::Result<SocketAddr> {
+    let mut v4 = UNSPECIFIED_ADDR;
+    v4.set_port(port);
+
+    let mut v6 = UNSPECIFIED_ADDR_V6;
+    v6.set_port(port);
+
+    let probe = match UdpSocket::bind(v6) {
+        Ok(probe) => probe,
+        Err(e) => {
+            warn!("IPv6 unavailable ({e}), hosting on IPv4 only");
+            return UdpSocket::bind(v4)?.local_addr();
+        }
+    };
+
+    match SockRef::from(&probe).only_v6() {
+        Ok(false) => probe.local_addr(),
+        Ok(true) => {
+            info!("Sockets are IPv6 only by default here, hosting on IPv4 only");
+            drop(probe);
+            UdpSocket::bind(v4)?.local_addr()
+        }
+        Err(e) => {
+            warn!("Could not tell whether sockets are dual-stack ({e}), hosting on IPv4 only");
+            drop(probe);
+            UdpSocket::bind(v4)?.local_addr()
+        }
+    }
+}
+
+/// Rollback checks are plain function pointers, so the thresholds from
+/// [`NetcodeSettings`] reach them through these, stored as `f32` bits.
+static POSITION_ROLLBACK_THRESHOLD: AtomicU32 = AtomicU32::new(0.01f32.to_bits());
+static ROTATION_ROLLBACK_THRESHOLD: AtomicU32 = AtomicU32::new(0.01f32.to_bits());
+
 #[inline]
 fn position_should_rollback(this: &Position, that: &Position) -> bool {
-    (this.0 - that.0).length() >= 0.01
+    let threshold = f32::from_bits(POSITION_ROLLBACK_THRESHOLD.load(Ordering::Relaxed));
+    flag_rollback((this.0 - that.0).length() >= threshold)
 }
 
 #[inline]
 fn rotation_should_rollback(this: &Rotation, that: &Rotation) -> bool {
-    this.angle_between(*that) >= 0.01
+    let threshold = f32::from_bits(ROTATION_ROLLBACK_THRESHOLD.load(Ordering::Relaxed));
+    flag_rollback(this.angle_between(*that) >= threshold)
+}
+
+/// Netcode settings a client's timelines were last configured with.
+#[derive(Component)]
+pub struct AppliedNetcode(NetcodeSettings);
+
+/// Keeps every peer on the host's netcode settings. Clients reconfigure
+/// their input and interpolation timelines in place, keeping what they have
+/// synced so far, everyone updates the rollback checks. Lobby changes that
+/// leave the netcode alone touch nothing.
+pub fn s_apply_netcode_settings(
+    lobby: Option<Single<&LobbyConfig>>,
+    mut clients: Query<(Entity, &mut InputTimeline, &mut InterpolationTimeline, Option<&AppliedNetcode>), With<GameClient>>,
+    mut applied: Local<Option<NetcodeSettings>>,
+    mut commands: Commands,
+) {
+    let Some(lobby) = lobby else { return };
+    let netcode = lobby.netcode;
+
+    if *applied != Some(netcode) {
+        *applied = Some(netcode);
+        POSITION_ROLLBACK_THRESHOLD.store(netcode.position_rollback_threshold.to_bits(), Ordering::Relaxed);
+        ROTATION_ROLLBACK_THRESHOLD.store(netcode.rotation_rollback_threshold.to_bits(), Ordering::Relaxed);
+    }
+
+    for (client, mut input, mut interpolation, last) in clients.iter_mut() {
+        if last.is_some_and(|last| last.0 == netcode) {
+            continue;
+        }
+
+        input.config = InputDelayConfig {
+            minimum_input_delay_ticks: netcode.input_delay_ticks,
+            maximum_input_delay_before_prediction: netcode.input_delay_ticks,
+            maximum_predicted_ticks: netcode.max_prediction_ticks,
+        };
+        interpolation.config.min_delay = Duration::from_millis(netcode.interpolation_delay_ms as u64);
+
+        commands.entity(client).insert(AppliedNetcode(netcode));
+    }
+}
+
+#[inline]
+fn flag_rollback(rollback: bool) -> bool {
+    if rollback {
+        ROLLBACK_TRIGGERED.store(true, Ordering::Relaxed);
+    }
+    rollback
 }
 
 #[derive(Message, Serialize, Deserialize)]
@@ -1,6 +1,8 @@
 use std::fmt::format;
+use std::hash::{BuildHasher, RandomState};
+use std::time::SystemTime;
 use bevy::prelude::*;
-use lightyear::prelude::server::{NetcodeConfig, NetcodeServer, ServerPlugins, ServerUdpIo, Start};
+use lightyear::prelude::server::{ClientOf, NetcodeConfig, NetcodeServer, Server, ServerMultiMessageSender, ServerPlugins, ServerUdpIo, Start};
 use lightyear::prelude::*;
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
 use bevy::log::tracing::Instrument;
@@ -8,17 +10,37 @@ use lightyear::connection::host::HostClient;
 use lightyear::link::LinkStart;
 use lightyear::netcode::client::ClientConfig;
 use lightyear::netcode::{Key, NetcodeClient, ServerConfig};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use socket2::{Domain, Protocol, SockAddr, SockAddrStorage, Socket, Type};
-use crate::components::ui::ServerList;
+use crate::components::ui::{ServerList, UIOptionString};
 use crate::networking::client::{DiscoveredServers, ClientDiscoverySocket};
-use crate::networking::protocol::{make_reusable_udp_socket, ChangeLobbySettings, LobbyConfig, LobbyPlayerList, DISCOVERY_ADDR, DISCOVERY_CLIENT_MAGIC, DISCOVERY_PORT, UNSPECIFIED_ADDR};
-use crate::resources::OnlineGameConfig;
-const BROADCAST_INTERVAL_SECS: f32 = 30.0;
+use crate::networking::discovery::{decode_request, DiscoveryResponse, DISCOVERY_PROTOCOL_VERSION};
+use crate::networking::protocol::{make_reusable_udp_socket, make_reusable_udp_socket_v6, pick_server_addr, ChangeLobbySettings, NETWORK_PROTOCOL_ID, LobbyConfig, LobbyPlayerList, DISCOVERY_ADDR, DISCOVERY_PORT, UNSPECIFIED_ADDR};
+use crate::resources::{sanitize_player_name, OnlineGameConfig};
+use crate::bundles::player::PlayerBundle;
+use crate::components::{Ball, Goal, MatchEntity, MatchScore, MatchVisual, Paddle, PlayerConnection, PlayerIdentity, PLAYER_COLORS};
+use crate::events::gameplay::{BallHit, BallServed, MatchEnded, MatchOver, PointAwarded};
+use crate::networking::protocol::MatchEventsChannel;
+use crate::models::game::area::LocalPlayerID;
+use crate::networking::chat::{s_receive_chat, t_broadcast_chat, ChatMessage, ChatRateLimit};
+use crate::networking::protocol::{peer_display_name, JoinRequest, JoinRole, RemotePlayerId, Spectator};
+use crate::networking::lobby::{s_auto_start_dedicated, s_handle_seat_requests, s_reset_seats_on_lobby_change, HostSeat, LobbySeat};
+use crate::networking::moderation::{refuse_password, remove_from_room, s_assign_room_host, s_finish_removals, s_receive_host_commands, t_apply_host_command, PendingRemoval, RemovedFromRoom, RoomBans};
+use crate::utils::{BALL_RADIUS, PADDLE_SIZE};
+const BROADCAST_INTERVAL_SECS: f32 = 2.0;
+const LOBBY_ANNOUNCE_DELAY_SECS: f32 = 1.0;
 
 #[derive(Component)]
 pub struct ServerDiscoverySocket {
     pub socket: UdpSocket,
+    pub socket_v6: Option<UdpSocket>,
+}
+
+impl ServerDiscoverySocket {
+    pub fn sockets(&self) -> impl Iterator<Item = &UdpSocket> {
+        std::iter::once(&self.socket).chain(self.socket_v6.as_ref())
+    }
 }
 
 #[derive(Resource)]
@@ -27,9 +49,17 @@ pub struct BroadcastTimer(pub Timer);
 #[derive(Component)]
 pub struct ServerName(pub String);
 
+/// Sent along with discovery answers, see [`DiscoveryResponse::instance`].
+#[derive(Component, Clone, Copy)]
+pub struct ServerInstance(pub u64);
+
 #[derive(Component, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct LobbyEntity;
 
+/// Counts down the slot held for a player who dropped out mid-match.
+#[derive(Component)]
+pub struct ReconnectGrace(pub Timer);
+
 pub struct GameServerPlugin;
 
 impl Plugin for GameServerPlugin {
@@ -41,92 +71,146 @@ impl Plugin for GameServerPlugin {
             TimerMode::Repeating,
         )));
 
-        app.add_systems(Update, lan_discovery_responder);
+        app.add_systems(Update, (
+            lan_discovery_responder,
+            s_tick_reconnect_grace,
+            s_handle_join_requests,
+            s_receive_chat,
+            s_announce_lobby_changes,
+            s_handle_seat_requests,
+            s_reset_seats_on_lobby_change,
+            s_auto_start_dedicated,
+            s_receive_host_commands,
+            s_assign_room_host,
+            s_finish_removals,
+        ));
+        app.add_observer(t_client_connected);
+        app.add_observer(t_client_disconnected);
+        app.add_observer(t_replicate_match_score);
+        app.add_observer(t_replicate_ball);
+        app.add_observer(t_replicate_paddle);
+        app.add_observer(t_broadcast_match_event::<PointAwarded>);
+        app.add_observer(t_broadcast_match_event::<MatchOver>);
+        app.add_observer(t_broadcast_match_event::<MatchEnded>);
+        app.add_observer(t_broadcast_match_event::<BallServed>);
+        app.add_observer(t_broadcast_match_event::<BallHit>);
+        app.add_observer(t_broadcast_chat);
+        app.add_observer(t_apply_host_command);
+
+        app.init_resource::<RoomBans>();
     }
 }
 
 pub fn lan_discovery_responder(
-    socket: Single<&ServerDiscoverySocket>,
-    server: Single<(&ServerName, &LocalAddr), With<NetcodeServer>>,
+    discovery: Single<&ServerDiscoverySocket>,
+    server: Single<(&ServerName, &LocalAddr, &ServerInstance), With<NetcodeServer>>,
+    lobby: Option<Single<&LobbyConfig>>,
+    players: Query<(), With<RemotePlayerId>>,
+    spectators: Query<(), With<Spectator>>,
+    config: Res<OnlineGameConfig>,
 ) {
     let mut buf = [0u8; 256];
 
-    loop {
-        match socket.socket.recv_from(&mut buf) {
-            Ok((len, SocketAddr::V4(addr))) => {
-                if &buf[..len] == DISCOVERY_CLIENT_MAGIC {
-                    info!("Responding to discovery from {}", addr);
-                    
-                    let server_ip = match UdpSocket::bind("0.0.0.0:0") {
-                        Ok(temp) => {
-                            if temp.connect(addr).is_ok() {
-                                match temp.local_addr() {
-                                    Ok(a) => a.ip().to_string(),
-                                    Err(_) => "0.0.0.0".to_string(),
-                                }
-                            } else {
-                                "0.0.0.0".to_string()
-                            }
-                        }
-                        Err(_) => "0.0.0.0".to_string(),
-                    };
-                    
-                    let resp = format!(
-                        "OKBRO\nNAME {}\nIP {}:{}\n\n\n",
-                        server.0 .0,
-                        server_ip,
-                        server.1 .0.port()
-                    );
-
-                    if let Err(e) = socket.socket.send_to(resp.as_bytes(), addr) {
-                        warn!("Could not respond to discovery request from {addr}: {e}");
-                    } else {
-                        info!("Sent discovery response to {addr}");
-                    }
-                } else {
-                    if let Ok(strmsg) = std::str::from_utf8(&buf[..len]) {
-                        info!("Received non discovery client magic message: {}", strmsg);
-                    }
+    for socket in discovery.sockets() {
+        loop {
+            let Ok((len, addr)) = socket.recv_from(&mut buf) else { break };
+
+            let version = match decode_request(&buf[..len]) {
+                Ok(version) => version,
+                Err(e) => {
+                    debug!("Ignoring malformed discovery request from {addr}: {e:?}");
+                    continue;
                 }
+            };
+
+            if version != DISCOVERY_PROTOCOL_VERSION {
+                info!("Discovery request from {addr} uses protocol v{version}, answering with v{DISCOVERY_PROTOCOL_VERSION}");
+            }
+
+            let lobby = lobby.as_ref().map(|lobby| LobbyConfig::clone(lobby)).unwrap_or_default();
+
+            let resp = DiscoveryResponse {
+                version: DISCOVERY_PROTOCOL_VERSION,
+                instance: server.2 .0,
+                name: server.0 .0.clone(),
+                port: server.1 .0.port(),
+                current_players: occupied_slots(players.iter().count(), &config).min(u8::MAX as usize) as u8,
+                max_players: lobby.max_players,
+                spectators: spectators.iter().count().min(u8::MAX as usize) as u8,
+                game_mode: lobby.game_mode,
+                area_shape: lobby.area_shape,
+                locked: config.pass.as_ref().is_some_and(|pass| !pass.is_empty()),
+                closed: lobby.locked,
+            };
+
+            if let Err(e) = socket.send_to(&resp.encode(), addr) {
+                warn!("Could not respond to discovery request from {addr}: {e}");
+            } else {
+                info!("Sent discovery response to {addr}");
             }
-            _ => {break;}
         }
     }
 }
 
+/// Returns the server entity, or `None` when its sockets could not be opened.
+/// `lobby` is a lobby set up before the server started, otherwise one with
+/// the default settings is spawned. Either way it is replicated, so every
+/// client sees the settings and applies the netcode ones.
 pub fn start_server(
     commands: &mut Commands,
     config: &OnlineGameConfig,
-    lobby_config: &Option<Single<&LobbyConfig>>,
-) {
-    if let Ok(socket) = make_reusable_udp_socket(DISCOVERY_PORT) {
+    lobby: Option<Entity>,
+) -> Option<Entity> {
+    let socket = match make_reusable_udp_socket(DISCOVERY_PORT) {
+        Ok(socket) => socket,
+        Err(e) => {
+            error!("Could not start server: {e}");
+            return None;
+        }
+    };
 
-        let mut local_addr: LocalAddr = LocalAddr(UNSPECIFIED_ADDR);
-        {
-            let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
-            local_addr = LocalAddr(socket.local_addr().unwrap());
+    let socket_v6 = match make_reusable_udp_socket_v6(DISCOVERY_PORT) {
+        Ok(socket) => Some(socket),
+        Err(e) => {
+            warn!("IPv6 LAN discovery unavailable: {e}");
+            None
         }
+    };
 
-        
-        let server = commands.spawn((
-                NetcodeServer::new(NetcodeConfig::default()),
-                local_addr,
-                ServerUdpIo::default(),
-                ServerDiscoverySocket { socket: socket.into() },
-                ServerName(config.server_name.clone()),
-            ))
-            .id();
-
-        if lobby_config.is_none(){
-            commands.spawn(LobbyConfig::default());
+    let local_addr = match pick_server_addr(config.port) {
+        Ok(addr) => LocalAddr(addr),
+        Err(e) => {
+            error!("Could not start server: {e}");
+            return None;
         }
+    };
 
-        
-        commands.trigger(Start { entity: server });
+    info!("Server \"{}\" listening on {}", config.server_name, local_addr.0);
 
-    } else {
-        error!("Could not start server");
+    let server = commands.spawn((
+            NetcodeServer::new(NetcodeConfig {
+                protocol_id: NETWORK_PROTOCOL_ID,
+                ..default()
+            }),
+            local_addr,
+            ServerUdpIo::default(),
+            ServerDiscoverySocket { socket, socket_v6 },
+            ServerName(config.server_name.clone()),
+            ServerInstance(RandomState::new().hash_one(SystemTime::now())),
+        ))
+        .id();
+
+    let lobby = lobby.unwrap_or_else(|| commands.spawn(LobbyConfig::default()).id());
+    commands
+        .entity(lobby)
+        .insert((LobbyEntity, Replicate::to_clients(NetworkTarget::All)));
+
+    if !config.dedicated {
+        commands.spawn((HostSeat, LobbySeat::default(), Replicate::to_clients(NetworkTarget::All)));
     }
+
+    commands.trigger(Start { entity: server });
+    Some(server)
 }
 
 pub fn s_apply_lobby_changes(
@@ -140,3 +224,359 @@ pub fn s_apply_lobby_changes(
         info!("Lobby config updated: {:?}", **lobby);
     }
 }
+
+/// Turns away banned clients, and newcomers while the room is locked. A
+/// client rejoining a held slot gets past the lock, but like everyone else
+/// is only admitted once its [`JoinRequest`] carries the room's password.
+pub fn t_client_connected(
+    trigger: On<Add, Connected>,
+    mut links: Query<(&RemoteId, Option<&PeerAddr>, &mut MessageSender<RemovedFromRoom>), With<ClientOf>>,
+    players: Query<&RemotePlayerId>,
+    lobby: Option<Single<&LobbyConfig>>,
+    bans: Res<RoomBans>,
+    mut commands: Commands,
+) {
+    let Ok((remote, addr, mut sender)) = links.get_mut(trigger.entity) else { return };
+
+    let held_slot = players.iter().any(|id| id.0 == remote.0);
+    let refusal = if bans.is_banned(remote.0, addr.map(|addr| addr.0.ip())) {
+        Some("You are banned from this room")
+    } else if !held_slot && lobby.is_some_and(|lobby| lobby.locked) {
+        Some("The room is locked")
+    } else {
+        None
+    };
+
+    if let Some(reason) = refusal {
+        info!("Turning away {:?}: {reason}", remote.0);
+        remove_from_room(&mut commands, trigger.entity, &mut sender, None, reason);
+    }
+}
+
+/// Seats clients as players or spectators. Roles only change between
+/// matches, and a player is only seated while a slot is free. Players go
+/// by the identity they sent, if it holds up after [`clean_identity`].
+/// A client is admitted, and may chat, once its first request carries the
+/// room's password. A client rejoining with the same identity is then handed
+/// its held slot back, and replication resends the whole match to the new link.
+pub fn s_handle_join_requests(
+    mut links: Query<
+        (Entity, &RemoteId, &mut MessageReceiver<JoinRequest>, &mut MessageSender<RemovedFromRoom>, Has<ChatRateLimit>),
+        (With<ClientOf>, Without<PendingRemoval>),
+    >,
+    mut players: Query<(Entity, &RemotePlayerId, &mut PlayerConnection, Option<&PlayerIdentity>)>,
+    spectators: Query<(Entity, &Spectator)>,
+    lobby: Option<Single<&LobbyConfig>>,
+    match_entities: Query<(), With<MatchEntity>>,
+    config: Res<OnlineGameConfig>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let max_players = lobby.map_or(LobbyConfig::default().max_players, |lobby| lobby.max_players);
+    let in_match = !match_entities.is_empty();
+    let password = config.pass.as_deref().filter(|pass| !pass.is_empty());
+    // requests handled this frame are not visible to the queries yet
+    let mut seated = 0;
+
+    for (link, remote, mut receiver, mut sender, mut admitted) in links.iter_mut() {
+        for request in receiver.receive() {
+            let peer = remote.0;
+
+            if !admitted {
+                if password.is_some_and(|pass| request.password.as_deref() != Some(pass)) {
+                    info!("Turning away {peer:?}: wrong password");
+                    refuse_password(&mut commands, link, &mut sender, request.password.is_some());
+                    break;
+                }
+
+                commands.entity(link).insert(ChatRateLimit::new(time.elapsed_secs()));
+                admitted = true;
+
+                let held = players
+                    .iter_mut()
+                    .find(|(_, id, connection, _)| id.0 == peer && connection.is_reconnecting());
+
+                if let Some((entity, id, mut connection, identity)) = held {
+                    *connection = PlayerConnection::Connected;
+                    commands
+                        .entity(entity)
+                        .remove::<ReconnectGrace>()
+                        .insert(player_owner(link));
+                    commands.trigger(ChatMessage::system(format!("{} reconnected", id.name_with(identity))));
+                    continue;
+                }
+            }
+
+            let identity = request.identity.as_ref().and_then(clean_identity);
+            let name = identity.as_ref().map_or_else(|| peer_display_name(peer), |identity| identity.name.clone());
+            let player = players.iter().find(|(_, id, ..)| id.0 == peer).map(|(e, ..)| e);
+            let spectator = spectators.iter().find(|(_, s)| s.0 == peer).map(|(e, _)| e);
+
+            if (player.is_some() || spectator.is_some()) && in_match {
+                info!("{peer:?} can only switch roles between matches");
+                continue;
+            }
+
+            match request.role {
+                JoinRole::Player if player.is_some() => {
+                    if let (Some(player), Some(identity)) = (player, identity) {
+                        commands.entity(player).insert(identity);
+                    }
+                }
+                JoinRole::Player => {
+                    let free = occupied_slots(players.iter().count() + seated, &config) < max_players as usize;
+
+                    if free && !in_match {
+                        if let Some(spectator) = spectator {
+                            commands.entity(spectator).despawn();
+                        }
+
+                        let mut spawned = commands.spawn((
+                            PlayerBundle::new_network(RemotePlayerId(peer, LocalPlayerID::Keyboard(1))),
+                            LobbySeat::default(),
+                            player_owner(link),
+                        ));
+                        if let Some(identity) = identity {
+                            spawned.insert(identity);
+                        }
+                        seated += 1;
+                        commands.trigger(ChatMessage::system(format!("{name} joined as a player")));
+                    } else if spectator.is_none() {
+                        spawn_spectator(&mut commands, peer);
+                        commands.trigger(ChatMessage::system(format!(
+                            "{name} joined as a spectator, no player slot was free",
+                        )));
+                    }
+                }
+                JoinRole::Spectator if spectator.is_some() => {}
+                JoinRole::Spectator => {
+                    if let Some(player) = player {
+                        commands.entity(player).despawn();
+                    }
+
+                    spawn_spectator(&mut commands, peer);
+                    commands.trigger(ChatMessage::system(format!("{name} is spectating")));
+                }
+            }
+        }
+    }
+}
+
+/// A client's name trimmed to something fit to show, and its colour kept to
+/// the palette. Nothing is left of a blank name.
+fn clean_identity(identity: &PlayerIdentity) -> Option<PlayerIdentity> {
+    Some(PlayerIdentity {
+        name: sanitize_player_name(&identity.name)?,
+        color: identity.color % PLAYER_COLORS.len() as u8,
+    })
+}
+
+/// Slots taken by remote players, plus the host of a listen server who plays
+/// locally and is never a connected client.
+fn occupied_slots(remote_players: usize, config: &OnlineGameConfig) -> usize {
+    remote_players + usize::from(!config.dedicated)
+}
+
+fn player_owner(link: Entity) -> ControlledBy {
+    ControlledBy {
+        owner: link,
+        lifetime: Lifetime::Persistent,
+    }
+}
+
+/// Spectators own no entity, so any input they might send has nothing to drive.
+fn spawn_spectator(commands: &mut Commands, peer: PeerId) {
+    commands.spawn((Spectator(peer), Replicate::to_clients(NetworkTarget::All)));
+}
+
+/// Holds the slot of a player dropping out of a running match for the
+/// lobby's grace period, outside of a match the slot is freed right away.
+pub fn t_client_disconnected(
+    trigger: On<Remove, Connected>,
+    links: Query<&RemoteId, With<ClientOf>>,
+    mut players: Query<(Entity, &RemotePlayerId, &mut PlayerConnection, Option<&PlayerIdentity>)>,
+    spectators: Query<(Entity, &Spectator)>,
+    lobby: Option<Single<&LobbyConfig>>,
+    match_entities: Query<(), With<MatchEntity>>,
+    mut commands: Commands,
+) {
+    let Ok(remote) = links.get(trigger.entity) else { return };
+
+    for (entity, spectator) in spectators.iter() {
+        if spectator.0 == remote.0 {
+            commands.entity(entity).despawn();
+            commands.trigger(ChatMessage::system(format!("{} left", peer_display_name(remote.0))));
+        }
+    }
+    let Some((entity, id, mut connection, identity)) = players.iter_mut().find(|(_, id, ..)| id.0 == remote.0) else { return };
+    let name = id.name_with(identity);
+
+    let grace = lobby.map_or(0, |lobby| lobby.reconnect_grace_secs);
+
+    if grace == 0 || match_entities.is_empty() {
+        commands.entity(entity).despawn();
+        commands.trigger(ChatMessage::system(format!("{name} left")));
+        return;
+    }
+
+    *connection = PlayerConnection::Reconnecting { remaining_secs: grace };
+    commands.entity(entity).insert(ReconnectGrace(Timer::from_seconds(grace as f32, TimerMode::Once)));
+    commands.trigger(ChatMessage::system(format!(
+        "{name} dropped, holding their slot for {grace}s",
+    )));
+}
+
+pub fn s_tick_reconnect_grace(
+    time: Res<Time>,
+    mut players: Query<(Entity, &RemotePlayerId, &mut PlayerConnection, &mut ReconnectGrace, Option<&PlayerIdentity>)>,
+    mut commands: Commands,
+) {
+    for (entity, id, mut connection, mut grace, identity) in players.iter_mut() {
+        grace.0.tick(time.delta());
+
+        if grace.0.just_finished() {
+            commands.entity(entity).despawn();
+            commands.trigger(ChatMessage::system(format!(
+                "{} did not return in time, their slot is free",
+                id.name_with(identity),
+            )));
+            continue;
+        }
+
+        let remaining_secs = grace.0.remaining_secs().ceil() as u32;
+
+        // only touch the replicated state once per second
+        if *connection != (PlayerConnection::Reconnecting { remaining_secs }) {
+            *connection = PlayerConnection::Reconnecting { remaining_secs };
+        }
+    }
+}
+
+pub fn t_replicate_match_score(
+    added: On<Add, MatchScore>,
+    server: Option<Single<(), With<Server>>>,
+    mut commands: Commands,
+) {
+    if server.is_some() {
+        commands
+            .entity(added.entity)
+            .insert(Replicate::to_clients(NetworkTarget::All));
+    }
+}
+
+/// Forwards a match event triggered by the local simulation to all clients.
+pub fn t_broadcast_match_event<M: Event + Message + Clone + Serialize + DeserializeOwned>(
+    event: On<M>,
+    server: Option<Single<&Server>>,
+    mut sender: ServerMultiMessageSender,
+) {
+    let Some(server) = server else { return };
+
+    if let Err(e) = sender.send::<M, MatchEventsChannel>(event.event(), *server, &NetworkTarget::All) {
+        warn!("Could not broadcast match event: {e:?}");
+    }
+}
+
+/// Balls and paddles reach clients interpolated, spectators only ever see
+/// these and never predict anything.
+pub fn t_replicate_ball(
+    added: On<Add, Ball>,
+    server: Option<Single<(), With<Server>>>,
+    mut commands: Commands,
+) {
+    if server.is_some() {
+        commands.entity(added.entity).insert((
+            MatchVisual::Ball { radius: BALL_RADIUS },
+            Replicate::to_clients(NetworkTarget::All),
+            InterpolationTarget::to_clients(NetworkTarget::All),
+        ));
+    }
+}
+
+pub fn t_replicate_paddle(
+    added: On<Add, Paddle>,
+    paddles: Query<&Paddle>,
+    goals: Query<&Goal>,
+    server: Option<Single<(), With<Server>>>,
+    mut commands: Commands,
+) {
+    if server.is_none() {
+        return;
+    }
+
+    let Ok(paddle) = paddles.get(added.entity) else { return };
+    let Ok(goal) = goals.get(paddle.goal) else { return };
+
+    commands.entity(added.entity).insert((
+        MatchVisual::Paddle {
+            size: PADDLE_SIZE,
+            side: goal.side,
+        },
+        Replicate::to_clients(NetworkTarget::All),
+        InterpolationTarget::to_clients(NetworkTarget::All),
+    ));
+}
+
+/// Tells everyone in the room what the host changed, once the settings stop
+/// moving so dragging a slider does not flood the chat.
+pub fn s_announce_lobby_changes(
+    lobby: Single<Ref<LobbyConfig>>,
+    server: Option<Single<(), With<Server>>>,
+    time: Res<Time>,
+    mut announced: Local<Option<LobbyConfig>>,
+    mut settles_at: Local<f32>,
+    mut commands: Commands,
+) {
+    if server.is_none() {
+        return;
+    }
+
+    let now = time.elapsed_secs();
+    if lobby.is_changed() {
+        *settles_at = now + LOBBY_ANNOUNCE_DELAY_SECS;
+    }
+
+    let Some(before) = announced.as_ref() else {
+        *announced = Some(LobbyConfig::clone(&lobby));
+        return;
+    };
+
+    if now < *settles_at || *before == **lobby {
+        return;
+    }
+
+    let mut changes = Vec::new();
+
+    if before.game_mode != lobby.game_mode {
+        let mut mode = String::new();
+        lobby.game_mode.push_ui_option_string(&mut mode);
+        changes.push(format!("mode {mode}"));
+    }
+    if before.area_shape != lobby.area_shape {
+        let mut shape = String::new();
+        lobby.area_shape.push_ui_option_string(&mut shape);
+        changes.push(format!("arena {shape}"));
+    }
+    if before.points_to_win != lobby.points_to_win {
+        changes.push(format!("first to {}", lobby.points_to_win));
+    }
+    if before.max_players != lobby.max_players {
+        changes.push(format!("{} players max", lobby.max_players));
+    }
+    if before.reconnect_grace_secs != lobby.reconnect_grace_secs {
+        changes.push(format!("{}s reconnect grace", lobby.reconnect_grace_secs));
+    }
+    if before.netcode != lobby.netcode {
+        let netcode = &lobby.netcode;
+        changes.push(format!(
+            "netcode to {} ticks input delay, {} ticks prediction, {}ms interpolation",
+            netcode.input_delay_ticks, netcode.max_prediction_ticks, netcode.interpolation_delay_ms,
+        ));
+    }
+
+    if !changes.is_empty() {
+        commands.trigger(ChatMessage::system(format!("Host changed {}", changes.join(", "))));
+    }
+
+    *announced = Some(LobbyConfig::clone(&lobby));
+}
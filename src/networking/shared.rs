@@ -1,6 +1,8 @@
 use crate::networking::client::GameClientPlugin;
+use crate::networking::conditioner::NetworkConditionerPlugin;
 use crate::networking::protocol::GameProtocolPlugin;
 use crate::networking::server::GameServerPlugin;
+use crate::networking::stats::NetStatsPlugin;
 use bevy::prelude::*;
 use lightyear::avian2d::plugin::AvianReplicationMode;
 use lightyear::avian2d::prelude::LightyearAvianPlugin;
@@ -13,6 +15,8 @@ impl Plugin for GameNetworking {
             GameClientPlugin,
             GameProtocolPlugin,
             GameServerPlugin,
+            NetworkConditionerPlugin,
+            NetStatsPlugin,
         ));
 
         app.add_plugins(LightyearAvianPlugin {
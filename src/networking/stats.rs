@@ -0,0 +1,116 @@
+use crate::networking::client::GameClient;
+use bevy::prelude::*;
+use lightyear::link::Link;
+use lightyear::prelude::server::ClientOf;
+use lightyear::prelude::{InputTimeline, LinkSystems};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Samples are taken this often, and the graph holds [`NET_STATS_HISTORY`] of them.
+pub const NET_STATS_SAMPLE_SECS: f32 = 0.25;
+pub const NET_STATS_HISTORY: usize = 20;
+
+/// Raised by the rollback checks registered in the protocol when a predicted
+/// value strays far enough to be corrected. Lightyear runs every check of a
+/// frame in one pass and rolls back once for all of them, however many hit.
+pub static ROLLBACK_TRIGGERED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NetSample {
+    pub rtt_ms: f32,
+    pub jitter_ms: f32,
+    /// Between 0 and 1.
+    pub packet_loss: f32,
+    pub bytes_in_per_sec: f32,
+    pub bytes_out_per_sec: f32,
+    pub input_delay_ticks: u16,
+    pub rollbacks_per_sec: f32,
+}
+
+/// Recent samples of the link being measured: the client's own link, or on
+/// a server the client link with the worst round trip.
+#[derive(Resource, Default, Debug)]
+pub struct NetStats {
+    pub history: VecDeque<NetSample>,
+    /// Counts every sample taken, so readers can tell when a new one arrived.
+    pub samples_taken: u64,
+    bytes_in: usize,
+    bytes_out: usize,
+    rollbacks: u32,
+    since_sample: f32,
+}
+
+impl NetStats {
+    pub fn latest(&self) -> Option<&NetSample> {
+        self.history.back()
+    }
+}
+
+pub struct NetStatsPlugin;
+
+impl Plugin for NetStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetStats>()
+            .add_systems(PreUpdate, s_count_bytes_in.after(LinkSystems::Receive))
+            .add_systems(PostUpdate, s_count_bytes_out.before(LinkSystems::Send))
+            .add_systems(Update, (s_count_rollbacks, s_sample_net_stats).chain());
+    }
+}
+
+fn s_count_bytes_in(links: Query<&Link, Or<(With<GameClient>, With<ClientOf>)>>, mut stats: ResMut<NetStats>) {
+    let bytes: usize = links.iter().flat_map(|link| link.recv.iter()).map(|payload| payload.len()).sum();
+    stats.bytes_in += bytes;
+}
+
+fn s_count_bytes_out(links: Query<&Link, Or<(With<GameClient>, With<ClientOf>)>>, mut stats: ResMut<NetStats>) {
+    let bytes: usize = links.iter().flat_map(|link| link.send.iter()).map(|payload| payload.len()).sum();
+    stats.bytes_out += bytes;
+}
+
+fn s_count_rollbacks(mut stats: ResMut<NetStats>) {
+    if ROLLBACK_TRIGGERED.swap(false, Ordering::Relaxed) {
+        stats.rollbacks += 1;
+    }
+}
+
+pub fn s_sample_net_stats(
+    links: Query<(&Link, Option<&InputTimeline>), Or<(With<GameClient>, With<ClientOf>)>>,
+    time: Res<Time>,
+    mut stats: ResMut<NetStats>,
+) {
+    stats.since_sample += time.delta_secs();
+    if stats.since_sample < NET_STATS_SAMPLE_SECS {
+        return;
+    }
+
+    let elapsed = stats.since_sample;
+    stats.since_sample = 0.0;
+
+    let rollbacks = std::mem::take(&mut stats.rollbacks);
+    let bytes_in = std::mem::take(&mut stats.bytes_in);
+    let bytes_out = std::mem::take(&mut stats.bytes_out);
+
+    let Some((link, timeline)) = links
+        .iter()
+        .max_by_key(|(link, _)| link.stats.rtt)
+    else {
+        stats.history.clear();
+        return;
+    };
+
+    stats.history.push_back(NetSample {
+        rtt_ms: link.stats.rtt.as_secs_f32() * 1000.0,
+        jitter_ms: link.stats.jitter.as_secs_f32() * 1000.0,
+        packet_loss: link.stats.packet_loss,
+        bytes_in_per_sec: bytes_in as f32 / elapsed,
+        bytes_out_per_sec: bytes_out as f32 / elapsed,
+        input_delay_ticks: timeline.map_or(0, |timeline| timeline.input_delay()),
+        rollbacks_per_sec: rollbacks as f32 / elapsed,
+    });
+
+    stats.samples_taken += 1;
+
+    while stats.history.len() > NET_STATS_HISTORY {
+        stats.history.pop_front();
+    }
+}
@@ -1,15 +1,17 @@
 use crate::bundles::player::PlayerBundle;
 use crate::bundles::*;
-use crate::components::Player;
+use crate::components::{GamepadNumber, OrphanedPaddle, Paddle, Player};
 use crate::models::game::area::LocalPlayerID;
 use crate::resources::controls::MenuAction;
-use crate::resources::{GameModeConfig, OnlineGameConfig};
-use crate::systems::menu::{spawn_m_main, u_join_in, u_settings_visibility};
+use crate::plugins::gameplay::GameplayPlugin;
+use crate::resources::{GameSettings, OnlineGameConfig, TeamMaterials, MAX_KEYBOARD_PLAYERS};
+use crate::components::MatchEntity;
+use crate::models::game::gameplay::PlayerId;
+use crate::systems::local_lobby::{u_local_lobby_input, u_local_lobby_list};
+use crate::systems::menu::{spawn_m_main, u_settings_visibility};
 use crate::systems::selectors::u_selector_text;
 use crate::systems::settings::monitor::on_spawn_monitors;
 use crate::systems::*;
-use avian2d::prelude::Gravity;
-use bevy::ecs::query::Spawned;
 use bevy::input_focus::directional_navigation::DirectionalNavigationMap;
 use bevy::input_focus::InputFocusVisible;
 
@@ -20,56 +22,115 @@ impl Plugin for GameCorePlugin {
         app.add_systems(
             Update,
             (
-                u_move_paddle_i,
+                u_reconnect_status,
+                u_sync_interpolated_transforms,
+                u_spectator_camera,
                 check_connection,
-                maintain_ball_speed,
                 u_score_ui,
+                u_paddle_identity,
+                u_team_roster_ui,
+                (u_ball_last_touch, u_team_tint).chain(),
                 u_selector_text,
-                u_join_in,
+                (u_local_lobby_input, u_local_lobby_list).chain(),
                 u_spawned_gamepads,
-                u_tilt_i,
+                u_keyboard_players,
+                (u_mouse_paddle, u_analog_paddle).before(u_move_paddle_i),
                 u_settings_visibility,
+                u_sound_volume,
             ),
         )
-        .add_systems(Startup, (setup_common,))
+        .add_plugins(GameplayPlugin)
+        .add_observer(t_spawn_score_ui)
+        .add_observer(t_spawn_goal_glow)
+        .add_observer(t_match_over)
+        .add_observer(t_spawn_match_visual)
+        .add_observer(t_gamepad_removed)
+        .add_observer(t_rumble_on_bounce)
+        .add_observer(t_rumble_on_goal)
+        .add_observer(t_profile_paddle_hit)
+        .add_observer(t_profile_match_over)
+        .add_observer(t_sound_ball_hit)
+        .add_observer(t_sound_point)
+        .add_observer(t_sound_serve)
+        .add_observer(t_sound_match_over)
+        .add_observer(t_sound_navigate)
+        .add_observer(t_sound_option)
+        .add_observer(t_sound_confirm)
+        .add_systems(Startup, (setup_common, setup_sounds))
         .add_systems(PostStartup, (on_spawn_monitors,))
-        .add_observer(paddle_hit_dynamics)
-        .add_observer(t_ball_events)
-        .insert_resource(GameModeConfig::default())
-        .insert_resource(Gravity::ZERO)
+        .init_resource::<TeamMaterials>()
         .insert_resource(InputFocusVisible(false))
         .insert_resource(OnlineGameConfig::default());
     }
 }
 
-fn u_spawned_gamepads(query: Query<Entity, (Spawned, With<Gamepad>)>, mut commands: Commands) {
+/// Gives every connected gamepad a player. A pad plugged back in mid-match
+/// takes its paddle back under its old number.
+fn u_spawned_gamepads(
+    query: Query<Entity, Added<Gamepad>>,
+    numbers: Query<&GamepadNumber>,
+    mut orphans: Query<(Entity, &mut Paddle, &OrphanedPaddle)>,
+    settings: Res<GameSettings>,
+    mut commands: Commands,
+) {
+    let mut taken: Vec<u8> = numbers.iter().map(|number| number.0).collect();
+    taken.extend(orphans.iter().map(|(_, _, orphan)| orphan.number));
+
     for entity in query.iter() {
-        commands.entity(entity).observe(on_despawn_gamepad);
-        commands.spawn(PlayerBundle::new(LocalPlayerID::Gamepad(entity)));
+        let id = LocalPlayerID::Gamepad(entity);
+        let mut number = (1..=u8::MAX).find(|n| !taken.contains(n)).unwrap_or(u8::MAX);
+
+        if let Some((paddle_entity, mut paddle, orphan)) = orphans.iter_mut().find(|(_, _, orphan)| orphan.gamepad == entity) {
+            number = orphan.number;
+            paddle.id = PlayerId::Local(id);
+            commands.entity(paddle_entity).remove::<OrphanedPaddle>();
+        }
+
+        taken.push(number);
+
+        commands.spawn(PlayerBundle::new_gamepad(entity, number, &settings.bindings));
     }
 }
 
-fn on_despawn_gamepad(
-    despawn: On<Despawn>,
-    mut players: Query<(Entity, &mut Player)>,
+fn setup_common(
     mut commands: Commands,
+    mut map: ResMut<DirectionalNavigationMap>,
+    settings: Res<GameSettings>,
 ) {
-    for (player_entity, player) in players.iter_mut() {
-        if let LocalPlayerID::Gamepad(entity) = player.id.local() {
-            if entity == despawn.entity {
-                commands.entity(player_entity).despawn();
-            }
-        }
-    }
+    commands.spawn(CameraBundle::default());
+    commands.spawn(PlayerBundle::new(LocalPlayerID::Mouse, &settings.bindings));
+    commands.spawn(MenuAction::input_map());
+    spawn_m_main(&mut commands, map.as_mut());
 }
 
-fn setup_common(mut commands: Commands, mut map: ResMut<DirectionalNavigationMap>) {
-    commands.spawn(CameraBundle::default());
+/// Spawns and removes keyboard players to match the settings. Left alone
+/// during a match, the change applies once it is over.
+fn u_keyboard_players(
+    settings: Res<GameSettings>,
+    players: Query<(Entity, &Player)>,
+    match_entities: Query<(), With<MatchEntity>>,
+    mut commands: Commands,
+) {
+    if !match_entities.is_empty() {
+        return;
+    }
+
+    let wanted = settings.keyboard_players.min(MAX_KEYBOARD_PLAYERS);
+    let mut present = Vec::with_capacity(wanted as usize);
 
-    for i in 1..=2 {
-        commands.spawn(PlayerBundle::new(LocalPlayerID::Keyboard(i)));
+    for (entity, player) in players.iter() {
+        if let PlayerId::Local(LocalPlayerID::Keyboard(id)) = player.id {
+            if id > wanted {
+                commands.entity(entity).despawn();
+            } else {
+                present.push(id);
+            }
+        }
     }
 
-    commands.spawn(MenuAction::input_map());
-    spawn_m_main(&mut commands, map.as_mut());
+    for id in 1..=wanted {
+        if !present.contains(&id) {
+            commands.spawn(PlayerBundle::new(LocalPlayerID::Keyboard(id), &settings.bindings));
+        }
+    }
 }
@@ -2,8 +2,13 @@
 use crate::components::ui::Menu;
 use crate::events::gameplay::UINavigated;
 use crate::events::widgets::{SliderValueChanged, TextInputSubmitted};
-use crate::systems::menu::u_server_list;
+use crate::resources::ServerBrowserConfig;
+use crate::systems::menu::{t_address_resolved, t_close_lobby_on_match, t_connection_established, t_return_to_lobby, t_connection_failed, u_lobby_player_list, u_lobby_settings_display, u_lobby_start_state, u_server_list, u_server_ping_text};
 use crate::systems::widgets::*;
+use crate::networking::chat::{t_log_chat, ChatLog};
+use crate::systems::net_stats::{u_net_stats_overlay, u_toggle_net_stats};
+use crate::systems::controls::{u_capture_rebind, u_rebind_labels};
+use crate::systems::chat::{u_chat_blocks_input, u_chat_log, u_chat_overlay};
 use crate::utils::FIXED_DIMENSIONS;
 use bevy::ecs::relationship::Relationship;
 use bevy::input_focus::directional_navigation::DirectionalNavigation;
@@ -28,10 +33,28 @@ impl Plugin for GameUIPlugin {
                 u_button_press,
                 t_input_submit,
                 u_server_list,
+                u_server_ping_text,
+                u_lobby_player_list,
+                u_lobby_settings_display,
+                u_lobby_start_state,
+                u_chat_log,
+                u_chat_overlay,
+                u_chat_blocks_input,
+                u_toggle_net_stats,
+                u_net_stats_overlay,
+                (u_capture_rebind, u_rebind_labels).chain(),
                 u_disabled_timeout
             ),
         )
+        .init_resource::<ServerBrowserConfig>()
+        .init_resource::<ChatLog>()
+        .add_observer(t_log_chat)
         .add_observer(handle_invisible_nav)
+        .add_observer(t_connection_established)
+        .add_observer(t_connection_failed)
+        .add_observer(t_address_resolved)
+        .add_observer(t_close_lobby_on_match)
+        .add_observer(t_return_to_lobby)
         .add_observer(t_slider_change)
         .add_observer(update_selector);
     }
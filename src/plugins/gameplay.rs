@@ -0,0 +1,30 @@
+use crate::resources::GameModeConfig;
+use crate::systems::*;
+use avian2d::prelude::Gravity;
+use bevy::prelude::*;
+
+/// Match simulation without any presentation, shared by the game and the
+/// dedicated server.
+pub struct GameplayPlugin;
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                u_move_paddle_i,
+                u_pilot_absent_paddles,
+                maintain_ball_speed,
+                u_tilt_i,
+                u_match_teardown,
+            ),
+        )
+        .add_observer(paddle_hit_dynamics)
+        .add_observer(t_ball_events)
+        .add_observer(t_paddle_identity)
+        .add_observer(t_last_touch)
+        .add_observer(t_serve_clears_last_touch)
+        .insert_resource(GameModeConfig::default())
+        .insert_resource(Gravity::ZERO);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod game_core;
 pub mod game_ui;
+pub mod gameplay;
 
 pub use game_core::*;
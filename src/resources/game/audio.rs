@@ -0,0 +1,15 @@
+use bevy::audio::AudioSource;
+use bevy::prelude::{Handle, Resource};
+
+/// Every clip the game plays, synthesised once at startup.
+#[derive(Resource)]
+pub struct SoundBank {
+    pub paddle_hit: Handle<AudioSource>,
+    pub wall_bounce: Handle<AudioSource>,
+    pub goal: Handle<AudioSource>,
+    pub serve: Handle<AudioSource>,
+    pub menu_navigate: Handle<AudioSource>,
+    pub menu_confirm: Handle<AudioSource>,
+    pub win_jingle: Handle<AudioSource>,
+    pub music: Handle<AudioSource>,
+}
@@ -0,0 +1,487 @@
+use crate::components::Player;
+use crate::resources::{PlayerAction, MAX_GAMEPAD_PLAYERS};
+use bevy::prelude::{GamepadAxis, GamepadButton, KeyCode, Reflect, Resource, Timer, TimerMode};
+use leafwing_input_manager::prelude::{Buttonlike, InputMap, VirtualAxis};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How long a rebind waits for a press before giving up.
+pub const REBIND_TIMEOUT_SECS: f32 = 5.0;
+/// Keeps the press that ended a capture from starting the next one.
+pub const REBIND_SETTLE_SECS: f32 = 0.25;
+
+/// A player slot with its own layout. Gamepads go by their
+/// [`GamepadNumber`](crate::components::GamepadNumber).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BindingSlot {
+    Keyboard(u8),
+    Gamepad(u8),
+}
+
+/// One rebindable row of a layout: a button action, one end of a keyboard
+/// axis or the stick driving an axis.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BindingTarget {
+    Button(PlayerAction),
+    Positive(PlayerAction),
+    Negative(PlayerAction),
+    /// Both axes of the stick drive the action.
+    Stick(PlayerAction),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StickSide {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BoundInput {
+    Key(KeyCode),
+    Button(GamepadButton),
+    Stick(StickSide),
+}
+
+const KEYBOARD_TARGETS: [BindingTarget; 7] = [
+    BindingTarget::Positive(PlayerAction::Move),
+    BindingTarget::Negative(PlayerAction::Move),
+    BindingTarget::Positive(PlayerAction::Tilt),
+    BindingTarget::Negative(PlayerAction::Tilt),
+    BindingTarget::Button(PlayerAction::Dash),
+    BindingTarget::Button(PlayerAction::Push),
+    BindingTarget::Button(PlayerAction::Pause),
+];
+
+const GAMEPAD_TARGETS: [BindingTarget; 5] = [
+    BindingTarget::Stick(PlayerAction::Move),
+    BindingTarget::Stick(PlayerAction::Tilt),
+    BindingTarget::Button(PlayerAction::Dash),
+    BindingTarget::Button(PlayerAction::Push),
+    BindingTarget::Button(PlayerAction::Pause),
+];
+
+impl BindingSlot {
+    /// Slots in use with this many players on the keyboard, and every
+    /// gamepad player.
+    pub fn active(keyboard_players: u8) -> Vec<BindingSlot> {
+        (1..=keyboard_players)
+            .map(BindingSlot::Keyboard)
+            .chain((1..=MAX_GAMEPAD_PLAYERS).map(BindingSlot::Gamepad))
+            .collect()
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            BindingSlot::Keyboard(id) => format!("Keyboard {id}"),
+            BindingSlot::Gamepad(number) => format!("Gamepad {number}"),
+        }
+    }
+
+    pub fn targets(self) -> &'static [BindingTarget] {
+        match self {
+            BindingSlot::Keyboard(_) => &KEYBOARD_TARGETS,
+            BindingSlot::Gamepad(_) => &GAMEPAD_TARGETS,
+        }
+    }
+
+    /// Keyboard layouts share one keyboard, so their keys clash with each
+    /// other, while every gamepad is its own device.
+    fn shares_inputs_with(self, other: BindingSlot) -> bool {
+        match (self, other) {
+            (BindingSlot::Keyboard(_), BindingSlot::Keyboard(_)) => true,
+            (BindingSlot::Gamepad(a), BindingSlot::Gamepad(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    fn default_map(self) -> InputMap<PlayerAction> {
+        match self {
+            BindingSlot::Keyboard(id) => Player::get_keyboard_input_map(id),
+            BindingSlot::Gamepad(_) => Player::get_gamepad_input_map(),
+        }
+    }
+}
+
+impl BindingTarget {
+    pub fn action(self) -> PlayerAction {
+        match self {
+            BindingTarget::Button(action)
+            | BindingTarget::Positive(action)
+            | BindingTarget::Negative(action)
+            | BindingTarget::Stick(action) => action,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BindingTarget::Positive(PlayerAction::Move) => "Move +",
+            BindingTarget::Negative(PlayerAction::Move) => "Move -",
+            BindingTarget::Positive(PlayerAction::Tilt) => "Tilt +",
+            BindingTarget::Negative(PlayerAction::Tilt) => "Tilt -",
+            other => match other.action() {
+                PlayerAction::Move => "Move",
+                PlayerAction::Tilt => "Tilt",
+                PlayerAction::Dash => "Dash",
+                PlayerAction::Push => "Push",
+                PlayerAction::Pause => "Pause",
+            },
+        }
+    }
+
+    /// Whether a press of `input` can be bound here at all.
+    pub fn accepts(self, input: BoundInput) -> bool {
+        match self {
+            BindingTarget::Button(_) => !matches!(input, BoundInput::Stick(_)),
+            BindingTarget::Positive(_) | BindingTarget::Negative(_) => matches!(input, BoundInput::Key(_)),
+            BindingTarget::Stick(_) => matches!(input, BoundInput::Stick(_)),
+        }
+    }
+}
+
+impl StickSide {
+    pub fn axes(self) -> [GamepadAxis; 2] {
+        match self {
+            StickSide::Left => [GamepadAxis::LeftStickX, GamepadAxis::LeftStickY],
+            StickSide::Right => [GamepadAxis::RightStickX, GamepadAxis::RightStickY],
+        }
+    }
+
+    fn of(axis: GamepadAxis) -> Option<Self> {
+        match axis {
+            GamepadAxis::LeftStickX | GamepadAxis::LeftStickY => Some(StickSide::Left),
+            GamepadAxis::RightStickX | GamepadAxis::RightStickY => Some(StickSide::Right),
+            _ => None,
+        }
+    }
+}
+
+impl BoundInput {
+    pub fn label(self) -> String {
+        match self {
+            BoundInput::Key(key) => {
+                let name = format!("{key:?}");
+
+                // KeyW and Digit1 read better as W and 1
+                name.strip_prefix("Key")
+                    .or_else(|| name.strip_prefix("Digit"))
+                    .filter(|rest| !rest.is_empty())
+                    .unwrap_or(&name)
+                    .to_owned()
+            }
+            BoundInput::Button(button) => format!("{button:?}"),
+            BoundInput::Stick(StickSide::Left) => "Left Stick".to_owned(),
+            BoundInput::Stick(StickSide::Right) => "Right Stick".to_owned(),
+        }
+    }
+}
+
+/// Layouts the player changed, kept in the settings file. Slots missing
+/// here use the built in defaults.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct ControlBindings {
+    #[serde(default)]
+    pub keyboards: BTreeMap<u8, InputMap<PlayerAction>>,
+    /// By [`GamepadNumber`](crate::components::GamepadNumber). Saved
+    /// without a gamepad, each player's pad is attached when spawned.
+    #[serde(default)]
+    pub gamepads: BTreeMap<u8, InputMap<PlayerAction>>,
+}
+
+impl ControlBindings {
+    pub fn input_map(&self, slot: BindingSlot) -> InputMap<PlayerAction> {
+        let saved = match slot {
+            BindingSlot::Keyboard(id) => self.keyboards.get(&id),
+            BindingSlot::Gamepad(number) => self.gamepads.get(&number),
+        };
+
+        saved.cloned().unwrap_or_else(|| slot.default_map())
+    }
+
+    pub fn get(&self, slot: BindingSlot, target: BindingTarget) -> Option<BoundInput> {
+        read_input(&self.input_map(slot), target)
+    }
+
+    /// The row among `slots` already using `input` where it would fire
+    /// alongside `target`.
+    pub fn find_conflict(
+        &self,
+        slots: &[BindingSlot],
+        slot: BindingSlot,
+        target: BindingTarget,
+        input: BoundInput,
+    ) -> Option<(BindingSlot, BindingTarget)> {
+        slots
+            .iter()
+            .copied()
+            .filter(|other| slot.shares_inputs_with(*other))
+            .flat_map(|other| other.targets().iter().map(move |t| (other, *t)))
+            .filter(|&row| row != (slot, target))
+            .find(|&(s, t)| self.get(s, t) == Some(input))
+    }
+
+    /// Binds `input` to `target`. A conflicting row takes over what `target`
+    /// had before, so the two swap and nothing ends up bound twice.
+    pub fn rebind(
+        &mut self,
+        slots: &[BindingSlot],
+        slot: BindingSlot,
+        target: BindingTarget,
+        input: BoundInput,
+    ) -> Option<(BindingSlot, BindingTarget)> {
+        let previous = self.get(slot, target);
+        let conflict = self.find_conflict(slots, slot, target, input);
+
+        if let (Some((other_slot, other_target)), Some(previous)) = (conflict, previous) {
+            self.set(other_slot, other_target, previous);
+        }
+
+        self.set(slot, target, input);
+        conflict
+    }
+
+    pub fn restore_defaults(&mut self) {
+        *self = Self::default();
+    }
+
+    fn set(&mut self, slot: BindingSlot, target: BindingTarget, input: BoundInput) {
+        let mut map = self.input_map(slot);
+        let action = target.action();
+
+        match (target, input) {
+            (BindingTarget::Button(_), BoundInput::Key(key)) => {
+                map.clear_action(&action);
+                map.insert(action, key);
+            }
+            (BindingTarget::Button(_), BoundInput::Button(button)) => {
+                map.clear_action(&action);
+                map.insert(action, button);
+            }
+            (BindingTarget::Positive(_) | BindingTarget::Negative(_), BoundInput::Key(key)) => {
+                let positive = matches!(target, BindingTarget::Positive(_));
+                let other_end = if positive {
+                    BindingTarget::Negative(action)
+                } else {
+                    BindingTarget::Positive(action)
+                };
+
+                let Some(BoundInput::Key(other)) = read_input(&map, other_end) else { return };
+                let (negative, positive) = if positive { (other, key) } else { (key, other) };
+
+                map.clear_action(&action);
+                map.insert_axis(action, VirtualAxis::new(negative, positive));
+            }
+            (BindingTarget::Stick(_), BoundInput::Stick(side)) => {
+                map.clear_action(&action);
+                for axis in side.axes() {
+                    map.insert_axis(action, axis);
+                }
+            }
+            _ => return,
+        }
+
+        match slot {
+            BindingSlot::Keyboard(id) => {
+                self.keyboards.insert(id, map);
+            }
+            BindingSlot::Gamepad(number) => {
+                self.gamepads.insert(number, map);
+            }
+        }
+    }
+}
+
+fn read_input(map: &InputMap<PlayerAction>, target: BindingTarget) -> Option<BoundInput> {
+    match target {
+        BindingTarget::Button(action) => bound_button(map.get_buttonlike(&action)?.first()?.as_ref()),
+        BindingTarget::Positive(action) | BindingTarget::Negative(action) => {
+            let axis = map
+                .get_axislike(&action)?
+                .iter()
+                .find_map(|axis| Reflect::as_any(axis.as_ref()).downcast_ref::<VirtualAxis>())?;
+
+            let end = match target {
+                BindingTarget::Positive(_) => &axis.positive,
+                _ => &axis.negative,
+            };
+
+            bound_button(end.as_ref())
+        }
+        BindingTarget::Stick(action) => map
+            .get_axislike(&action)?
+            .iter()
+            .filter_map(|axis| Reflect::as_any(axis.as_ref()).downcast_ref::<GamepadAxis>())
+            .find_map(|axis| StickSide::of(*axis))
+            .map(BoundInput::Stick),
+    }
+}
+
+fn bound_button(button: &dyn Buttonlike) -> Option<BoundInput> {
+    let any = Reflect::as_any(button);
+
+    any.downcast_ref::<KeyCode>()
+        .map(|key| BoundInput::Key(*key))
+        .or_else(|| any.downcast_ref::<GamepadButton>().map(|button| BoundInput::Button(*button)))
+}
+
+/// Row of the controls page waiting for a press.
+#[derive(Resource, Debug)]
+pub struct RebindCapture {
+    pub slot: BindingSlot,
+    pub target: BindingTarget,
+    /// Runs out when nothing was pressed in time, or once a press was taken
+    /// the capture lingers briefly so it cannot start again at once.
+    pub timer: Timer,
+    pub captured: bool,
+}
+
+impl RebindCapture {
+    pub fn new(slot: BindingSlot, target: BindingTarget) -> Self {
+        Self {
+            slot,
+            target,
+            timer: Timer::from_seconds(REBIND_TIMEOUT_SECS, TimerMode::Once),
+            captured: false,
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.captured = true;
+        self.timer = Timer::from_seconds(REBIND_SETTLE_SECS, TimerMode::Once);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOVE_UP: BindingTarget = BindingTarget::Positive(PlayerAction::Move);
+    const MOVE_DOWN: BindingTarget = BindingTarget::Negative(PlayerAction::Move);
+    const DASH: BindingTarget = BindingTarget::Button(PlayerAction::Dash);
+    const PUSH: BindingTarget = BindingTarget::Button(PlayerAction::Push);
+
+    fn slots() -> Vec<BindingSlot> {
+        BindingSlot::active(2)
+    }
+
+    #[test]
+    fn defaults_read_back() {
+        let bindings = ControlBindings::default();
+
+        assert_eq!(bindings.get(BindingSlot::Keyboard(1), MOVE_UP), Some(BoundInput::Key(KeyCode::KeyW)));
+        assert_eq!(bindings.get(BindingSlot::Keyboard(1), MOVE_DOWN), Some(BoundInput::Key(KeyCode::KeyS)));
+        assert_eq!(bindings.get(BindingSlot::Keyboard(2), PUSH), Some(BoundInput::Key(KeyCode::Enter)));
+        assert_eq!(
+            bindings.get(BindingSlot::Gamepad(1), BindingTarget::Stick(PlayerAction::Move)),
+            Some(BoundInput::Stick(StickSide::Left)),
+        );
+    }
+
+    #[test]
+    fn free_input_binds_without_conflict() {
+        let mut bindings = ControlBindings::default();
+        let slot = BindingSlot::Keyboard(1);
+
+        assert_eq!(bindings.rebind(&slots(), slot, MOVE_UP, BoundInput::Key(KeyCode::KeyQ)), None);
+        assert_eq!(bindings.get(slot, MOVE_UP), Some(BoundInput::Key(KeyCode::KeyQ)));
+        // the other end of the axis is kept
+        assert_eq!(bindings.get(slot, MOVE_DOWN), Some(BoundInput::Key(KeyCode::KeyS)));
+    }
+
+    #[test]
+    fn conflict_in_the_same_layout_swaps() {
+        let mut bindings = ControlBindings::default();
+        let slot = BindingSlot::Keyboard(1);
+
+        let conflict = bindings.rebind(&slots(), slot, DASH, BoundInput::Key(KeyCode::Space));
+
+        assert_eq!(conflict, Some((slot, PUSH)));
+        assert_eq!(bindings.get(slot, DASH), Some(BoundInput::Key(KeyCode::Space)));
+        assert_eq!(bindings.get(slot, PUSH), Some(BoundInput::Key(KeyCode::ControlLeft)));
+    }
+
+    #[test]
+    fn conflict_across_keyboard_layouts_swaps() {
+        let mut bindings = ControlBindings::default();
+        let first = BindingSlot::Keyboard(1);
+        let second = BindingSlot::Keyboard(2);
+
+        let conflict = bindings.rebind(&slots(), first, MOVE_UP, BoundInput::Key(KeyCode::ArrowUp));
+
+        assert_eq!(conflict, Some((second, MOVE_UP)));
+        assert_eq!(bindings.get(first, MOVE_UP), Some(BoundInput::Key(KeyCode::ArrowUp)));
+        assert_eq!(bindings.get(second, MOVE_UP), Some(BoundInput::Key(KeyCode::KeyW)));
+        assert_eq!(bindings.get(second, MOVE_DOWN), Some(BoundInput::Key(KeyCode::ArrowDown)));
+    }
+
+    #[test]
+    fn inactive_layouts_do_not_conflict() {
+        let bindings = ControlBindings::default();
+
+        let conflict = bindings.find_conflict(
+            &BindingSlot::active(1),
+            BindingSlot::Keyboard(1),
+            MOVE_UP,
+            BoundInput::Key(KeyCode::ArrowUp),
+        );
+
+        assert_eq!(conflict, None);
+    }
+
+    #[test]
+    fn rebinding_to_the_current_input_is_no_conflict() {
+        let bindings = ControlBindings::default();
+
+        let conflict = bindings.find_conflict(&slots(), BindingSlot::Keyboard(1), MOVE_UP, BoundInput::Key(KeyCode::KeyW));
+
+        assert_eq!(conflict, None);
+    }
+
+    #[test]
+    fn sticks_swap_on_the_gamepad() {
+        let mut bindings = ControlBindings::default();
+        let movement = BindingTarget::Stick(PlayerAction::Move);
+        let tilt = BindingTarget::Stick(PlayerAction::Tilt);
+
+        let pad = BindingSlot::Gamepad(1);
+
+        let conflict = bindings.rebind(&slots(), pad, movement, BoundInput::Stick(StickSide::Right));
+
+        assert_eq!(conflict, Some((pad, tilt)));
+        assert_eq!(bindings.get(pad, movement), Some(BoundInput::Stick(StickSide::Right)));
+        assert_eq!(bindings.get(pad, tilt), Some(BoundInput::Stick(StickSide::Left)));
+    }
+
+    #[test]
+    fn gamepads_keep_their_own_layouts() {
+        let mut bindings = ControlBindings::default();
+        let movement = BindingTarget::Stick(PlayerAction::Move);
+        let (first, second) = (BindingSlot::Gamepad(1), BindingSlot::Gamepad(2));
+
+        let conflict = bindings.rebind(&slots(), second, movement, BoundInput::Stick(StickSide::Right));
+
+        assert_eq!(conflict, Some((second, BindingTarget::Stick(PlayerAction::Tilt))));
+        assert_eq!(bindings.get(second, movement), Some(BoundInput::Stick(StickSide::Right)));
+        assert_eq!(bindings.get(first, movement), Some(BoundInput::Stick(StickSide::Left)));
+    }
+
+    #[test]
+    fn unaccepted_inputs_are_ignored() {
+        let mut bindings = ControlBindings::default();
+        let slot = BindingSlot::Keyboard(1);
+
+        assert!(!MOVE_UP.accepts(BoundInput::Stick(StickSide::Left)));
+        bindings.rebind(&slots(), slot, MOVE_UP, BoundInput::Stick(StickSide::Left));
+
+        assert_eq!(bindings.get(slot, MOVE_UP), Some(BoundInput::Key(KeyCode::KeyW)));
+    }
+
+    #[test]
+    fn restoring_defaults_forgets_every_change() {
+        let mut bindings = ControlBindings::default();
+        bindings.rebind(&slots(), BindingSlot::Keyboard(1), DASH, BoundInput::Key(KeyCode::KeyQ));
+
+        bindings.restore_defaults();
+
+        assert_eq!(bindings, ControlBindings::default());
+    }
+}
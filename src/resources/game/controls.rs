@@ -22,6 +22,8 @@ pub enum MenuAction {
     Cancel,
     #[actionlike(DualAxis)]
     Navigate,
+    OpenChat,
+    ToggleNetStats,
 }
 
 impl MenuAction {
@@ -31,6 +33,8 @@ impl MenuAction {
         map.insert(MenuAction::Confirm, KeyCode::Enter);
         map.insert(MenuAction::Confirm, KeyCode::Space);
         map.insert(MenuAction::Cancel, KeyCode::Escape);
+        map.insert(MenuAction::OpenChat, KeyCode::KeyT);
+        map.insert(MenuAction::ToggleNetStats, KeyCode::F3);
 
         map.insert(MenuAction::Confirm, GamepadButton::South);
         map.insert(MenuAction::Cancel, GamepadButton::East);
@@ -44,3 +48,31 @@ impl MenuAction {
         map
     }
 }
+
+#[derive(Actionlike, Clone, Copy, Debug, Reflect, PartialEq, Eq, Hash)]
+pub enum SpectatorAction {
+    #[actionlike(DualAxis)]
+    Pan,
+    #[actionlike(Axis)]
+    Zoom,
+    CycleFollow,
+}
+
+impl SpectatorAction {
+    pub fn input_map() -> InputMap<Self> {
+        let mut map = InputMap::default();
+
+        map.insert_dual_axis(SpectatorAction::Pan, VirtualDPad::wasd());
+        map.insert_dual_axis(SpectatorAction::Pan, VirtualDPad::arrow_keys());
+        map.insert_dual_axis(SpectatorAction::Pan, GamepadStick::LEFT);
+
+        map.insert_axis(SpectatorAction::Zoom, MouseScrollAxis::Y);
+        map.insert_axis(SpectatorAction::Zoom, VirtualAxis::new(KeyCode::KeyQ, KeyCode::KeyE));
+        map.insert_axis(SpectatorAction::Zoom, VirtualAxis::new(GamepadButton::LeftTrigger, GamepadButton::RightTrigger));
+
+        map.insert(SpectatorAction::CycleFollow, KeyCode::Tab);
+        map.insert(SpectatorAction::CycleFollow, GamepadButton::North);
+
+        map
+    }
+}
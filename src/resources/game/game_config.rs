@@ -1,20 +1,353 @@
 use std::net::SocketAddrV4;
 use crate::components::ui::UIOptionString;
-use crate::models::game::area::AreaShape;
+use crate::components::SoundChannel;
+use crate::models::game::area::{AreaShape, AreaSide};
 use crate::models::game::gameplay::GameMode;
-use bevy::prelude::{Deref, Resource, UVec2};
+use crate::networking::protocol::JoinRole;
+use crate::resources::ControlBindings;
+use bevy::prelude::{Color, Deref, Resource, UVec2};
 use bevy::window::{MonitorSelection, PresentMode, VideoMode, WindowMode};
 use derive_more::{From, Into};
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 #[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct GameSettings {
     pub master_volume: f32,
     pub sfx_volume: f32,
+    #[serde(default = "default_music_volume")]
+    pub music_volume: f32,
     pub window_mode: WindowMode,
     pub window_resolution: Option<UVec2>,
     pub vsync: PresentMode,
+    #[serde(default)]
+    pub recent_addresses: Vec<String>,
+    /// Identity presented to servers, lets a dropped player reclaim their
+    /// slot. Missing from older files until [`load_settings`] fills it in.
+    ///
+    /// [`load_settings`]: crate::systems::settings::persistence::load_settings
+    #[serde(default)]
+    pub client_id: Option<u64>,
+    /// Identity of this instance alone, never saved. See [`client_id_from_args`].
+    #[serde(skip)]
+    pub client_id_override: Option<u64>,
+    #[serde(default)]
+    pub bindings: ControlBindings,
+    /// Players sharing the keyboard, each with a layout of their own.
+    #[serde(default = "default_keyboard_players")]
+    pub keyboard_players: u8,
+    #[serde(default)]
+    pub mouse: MouseSettings,
+    /// Stick handling of each gamepad player, by [`GamepadNumber`](crate::components::GamepadNumber).
+    #[serde(default)]
+    pub analog: Vec<AnalogSettings>,
+    #[serde(default)]
+    pub rumble: RumbleSettings,
+    #[serde(default)]
+    pub team_palette: TeamPalette,
+}
+
+impl GameSettings {
+    /// Identity this instance connects with.
+    pub fn client_id(&self) -> u64 {
+        self.client_id_override.or(self.client_id).unwrap_or_default()
+    }
+
+    /// Linear volume of a channel, the master slider scaling it.
+    pub fn volume(&self, channel: SoundChannel) -> f32 {
+        let channel = match channel {
+            SoundChannel::Effects => self.sfx_volume,
+            SoundChannel::Music => self.music_volume,
+        };
+
+        (self.master_volume / 100.0 * channel / 100.0).clamp(0.0, 1.0)
+    }
+}
+
+fn default_music_volume() -> f32 {
+    50.0
+}
+
+pub fn generate_client_id() -> u64 {
+    // zero is what an unset id looks like, never hand it out
+    RandomState::new().hash_one(SystemTime::now()).max(1)
+}
+
+/// `--client-id <id>` gives this instance an identity of its own instead
+/// of the saved one, `--client-id random` a fresh one every run. Two
+/// instances started from one directory need it, or the second reclaims
+/// the first one's slot on the server.
+pub fn client_id_from_args(mut args: impl Iterator<Item = String>) -> Result<Option<u64>, String> {
+    while let Some(flag) = args.next() {
+        if flag != "--client-id" {
+            continue;
+        }
+
+        let value = args.next().ok_or_else(|| format!("{flag} expects a value"))?;
+
+        return match value.as_str() {
+            "random" => Ok(Some(generate_client_id())),
+            _ => match value.parse::<u64>() {
+                Ok(0) | Err(_) => Err(format!("{flag} expects a non-zero number or \"random\", got {value}")),
+                Ok(id) => Ok(Some(id)),
+            },
+        };
+    }
+
+    Ok(None)
+}
+
+pub const MAX_RECENT_ADDRESSES: usize = 5;
+pub const MAX_KEYBOARD_PLAYERS: u8 = 4;
+
+fn default_keyboard_players() -> u8 {
+    2
+}
+
+/// How the mouse player's paddle follows the mouse.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum MouseControl {
+    /// Mouse motion pushes the paddle, like a trackball.
+    #[default]
+    Relative,
+    /// The paddle lines up with the cursor.
+    Cursor,
+}
+
+impl UIOptionString for MouseControl {
+    fn push_ui_option_string(&self, string: &mut String) {
+        let s = match self {
+            MouseControl::Relative => "Relative",
+            MouseControl::Cursor => "Cursor",
+        };
+        string.push_str(s);
+    }
+}
+
+pub const MIN_MOUSE_SENSITIVITY: f32 = 0.1;
+pub const MAX_MOUSE_SENSITIVITY: f32 = 5.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct MouseSettings {
+    pub control: MouseControl,
+    /// Scales mouse motion in relative mode, and the cursor's distance from
+    /// the middle of the arena in cursor mode.
+    pub sensitivity: f32,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            control: MouseControl::Relative,
+            sensitivity: 1.0,
+        }
+    }
+}
+
+pub const MAX_GAMEPAD_PLAYERS: u8 = 4;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    /// Squares the deflection, finer control near the middle of the stick.
+    Exponential,
+}
+
+impl UIOptionString for ResponseCurve {
+    fn push_ui_option_string(&self, string: &mut String) {
+        let s = match self {
+            ResponseCurve::Linear => "Linear",
+            ResponseCurve::Exponential => "Exponential",
+        };
+        string.push_str(s);
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum AnalogMode {
+    /// Stick deflection sets how fast the paddle moves.
+    #[default]
+    Speed,
+    /// Stick deflection sets where along its side the paddle sits.
+    Position,
+}
+
+impl UIOptionString for AnalogMode {
+    fn push_ui_option_string(&self, string: &mut String) {
+        let s = match self {
+            AnalogMode::Speed => "Speed",
+            AnalogMode::Position => "Position",
+        };
+        string.push_str(s);
+    }
+}
+
+pub const MAX_ANALOG_DEADZONE: f32 = 0.5;
+pub const MIN_ANALOG_SENSITIVITY: f32 = 0.25;
+pub const MAX_ANALOG_SENSITIVITY: f32 = 2.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AnalogSettings {
+    /// Deflection below this is ignored, the rest is stretched back to the full range.
+    pub deadzone: f32,
+    pub sensitivity: f32,
+    pub curve: ResponseCurve,
+    pub mode: AnalogMode,
+}
+
+impl Default for AnalogSettings {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.15,
+            sensitivity: 1.0,
+            curve: ResponseCurve::Linear,
+            mode: AnalogMode::Speed,
+        }
+    }
+}
+
+impl AnalogSettings {
+    /// Raw stick value to paddle input, between -1 and 1.
+    pub fn apply(&self, raw: f32) -> f32 {
+        let magnitude = raw.abs();
+        if magnitude <= self.deadzone {
+            return 0.0;
+        }
+
+        let scaled = ((magnitude - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+        let curved = match self.curve {
+            ResponseCurve::Linear => scaled,
+            ResponseCurve::Exponential => scaled * scaled,
+        };
+
+        (curved * self.sensitivity).min(1.0).copysign(raw)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RumbleSettings {
+    /// Turns rumble off for every gamepad at once.
+    pub enabled: bool,
+    /// Strength of each gamepad player's rumble between 0 and 1, by
+    /// [`GamepadNumber`](crate::components::GamepadNumber).
+    pub intensity: Vec<f32>,
+}
+
+impl Default for RumbleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            intensity: Vec::new(),
+        }
+    }
+}
+
+impl RumbleSettings {
+    /// Players who never changed theirs rumble at full strength.
+    pub fn intensity_for(&self, gamepad_number: u8) -> f32 {
+        gamepad_number
+            .checked_sub(1)
+            .and_then(|i| self.intensity.get(i as usize))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    pub fn intensity_for_mut(&mut self, gamepad_number: u8) -> &mut f32 {
+        let index = gamepad_number.saturating_sub(1) as usize;
+
+        if self.intensity.len() <= index {
+            self.intensity.resize(index + 1, 1.0);
+        }
+
+        &mut self.intensity[index]
+    }
+}
+
+/// Colours the teams are told apart by, on paddles, goals, score labels and
+/// the ball. Both colour-blind palettes keep every side distinguishable
+/// under the common kinds of colour blindness.
+#[derive(Clone, Copy, Eq, Hash, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum TeamPalette {
+    #[default]
+    Standard,
+    /// Okabe and Ito's palette.
+    OkabeIto,
+    /// Paul Tol's bright palette.
+    TolBright,
+}
+
+impl TeamPalette {
+    pub fn color(self, side: AreaSide) -> Color {
+        let [left, right, top, bottom] = match self {
+            TeamPalette::Standard => [
+                Color::srgb(0.9, 0.3, 0.3),
+                Color::srgb(0.3, 0.55, 0.95),
+                Color::srgb(0.35, 0.85, 0.4),
+                Color::srgb(0.95, 0.8, 0.25),
+            ],
+            TeamPalette::OkabeIto => [
+                Color::srgb_u8(0xD5, 0x5E, 0x00),
+                Color::srgb_u8(0x56, 0xB4, 0xE9),
+                Color::srgb_u8(0xF0, 0xE4, 0x42),
+                Color::srgb_u8(0xCC, 0x79, 0xA7),
+            ],
+            TeamPalette::TolBright => [
+                Color::srgb_u8(0xEE, 0x66, 0x77),
+                Color::srgb_u8(0x44, 0x77, 0xAA),
+                Color::srgb_u8(0xCC, 0xBB, 0x44),
+                Color::srgb_u8(0x66, 0xCC, 0xEE),
+            ],
+        };
+
+        match side {
+            AreaSide::Left => left,
+            AreaSide::Right => right,
+            AreaSide::Top => top,
+            AreaSide::Bottom => bottom,
+        }
+    }
+}
+
+impl UIOptionString for TeamPalette {
+    fn push_ui_option_string(&self, string: &mut String) {
+        let s = match self {
+            TeamPalette::Standard => "Standard",
+            TeamPalette::OkabeIto => "Colour-blind (Okabe-Ito)",
+            TeamPalette::TolBright => "Colour-blind (Tol)",
+        };
+        string.push_str(s);
+    }
+}
+
+impl GameSettings {
+    pub fn analog_for(&self, gamepad_number: u8) -> AnalogSettings {
+        gamepad_number
+            .checked_sub(1)
+            .and_then(|i| self.analog.get(i as usize))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn analog_for_mut(&mut self, gamepad_number: u8) -> &mut AnalogSettings {
+        let index = gamepad_number.saturating_sub(1) as usize;
+
+        if self.analog.len() <= index {
+            self.analog.resize(index + 1, AnalogSettings::default());
+        }
+
+        &mut self.analog[index]
+    }
+
+    pub fn remember_address(&mut self, address: &str) {
+        self.recent_addresses.retain(|recent| recent != address);
+        self.recent_addresses.insert(0, address.to_owned());
+        self.recent_addresses.truncate(MAX_RECENT_ADDRESSES);
+    }
 }
 
 #[derive(Resource, Clone, Eq, PartialEq, Debug)]
@@ -44,7 +377,57 @@ pub struct Monitors {
 pub struct OnlineGameConfig {
     pub server_name: String,
     pub pass: Option<String>,
+    /// Game port to listen on, zero lets the OS pick one.
+    pub port: u16,
+    /// Set by the headless server, nobody plays on the hosting machine.
+    pub dedicated: bool,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum ServerSortOrder {
+    #[default]
+    Ping,
+    Name,
+    Players,
 }
+
+impl UIOptionString for ServerSortOrder {
+    fn push_ui_option_string(&self, string: &mut String) {
+        let s = match self {
+            ServerSortOrder::Ping => "Ping",
+            ServerSortOrder::Name => "Name",
+            ServerSortOrder::Players => "Players",
+        };
+        string.push_str(s);
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum ServerFilter {
+    #[default]
+    All,
+    Joinable,
+    Unlocked,
+}
+
+impl UIOptionString for ServerFilter {
+    fn push_ui_option_string(&self, string: &mut String) {
+        let s = match self {
+            ServerFilter::All => "All",
+            ServerFilter::Joinable => "Joinable",
+            ServerFilter::Unlocked => "No Password",
+        };
+        string.push_str(s);
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct ServerBrowserConfig {
+    pub sort: ServerSortOrder,
+    pub filter: ServerFilter,
+    pub role: JoinRole,
+}
+
 impl UIOptionString for MonitorInfo {
     fn push_ui_option_string(&self, string: &mut String) {
         string.push_str(&*self.name);
@@ -100,9 +483,19 @@ impl Default for GameSettings {
         Self {
             master_volume: 50.0,
             sfx_volume: 50.0,
+            music_volume: default_music_volume(),
             window_mode: WindowMode::BorderlessFullscreen(MonitorSelection::Primary),
             vsync: PresentMode::AutoVsync,
             window_resolution: None,
+            recent_addresses: Vec::new(),
+            client_id: Some(generate_client_id()),
+            client_id_override: None,
+            bindings: ControlBindings::default(),
+            keyboard_players: default_keyboard_players(),
+            mouse: MouseSettings::default(),
+            analog: Vec::new(),
+            rumble: RumbleSettings::default(),
+            team_palette: TeamPalette::default(),
         }
     }
 }
@@ -143,3 +536,66 @@ impl GameModeConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn deadzone_swallows_small_deflection() {
+        let analog = AnalogSettings::default();
+
+        assert_eq!(analog.apply(0.0), 0.0);
+        assert_eq!(analog.apply(analog.deadzone), 0.0);
+        assert_eq!(analog.apply(-analog.deadzone), 0.0);
+    }
+
+    #[test]
+    fn outside_the_deadzone_is_stretched_to_the_full_range() {
+        let analog = AnalogSettings { deadzone: 0.2, ..AnalogSettings::default() };
+
+        assert!(close(analog.apply(0.6), 0.5));
+        assert!(close(analog.apply(1.0), 1.0));
+        assert!(close(analog.apply(-1.0), -1.0));
+    }
+
+    #[test]
+    fn sign_is_kept() {
+        let analog = AnalogSettings::default();
+
+        for raw in [0.3, 0.7, 1.0] {
+            assert!(close(analog.apply(-raw), -analog.apply(raw)));
+        }
+    }
+
+    #[test]
+    fn exponential_curve_softens_small_deflection() {
+        let linear = AnalogSettings { deadzone: 0.0, ..AnalogSettings::default() };
+        let exponential = AnalogSettings { curve: ResponseCurve::Exponential, ..linear };
+
+        assert!(close(exponential.apply(0.5), 0.25));
+        assert!(exponential.apply(0.5) < linear.apply(0.5));
+        assert!(close(exponential.apply(1.0), 1.0));
+    }
+
+    #[test]
+    fn sensitivity_scales_and_clamps() {
+        let analog = AnalogSettings { deadzone: 0.0, sensitivity: 2.0, ..AnalogSettings::default() };
+
+        assert!(close(analog.apply(0.25), 0.5));
+        assert!(close(analog.apply(0.9), 1.0));
+        assert!(close(analog.apply(-0.9), -1.0));
+    }
+
+    #[test]
+    fn overshooting_sticks_are_clamped() {
+        let analog = AnalogSettings { deadzone: 0.0, ..AnalogSettings::default() };
+
+        assert!(close(analog.apply(1.2), 1.0));
+        assert!(close(analog.apply(-1.2), -1.0));
+    }
+}
@@ -1,4 +1,12 @@
+pub mod audio;
+pub mod bindings;
 pub mod controls;
 pub mod game_config;
+pub mod profiles;
+pub mod team_materials;
+pub use audio::*;
+pub use bindings::*;
 pub use controls::*;
 pub use game_config::*;
+pub use profiles::*;
+pub use team_materials::*;
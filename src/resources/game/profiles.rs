@@ -0,0 +1,156 @@
+use crate::components::ui::UIOptionString;
+use crate::components::{PlayerIdentity, PLAYER_COLORS, PLAYER_NAMES};
+use crate::models::game::area::LocalPlayerID;
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Longer names are cut down to this many characters.
+pub const MAX_PROFILE_NAME_LEN: usize = 16;
+
+/// Device a profile is picked for first when it joins the local lobby.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum PreferredControls {
+    #[default]
+    Any,
+    Keyboard(u8),
+    Gamepad,
+    Mouse,
+}
+
+impl PreferredControls {
+    pub fn prefers(self, id: LocalPlayerID) -> bool {
+        match (self, id) {
+            (PreferredControls::Keyboard(layout), LocalPlayerID::Keyboard(id)) => layout == id,
+            (PreferredControls::Gamepad, LocalPlayerID::Gamepad(_)) => true,
+            (PreferredControls::Mouse, LocalPlayerID::Mouse) => true,
+            _ => false,
+        }
+    }
+}
+
+impl UIOptionString for PreferredControls {
+    fn push_ui_option_string(&self, string: &mut String) {
+        match self {
+            PreferredControls::Any => string.push_str("Any"),
+            PreferredControls::Keyboard(id) => write!(string, "Keyboard {id}").unwrap(),
+            PreferredControls::Gamepad => string.push_str("Gamepad"),
+            PreferredControls::Mouse => string.push_str("Mouse"),
+        }
+    }
+}
+
+/// Lifetime totals over every local match the profile played.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileStats {
+    pub matches_played: u32,
+    pub matches_won: u32,
+    pub paddle_hits: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct PlayerProfile {
+    pub name: String,
+    /// Index into [`PLAYER_COLORS`].
+    pub color: u8,
+    #[serde(default)]
+    pub controls: PreferredControls,
+    #[serde(default)]
+    pub stats: ProfileStats,
+}
+
+impl PlayerProfile {
+    pub fn identity(&self) -> PlayerIdentity {
+        PlayerIdentity {
+            name: self.name.clone(),
+            color: self.color,
+        }
+    }
+}
+
+/// Every local profile, kept in their own file next to the settings.
+#[derive(Resource, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Profiles {
+    pub profiles: Vec<PlayerProfile>,
+    /// Profile whose name and colour are sent to online servers.
+    #[serde(default)]
+    pub online: usize,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Self {
+            profiles: PLAYER_NAMES
+                .iter()
+                .enumerate()
+                .map(|(i, name)| PlayerProfile {
+                    name: (*name).to_owned(),
+                    color: (i % PLAYER_COLORS.len()) as u8,
+                    controls: PreferredControls::Any,
+                    stats: ProfileStats::default(),
+                })
+                .collect(),
+            online: 0,
+        }
+    }
+}
+
+impl Profiles {
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&PlayerProfile> {
+        self.profiles.get(index)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut PlayerProfile> {
+        self.profiles.get_mut(index)
+    }
+
+    pub fn online_identity(&self) -> Option<PlayerIdentity> {
+        self.get(self.online).map(PlayerProfile::identity)
+    }
+
+    /// Adds a fresh profile and returns its index.
+    pub fn add(&mut self) -> usize {
+        let number = (1..)
+            .find(|n| !self.profiles.iter().any(|profile| profile.name == format!("Player {n}")))
+            .unwrap_or(1);
+
+        self.profiles.push(PlayerProfile {
+            name: format!("Player {number}"),
+            color: (self.profiles.len() % PLAYER_COLORS.len()) as u8,
+            controls: PreferredControls::Any,
+            stats: ProfileStats::default(),
+        });
+
+        self.profiles.len() - 1
+    }
+
+    /// Removes a profile, the last one left is kept so there is always
+    /// someone to play as.
+    pub fn remove(&mut self, index: usize) {
+        if self.profiles.len() <= 1 || index >= self.profiles.len() {
+            return;
+        }
+
+        self.profiles.remove(index);
+
+        if self.online > index || self.online >= self.profiles.len() {
+            self.online = self.online.saturating_sub(1);
+        }
+    }
+}
+
+/// Trimmed and cut down to [`MAX_PROFILE_NAME_LEN`], `None` when nothing is left.
+pub fn sanitize_player_name(name: &str) -> Option<String> {
+    let name: String = name
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_PROFILE_NAME_LEN)
+        .collect();
+
+    let name = name.trim_end().to_owned();
+    (!name.is_empty()).then_some(name)
+}
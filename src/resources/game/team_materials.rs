@@ -0,0 +1,24 @@
+use crate::models::game::area::AreaSide;
+use crate::resources::TeamPalette;
+use bevy::prelude::{Assets, ColorMaterial, Handle, Resource};
+use std::collections::HashMap;
+
+/// One material per team colour in use, shared by every tinted mesh. The
+/// alpha is keyed by its bits, tints only ever use a few fixed values.
+#[derive(Resource, Default)]
+pub struct TeamMaterials(HashMap<(TeamPalette, AreaSide, u32), Handle<ColorMaterial>>);
+
+impl TeamMaterials {
+    pub fn get(
+        &mut self,
+        palette: TeamPalette,
+        side: AreaSide,
+        alpha: f32,
+        materials: &mut Assets<ColorMaterial>,
+    ) -> Handle<ColorMaterial> {
+        self.0
+            .entry((palette, side, alpha.to_bits()))
+            .or_insert_with(|| materials.add(palette.color(side).with_alpha(alpha)))
+            .clone()
+    }
+}
@@ -0,0 +1,230 @@
+use crate::components::{MusicTrack, SoundChannel};
+use crate::events::gameplay::{BallHit, BallServed, HitSurface, MatchOver, PointAwarded, UINavigated};
+use crate::events::widgets::{ButtonPressed, OptionChanged};
+use crate::resources::{GameSettings, SoundBank};
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+const SAMPLE_RATE: u32 = 22_050;
+/// Fade in of every note, keeps them from clicking.
+const ATTACK_SECS: f32 = 0.004;
+
+/// The music entity only gets a sink once there is a device to play on.
+/// Without one nothing is spawned, the clips would never finish and pile up.
+type AudioOutput<'w, 's> = Query<'w, 's, (), (With<MusicTrack>, With<AudioSink>)>;
+
+#[derive(Clone, Copy)]
+enum Wave {
+    Sine,
+    Square,
+    Triangle,
+}
+
+/// A note gliding from `from` to `to` Hz and fading out over its length.
+#[derive(Clone, Copy)]
+struct Note {
+    wave: Wave,
+    from: f32,
+    to: f32,
+    secs: f32,
+    gain: f32,
+}
+
+impl Note {
+    const fn new(wave: Wave, from: f32, to: f32, secs: f32, gain: f32) -> Self {
+        Self { wave, from, to, secs, gain }
+    }
+}
+
+pub fn setup_sounds(
+    settings: Res<GameSettings>,
+    mut sources: ResMut<Assets<AudioSource>>,
+    mut commands: Commands,
+) {
+    let mut clip = |notes: &[Note]| sources.add(wav(&render(notes)));
+
+    let bank = SoundBank {
+        paddle_hit: clip(&[Note::new(Wave::Square, 520.0, 440.0, 0.07, 0.5)]),
+        wall_bounce: clip(&[Note::new(Wave::Triangle, 260.0, 240.0, 0.05, 0.6)]),
+        goal: clip(&[Note::new(Wave::Square, 660.0, 180.0, 0.4, 0.45)]),
+        serve: clip(&[
+            Note::new(Wave::Sine, 880.0, 880.0, 0.1, 0.6),
+            Note::new(Wave::Sine, 0.0, 0.0, 0.06, 0.0),
+            Note::new(Wave::Sine, 1320.0, 1320.0, 0.16, 0.6),
+        ]),
+        menu_navigate: clip(&[Note::new(Wave::Triangle, 1200.0, 1100.0, 0.03, 0.35)]),
+        menu_confirm: clip(&[Note::new(Wave::Square, 660.0, 990.0, 0.09, 0.35)]),
+        win_jingle: clip(&[
+            Note::new(Wave::Square, 523.25, 523.25, 0.12, 0.4),
+            Note::new(Wave::Square, 659.25, 659.25, 0.12, 0.4),
+            Note::new(Wave::Square, 783.99, 783.99, 0.12, 0.4),
+            Note::new(Wave::Square, 1046.5, 1046.5, 0.45, 0.4),
+        ]),
+        music: sources.add(wav(&music())),
+    };
+
+    commands.spawn((
+        AudioPlayer::new(bank.music.clone()),
+        PlaybackSettings::LOOP.with_volume(Volume::Linear(settings.volume(SoundChannel::Music))),
+        SoundChannel::Music,
+        MusicTrack,
+    ));
+
+    commands.insert_resource(bank);
+}
+
+/// Moving a volume slider is heard right away, on what is already playing.
+pub fn u_sound_volume(settings: Res<GameSettings>, mut sinks: Query<(&mut AudioSink, &SoundChannel)>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (mut sink, channel) in sinks.iter_mut() {
+        sink.set_volume(Volume::Linear(settings.volume(*channel)));
+    }
+}
+
+pub fn t_sound_ball_hit(hit: On<BallHit>, bank: Res<SoundBank>, settings: Res<GameSettings>, output: AudioOutput, mut commands: Commands) {
+    let clip = match hit.surface {
+        HitSurface::Paddle => &bank.paddle_hit,
+        HitSurface::Wall => &bank.wall_bounce,
+    };
+
+    play(clip, &settings, &output, &mut commands);
+}
+
+pub fn t_sound_point(_: On<PointAwarded>, bank: Res<SoundBank>, settings: Res<GameSettings>, output: AudioOutput, mut commands: Commands) {
+    play(&bank.goal, &settings, &output, &mut commands);
+}
+
+/// Every serve is marked with a short double beep. Balls are served the
+/// moment a point is scored, there is no countdown before it to give a cue.
+pub fn t_sound_serve(_: On<BallServed>, bank: Res<SoundBank>, settings: Res<GameSettings>, output: AudioOutput, mut commands: Commands) {
+    play(&bank.serve, &settings, &output, &mut commands);
+}
+
+pub fn t_sound_match_over(_: On<MatchOver>, bank: Res<SoundBank>, settings: Res<GameSettings>, output: AudioOutput, mut commands: Commands) {
+    play(&bank.win_jingle, &settings, &output, &mut commands);
+}
+
+pub fn t_sound_navigate(_: On<UINavigated>, bank: Res<SoundBank>, settings: Res<GameSettings>, output: AudioOutput, mut commands: Commands) {
+    play(&bank.menu_navigate, &settings, &output, &mut commands);
+}
+
+pub fn t_sound_option(_: On<OptionChanged>, bank: Res<SoundBank>, settings: Res<GameSettings>, output: AudioOutput, mut commands: Commands) {
+    play(&bank.menu_navigate, &settings, &output, &mut commands);
+}
+
+pub fn t_sound_confirm(_: On<ButtonPressed>, bank: Res<SoundBank>, settings: Res<GameSettings>, output: AudioOutput, mut commands: Commands) {
+    play(&bank.menu_confirm, &settings, &output, &mut commands);
+}
+
+fn play(clip: &Handle<AudioSource>, settings: &GameSettings, output: &AudioOutput, commands: &mut Commands) {
+    let volume = settings.volume(SoundChannel::Effects);
+
+    if output.is_empty() || volume <= 0.0 {
+        return;
+    }
+
+    commands.spawn((
+        AudioPlayer::new(clip.clone()),
+        PlaybackSettings::DESPAWN.with_volume(Volume::Linear(volume)),
+        SoundChannel::Effects,
+    ));
+}
+
+/// Plays the notes one after another.
+fn render(notes: &[Note]) -> Vec<f32> {
+    let mut samples = Vec::new();
+
+    for note in notes {
+        let len = (note.secs * SAMPLE_RATE as f32) as usize;
+        let mut phase = 0.0;
+
+        for i in 0..len {
+            let progress = i as f32 / len as f32;
+            let freq = note.from + (note.to - note.from) * progress;
+            let attack = (i as f32 / (ATTACK_SECS * SAMPLE_RATE as f32)).min(1.0);
+            let decay = (1.0 - progress).powi(2);
+
+            samples.push(oscillate(note.wave, phase) * note.gain * attack * decay);
+            phase = (phase + freq / SAMPLE_RATE as f32).fract();
+        }
+    }
+
+    samples
+}
+
+/// Eight bars of a slow arpeggio over Am, F, C and G with a soft bass,
+/// made to loop seamlessly.
+fn music() -> Vec<f32> {
+    const BAR_SECS: f32 = 2.0;
+    const STEPS: usize = 8;
+    const CHORDS: [[f32; 3]; 4] = [
+        [220.0, 261.63, 329.63],
+        [174.61, 220.0, 261.63],
+        [261.63, 329.63, 392.0],
+        [196.0, 246.94, 293.66],
+    ];
+
+    let bar_len = (BAR_SECS * SAMPLE_RATE as f32) as usize;
+    let step_len = bar_len / STEPS;
+    let mut samples = vec![0.0; bar_len * CHORDS.len() * 2];
+
+    for (bar, out) in samples.chunks_mut(bar_len).enumerate() {
+        let chord = CHORDS[bar % CHORDS.len()];
+
+        for (i, sample) in out.iter_mut().enumerate() {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let step = i / step_len;
+            let in_step = (i % step_len) as f32 / step_len as f32;
+            let note = chord[[0, 1, 2, 1][step % 4]] * 2.0;
+
+            let bass = (TAU * chord[0] / 2.0 * t).sin() * 0.18;
+            let bass_swell = (PI * i as f32 / bar_len as f32).sin();
+            let attack = (in_step * step_len as f32 / (ATTACK_SECS * SAMPLE_RATE as f32)).min(1.0);
+            let lead = oscillate(Wave::Triangle, (note * t).fract()) * 0.16 * attack * (1.0 - in_step).powi(2);
+
+            *sample = bass * bass_swell + lead;
+        }
+    }
+
+    samples
+}
+
+/// One period of `wave`, `phase` running from 0 to 1.
+fn oscillate(wave: Wave, phase: f32) -> f32 {
+    match wave {
+        Wave::Sine => (TAU * phase).sin(),
+        Wave::Square => if phase < 0.5 { 0.6 } else { -0.6 },
+        Wave::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+    }
+}
+
+/// Mono 16-bit PCM in a WAV container, the format the audio backend decodes.
+fn wav(samples: &[f32]) -> AudioSource {
+    let data_len = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    AudioSource { bytes: bytes.into() }
+}
@@ -1,7 +1,99 @@
-use crate::bundles::Query;
-use bevy::ecs::query::Spawned;
-use bevy::prelude::{Commands, Entity, Gamepad};
+use crate::bundles::widgets::LabelBundle;
+use crate::components::ui::ControllerDisconnectedText;
+use crate::components::{GamepadNumber, OrphanedPaddle, Paddle, Player};
+use crate::models::game::area::LocalPlayerID;
+use crate::models::game::gameplay::PlayerId;
+use crate::resources::PlayerAction;
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
 
-pub fn check_connection(mut query: Query<(Entity, &Gamepad), Spawned>, mut commands: Commands) {
-    for (entity, gamepad) in query {}
+/// Drops the player of an unplugged gamepad. A paddle they had in a match
+/// is kept, waiting in [`check_connection`] for a new owner.
+pub fn t_gamepad_removed(
+    removed: On<Remove, Gamepad>,
+    players: Query<(Entity, &Player, Option<&GamepadNumber>)>,
+    paddles: Query<(Entity, &Paddle), Without<OrphanedPaddle>>,
+    mut commands: Commands,
+) {
+    let id = PlayerId::Local(LocalPlayerID::Gamepad(removed.entity));
+
+    for (player_entity, player, number) in players.iter() {
+        if player.id != id {
+            continue;
+        }
+
+        if let Some((paddle_entity, _)) = paddles.iter().find(|(_, paddle)| paddle.id == id) {
+            commands.entity(paddle_entity).insert(OrphanedPaddle {
+                gamepad: removed.entity,
+                number: number.map_or(0, |number| number.0),
+            });
+        }
+
+        commands.entity(player_entity).despawn();
+    }
+}
+
+/// Holds the match while any paddle has lost its gamepad. Any device
+/// without a paddle takes the first one over by pressing something.
+pub fn check_connection(
+    mut orphans: Query<(Entity, &mut Paddle, &OrphanedPaddle)>,
+    owned: Query<&Paddle, Without<OrphanedPaddle>>,
+    players: Query<(&Player, &ActionState<PlayerAction>)>,
+    prompt: Option<Single<(Entity, &mut Text), With<ControllerDisconnectedText>>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut commands: Commands,
+) {
+    let mut waiting: Vec<_> = orphans.iter_mut().collect();
+    waiting.sort_by_key(|(_, _, orphan)| orphan.number);
+
+    for (player, action_state) in players.iter() {
+        if waiting.is_empty() {
+            break;
+        }
+
+        if action_state.get_just_pressed().is_empty() || owned.iter().any(|paddle| paddle.id == player.id) {
+            continue;
+        }
+
+        let (entity, mut paddle, _) = waiting.remove(0);
+        paddle.id = player.id;
+        commands.entity(entity).remove::<OrphanedPaddle>();
+    }
+
+    let status = waiting
+        .iter()
+        .map(|(_, _, orphan)| {
+            format!(
+                "Controller {} disconnected - reconnect it or press a button on another device",
+                orphan.number
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    match prompt {
+        Some(prompt) if status.is_empty() => {
+            commands.entity(prompt.0).despawn();
+            time.unpause();
+        }
+        Some(mut prompt) => {
+            if prompt.1 .0 != status {
+                prompt.1 .0 = status;
+            }
+        }
+        None if status.is_empty() => {}
+        None => {
+            commands.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(40.0),
+                    left: Val::Px(20.0),
+                    ..default()
+                },
+                LabelBundle::custom(&status, Color::srgb(1.0, 0.8, 0.3), 40.0),
+                ControllerDisconnectedText,
+            ));
+            time.pause();
+        }
+    }
 }
@@ -1,52 +1,145 @@
+use crate::bundles::widgets::LabelBundle;
 use crate::bundles::BallBundle;
-use crate::components::ui::ScoreText;
+use crate::components::ui::{ScoreText, UIOptionString};
 use crate::components::*;
-use crate::resources::GameModeConfig;
+use crate::events::gameplay::{BallServed, MatchEnded, MatchOver, PointAwarded};
+use crate::networking::client::GameClient;
+use crate::networking::lobby::LobbySeat;
+use crate::resources::GameSettings;
 use crate::utils::screen::BALL_RADIUS;
 use avian2d::prelude::*;
 use bevy::prelude::*;
 
+const SERVE_VELOCITY: Vec2 = Vec2::new(-300.0, 300.0);
+/// How long the winner stays on screen before the match is torn down.
+const MATCH_OVER_SECS: f32 = 4.0;
+
 pub fn handle_scoring(
     collision: On<CollisionStart>,
     goals: Query<&Goal>,
-    mut game_config: ResMut<GameModeConfig>,
+    mut score: Single<&mut MatchScore>,
+    clients: Query<(), With<GameClient>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut commands: Commands,
 ) {
+    // points only ever come from the peer simulating the match
+    if !clients.is_empty() {
+        return;
+    }
+
     let ball = collision.collider1;
     let other = collision.collider2;
 
-    if let Ok(goal) = goals.get(other) {
-        if let Some(team) = game_config.area_shape.get_team_mut(goal.side) {
-            team.current_score += 1;
+    let Ok(goal) = goals.get(other) else { return };
+    if score.winner.is_some() {
+        return;
+    }
+
+    let Some(points) = score.award_point(goal.side) else { return };
+
+    commands.entity(ball).despawn();
+    commands.trigger(PointAwarded {
+        side: goal.side,
+        score: points,
+    });
 
-            commands.entity(ball).despawn();
+    if let Some(winner) = score.winner {
+        commands.trigger(MatchOver { winner });
+        commands.spawn((
+            MatchTeardown(Timer::from_seconds(MATCH_OVER_SECS, TimerMode::Once)),
+            MatchEntity,
+        ));
+        return;
+    }
+
+    commands
+        .spawn((
+            BallBundle::new(
+                &mut meshes,
+                &mut materials,
+                Vec3::ZERO,
+                SERVE_VELOCITY,
+                BALL_RADIUS,
+            ),
+            MatchEntity,
+        ))
+        .observe(handle_scoring);
+
+    commands.trigger(BallServed {
+        velocity: SERVE_VELOCITY,
+    });
+}
+
+/// Score labels follow the match score, which clients get from the server,
+/// so they work the same whether the match runs locally or remotely.
+pub fn t_spawn_score_ui(
+    added: On<Add, MatchScore>,
+    scores: Query<&MatchScore>,
+    mut commands: Commands,
+) {
+    let Ok(score) = scores.get(added.entity) else { return };
 
-            commands
-                .spawn(BallBundle::new(
-                    &mut meshes,
-                    &mut materials,
-                    Vec3::ZERO,
-                    Vec2::new(-300.0, 300.0),
-                    BALL_RADIUS,
-                ))
-                .observe(handle_scoring);
-        }
+    for (side, points) in &score.scores {
+        side.spawn_score_text(*points, &mut commands);
     }
 }
 
 pub fn u_score_ui(
-    mut game_config: ResMut<GameModeConfig>,
+    score: Option<Single<&MatchScore, Changed<MatchScore>>>,
     mut score_texts: Query<(&mut Text, &ScoreText)>,
 ) {
-    if !game_config.is_changed() {
+    let Some(score) = score else { return };
+
+    for (mut text, score_text) in score_texts.iter_mut() {
+        text.0 = score.get(score_text.area_side).to_string();
+    }
+}
+
+/// Despawns the finished match, which replication mirrors on clients, and
+/// sends everyone back to the lobby to ready up for the next one.
+pub fn u_match_teardown(
+    time: Res<Time>,
+    teardown: Option<Single<&mut MatchTeardown>>,
+    match_entities: Query<Entity, With<MatchEntity>>,
+    mut seats: Query<&mut LobbySeat>,
+    mut commands: Commands,
+) {
+    let Some(mut teardown) = teardown else { return };
+
+    if !teardown.0.tick(time.delta()).just_finished() {
         return;
     }
 
-    for (mut text, score_text) in score_texts.iter_mut() {
-        if let Some(team) = game_config.area_shape.get_team(score_text.area_side) {
-            text.0 = team.current_score.to_string();
-        }
+    for entity in match_entities.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    for mut seat in seats.iter_mut() {
+        seat.ready = false;
     }
+
+    commands.trigger(MatchEnded);
+}
+
+pub fn t_match_over(over: On<MatchOver>, settings: Res<GameSettings>, mut commands: Commands) {
+    let mut banner = String::new();
+    over.winner.push_ui_option_string(&mut banner);
+    banner.push_str(" side wins!");
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(40.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        MatchEntity,
+        children![LabelBundle::custom(
+            &banner,
+            settings.team_palette.color(over.winner),
+            80.0,
+        )],
+    ));
 }
@@ -0,0 +1,159 @@
+use crate::components::ui::{PaddleNameTag, TeamRosterText};
+use crate::components::*;
+use crate::events::gameplay::{BallBounced, MatchOver};
+use crate::models::game::area::AreaSide;
+use crate::networking::lobby::HOST_PLAYER_ID;
+use crate::resources::Profiles;
+use crate::systems::settings::persistence::write_profiles;
+use crate::utils::PADDLE_SIZE;
+use bevy::prelude::*;
+use bevy::text::FontSmoothing;
+use lightyear::prelude::server::Server;
+
+/// Gap between the end of a paddle and its name tag.
+const NAME_TAG_GAP: f32 = 18.0;
+
+/// Paddles take the name and colour of the player steering them, so the
+/// server replicates them along with the paddle. The host of a listen
+/// server plays as its online profile, other paddles without a player
+/// identity stay anonymous.
+pub fn t_paddle_identity(
+    added: On<Add, Paddle>,
+    paddles: Query<&Paddle>,
+    players: Query<(&Player, &PlayerIdentity)>,
+    server: Option<Single<(), With<Server>>>,
+    profiles: Option<Res<Profiles>>,
+    mut commands: Commands,
+) {
+    let Ok(paddle) = paddles.get(added.entity) else { return };
+
+    let identity = match players.iter().find(|(player, _)| player.id == paddle.id) {
+        Some((_, identity)) => identity.clone(),
+        None if server.is_some() && paddle.id == HOST_PLAYER_ID => {
+            let Some(identity) = profiles.and_then(|profiles| profiles.online_identity()) else { return };
+            identity
+        }
+        None => return,
+    };
+
+    commands.entity(added.entity).insert(identity);
+}
+
+/// Hangs the player's name next to their paddle in their profile colour,
+/// on the playing peer and on every client alike. The paddle itself wears
+/// its team's colour.
+pub fn u_paddle_identity(
+    paddles: Query<
+        (Entity, &PlayerIdentity, Option<&Paddle>, Option<&MatchVisual>, Option<&Children>),
+        (Changed<PlayerIdentity>, With<Mesh2d>),
+    >,
+    goals: Query<&Goal>,
+    tags: Query<(), With<PaddleNameTag>>,
+    mut commands: Commands,
+) {
+    for (entity, identity, paddle, visual, children) in paddles.iter() {
+        let Some(side) = paddle_side(paddle, visual, &goals) else { continue };
+
+        for child in children.iter().flat_map(|children| children.iter()) {
+            if tags.contains(child) {
+                commands.entity(child).despawn();
+            }
+        }
+
+        // horizontal paddles are the vertical ones turned sideways, tags
+        // always sit on the side facing the field
+        let offset = match side {
+            AreaSide::Left | AreaSide::Right => PADDLE_SIZE.y / 2.0 + NAME_TAG_GAP,
+            AreaSide::Bottom => PADDLE_SIZE.x / 2.0 + NAME_TAG_GAP,
+            AreaSide::Top => -(PADDLE_SIZE.x / 2.0 + NAME_TAG_GAP),
+        };
+
+        commands.entity(entity).with_child((
+            Text2d::new(identity.name.clone()),
+            TextFont {
+                font_size: 18.0,
+                font_smoothing: FontSmoothing::None,
+                ..default()
+            },
+            TextColor(identity.color()),
+            Transform::from_xyz(0.0, offset, 1.0),
+            PaddleNameTag,
+        ));
+    }
+}
+
+/// Lists who plays on each side under its score.
+pub fn u_team_roster_ui(
+    paddles: Query<(&PlayerIdentity, Option<&Paddle>, Option<&MatchVisual>)>,
+    changed: Query<(), Changed<PlayerIdentity>>,
+    added_texts: Query<(), Added<TeamRosterText>>,
+    mut removed: RemovedComponents<PlayerIdentity>,
+    goals: Query<&Goal>,
+    mut texts: Query<(&mut Text, &TeamRosterText)>,
+) {
+    let any_removed = !removed.is_empty();
+    removed.clear();
+
+    if changed.is_empty() && added_texts.is_empty() && !any_removed {
+        return;
+    }
+
+    for (mut text, roster) in texts.iter_mut() {
+        text.0 = paddles
+            .iter()
+            .filter(|(_, paddle, visual)| paddle_side(*paddle, *visual, &goals) == Some(roster.area_side))
+            .map(|(identity, ..)| identity.name.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
+
+/// Counts a hit for the profile of the local player whose paddle it was.
+pub fn t_profile_paddle_hit(
+    bounce: On<BallBounced>,
+    paddles: Query<&Paddle>,
+    players: Query<(&Player, &ActiveProfile)>,
+    mut profiles: ResMut<Profiles>,
+) {
+    let Ok(paddle) = paddles.get(bounce.paddle) else { return };
+    let Some((_, active)) = players.iter().find(|(player, _)| player.id == paddle.id) else { return };
+
+    if let Some(profile) = profiles.get_mut(active.0) {
+        profile.stats.paddle_hits += 1;
+    }
+}
+
+/// Books the finished match on every profile that played in it and saves them.
+pub fn t_profile_match_over(
+    over: On<MatchOver>,
+    paddles: Query<&Paddle>,
+    goals: Query<&Goal>,
+    players: Query<(&Player, &ActiveProfile)>,
+    mut profiles: ResMut<Profiles>,
+) {
+    let mut booked = false;
+
+    for (player, active) in players.iter() {
+        let Some(paddle) = paddles.iter().find(|paddle| paddle.id == player.id) else { continue };
+        let Some(profile) = profiles.get_mut(active.0) else { continue };
+
+        profile.stats.matches_played += 1;
+        if goals.get(paddle.goal).is_ok_and(|goal| goal.side == over.winner) {
+            profile.stats.matches_won += 1;
+        }
+        booked = true;
+    }
+
+    if booked {
+        write_profiles(&profiles);
+    }
+}
+
+/// Side of a simulated paddle, or of one replicated from the server.
+fn paddle_side(paddle: Option<&Paddle>, visual: Option<&MatchVisual>, goals: &Query<&Goal>) -> Option<AreaSide> {
+    match (paddle, visual) {
+        (_, Some(MatchVisual::Paddle { side, .. })) => Some(*side),
+        (Some(paddle), _) => goals.get(paddle.goal).ok().map(|goal| goal.side),
+        _ => None,
+    }
+}
@@ -1,9 +1,16 @@
 pub mod game_modes;
 pub mod handle_gamepads;
 pub mod handle_scoring;
+pub mod identity;
 pub mod movement;
-mod online;
+pub mod online;
+pub mod rumble;
+pub mod team_colors;
 
 pub use handle_gamepads::*;
 pub use handle_scoring::*;
+pub use identity::*;
 pub use movement::*;
+pub use online::*;
+pub use rumble::*;
+pub use team_colors::*;
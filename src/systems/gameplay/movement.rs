@@ -1,30 +1,48 @@
 use crate::components::game_modes::MAX_ABS_TILT;
 use crate::components::*;
-use crate::events::gameplay::{BallBounced, GoalScored};
+use crate::events::gameplay::{BallBounced, BallHit, GoalScored, HitSurface};
+use crate::models::game::area::LocalPlayerID;
+use crate::models::game::gameplay::PlayerId;
+use crate::resources::{AnalogMode, BindingSlot, BindingTarget, BoundInput, GameSettings, MouseControl};
 use crate::resources::controls::*;
 use crate::utils::screen::PADDLE_SIZE;
-use crate::utils::HALF_HEIGHT;
+use crate::utils::{HALF_HEIGHT, HALF_WIDTH};
 use avian2d::prelude::*;
+use bevy::input::mouse::AccumulatedMouseMotion;
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
 use leafwing_input_manager::prelude::*;
 use std::time::Duration;
 
 const BALL_SPEED: f32 = 600.0;
+const PADDLE_SPEED: f32 = 600.0;
+const PILOT_SPEED: f32 = 450.0;
 
 pub fn u_move_paddle_i(
-    player_query: Query<(&ActionState<PlayerAction>, &Player)>,
+    player_query: Query<(&ActionState<PlayerAction>, &Player, Option<&PlayerConnection>)>,
     mut paddle_query: Query<(&mut Transform, &Paddle)>,
+    goals: Query<&Goal>,
     time: Res<Time>,
 ) {
     for (mut transform, paddle) in paddle_query.iter_mut() {
-        for (action_state, player) in player_query {
+        for (action_state, player, connection) in player_query {
             if player.id.eq(&paddle.id) {
-                if let Some(data) = action_state.axis_data(&PlayerAction::Move) {
-                    transform.translation.y += 600.0 * time.delta_secs() * data.update_value;
+                // the last inputs of a dropped player would keep pushing the paddle
+                if connection.is_some_and(|c| c.is_reconnecting()) {
+                    break;
+                }
 
+                if let Some(data) = action_state.axis_data(&PlayerAction::Move) {
+                    let step = PADDLE_SPEED * time.delta_secs() * data.update_value;
                     let half_paddle_height = PADDLE_SIZE.y / 2.0;
-                    let limit = HALF_HEIGHT - half_paddle_height;
-                    transform.translation.y = transform.translation.y.clamp(-limit, limit);
+
+                    if is_horizontal_paddle(paddle, &goals) {
+                        let limit = HALF_WIDTH - half_paddle_height;
+                        transform.translation.x = (transform.translation.x + step).clamp(-limit, limit);
+                    } else {
+                        let limit = HALF_HEIGHT - half_paddle_height;
+                        transform.translation.y = (transform.translation.y + step).clamp(-limit, limit);
+                    }
                 }
                 break;
             }
@@ -32,6 +50,158 @@ pub fn u_move_paddle_i(
     }
 }
 
+#[inline]
+fn is_horizontal_paddle(paddle: &Paddle, goals: &Query<&Goal>) -> bool {
+    goals.get(paddle.goal).is_ok_and(|goal| goal.side.is_horizontal())
+}
+
+/// Turns mouse motion, or the cursor position, into the mouse player's move
+/// axis, scaled so [`u_move_paddle_i`] covers exactly that distance this frame.
+pub fn u_mouse_paddle(
+    settings: Res<GameSettings>,
+    motion: Res<AccumulatedMouseMotion>,
+    window: Option<Single<&Window, With<PrimaryWindow>>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut players: Query<(&Player, &mut ActionState<PlayerAction>)>,
+    paddles: Query<(&Transform, &Paddle)>,
+    goals: Query<&Goal>,
+    time: Res<Time>,
+) {
+    let full_step = PADDLE_SPEED * time.delta_secs();
+    if full_step <= 0.0 {
+        return;
+    }
+
+    let cursor = window
+        .and_then(|window| window.cursor_position())
+        .and_then(|position| {
+            cameras
+                .iter()
+                .find_map(|(camera, transform)| camera.viewport_to_world_2d(transform, position).ok())
+        });
+
+    let mouse = settings.mouse;
+
+    for (player, mut action_state) in players.iter_mut() {
+        if player.id != PlayerId::Local(LocalPlayerID::Mouse) {
+            continue;
+        }
+
+        let Some((transform, paddle)) = paddles.iter().find(|(_, paddle)| paddle.id == player.id) else { continue };
+        let horizontal = is_horizontal_paddle(paddle, &goals);
+
+        let distance = match mouse.control {
+            // screen y grows downwards, the arena's upwards
+            MouseControl::Relative if horizontal => motion.delta.x * mouse.sensitivity,
+            MouseControl::Relative => -motion.delta.y * mouse.sensitivity,
+            MouseControl::Cursor => match cursor {
+                Some(cursor) if horizontal => cursor.x * mouse.sensitivity - transform.translation.x,
+                Some(cursor) => cursor.y * mouse.sensitivity - transform.translation.y,
+                None => 0.0,
+            },
+        };
+
+        set_move_axis(&mut action_state, distance / full_step);
+    }
+}
+
+/// Drives gamepad paddles from the stick bound to Move through the player's
+/// analog settings. Only the stick axis the paddle slides along counts, so
+/// pushing diagonally no longer adds both axes together.
+pub fn u_analog_paddle(
+    settings: Res<GameSettings>,
+    gamepads: Query<&Gamepad>,
+    mut players: Query<(&Player, &GamepadNumber, &mut ActionState<PlayerAction>)>,
+    paddles: Query<(&Transform, &Paddle)>,
+    goals: Query<&Goal>,
+    time: Res<Time>,
+) {
+    let full_step = PADDLE_SPEED * time.delta_secs();
+    if full_step <= 0.0 {
+        return;
+    }
+
+    for (player, number, mut action_state) in players.iter_mut() {
+        let PlayerId::Local(LocalPlayerID::Gamepad(entity)) = player.id else { continue };
+        let Ok(gamepad) = gamepads.get(entity) else { continue };
+        let Some((transform, paddle)) = paddles.iter().find(|(_, paddle)| paddle.id == player.id) else { continue };
+
+        let Some(BoundInput::Stick(stick)) = settings
+            .bindings
+            .get(BindingSlot::Gamepad(number.0), BindingTarget::Stick(PlayerAction::Move))
+        else {
+            continue;
+        };
+
+        let [x_axis, y_axis] = stick.axes();
+
+        let horizontal = is_horizontal_paddle(paddle, &goals);
+        let analog = settings.analog_for(number.0);
+        let input = analog.apply(gamepad.get(if horizontal { x_axis } else { y_axis }).unwrap_or(0.0));
+
+        let value = match analog.mode {
+            AnalogMode::Speed => input,
+            AnalogMode::Position => {
+                let (half_extent, current) = if horizontal {
+                    (HALF_WIDTH, transform.translation.x)
+                } else {
+                    (HALF_HEIGHT, transform.translation.y)
+                };
+
+                (input * (half_extent - PADDLE_SIZE.y / 2.0) - current) / full_step
+            }
+        };
+
+        set_move_axis(&mut action_state, value);
+    }
+}
+
+/// Overrides what the input map read for Move this frame.
+fn set_move_axis(action_state: &mut ActionState<PlayerAction>, value: f32) {
+    let axis = action_state.axis_data_mut_or_default(&PlayerAction::Move);
+    axis.value = value;
+    axis.update_value = value;
+}
+
+/// Keeps the paddles of online players who dropped out, or never came back,
+/// tracking the ball so the match stays playable.
+pub fn u_pilot_absent_paddles(
+    player_query: Query<(&Player, &PlayerConnection)>,
+    mut paddle_query: Query<(&mut Transform, &Paddle), Without<Ball>>,
+    ball_query: Query<&Transform, With<Ball>>,
+    goals: Query<&Goal>,
+    time: Res<Time>,
+) {
+    let Some(ball) = ball_query.iter().next() else { return };
+
+    for (mut transform, paddle) in paddle_query.iter_mut() {
+        if !matches!(paddle.id, PlayerId::Network(_)) {
+            continue;
+        }
+
+        let present = player_query
+            .iter()
+            .any(|(player, connection)| player.id == paddle.id && !connection.is_reconnecting());
+
+        if present {
+            continue;
+        }
+
+        let step = PILOT_SPEED * time.delta_secs();
+        let half_paddle_height = PADDLE_SIZE.y / 2.0;
+
+        if is_horizontal_paddle(paddle, &goals) {
+            let offset = ball.translation.x - transform.translation.x;
+            let limit = HALF_WIDTH - half_paddle_height;
+            transform.translation.x = (transform.translation.x + offset.clamp(-step, step)).clamp(-limit, limit);
+        } else {
+            let offset = ball.translation.y - transform.translation.y;
+            let limit = HALF_HEIGHT - half_paddle_height;
+            transform.translation.y = (transform.translation.y + offset.clamp(-step, step)).clamp(-limit, limit);
+        }
+    }
+}
+
 pub fn u_tilt_i(
     player_query: Query<(&ActionState<PlayerAction>, &Player)>,
     mut paddle_query: Query<(Entity, &mut Transform, &Paddle)>,
@@ -82,15 +252,18 @@ pub fn paddle_hit_dynamics(
 
 pub fn t_ball_events(
     collision: On<CollisionStart>,
-    query: Query<(Option<&Paddle>, Option<&Goal>), With<Collider>>,
+    query: Query<(Option<&Paddle>, Option<&Goal>, Has<Wall>), With<Collider>>,
     mut commands: Commands,
 ) {
-    if let Ok((paddle, goal)) = query.get(collision.collider2) {
+    if let Ok((paddle, goal, wall)) = query.get(collision.collider2) {
         if paddle.is_some() {
             commands.trigger(BallBounced {
                 paddle: collision.collider2,
                 ball: collision.collider1,
             });
+            commands.trigger(BallHit { surface: HitSurface::Paddle });
+        } else if wall {
+            commands.trigger(BallHit { surface: HitSurface::Wall });
         } else if goal.is_some() {
             commands.trigger(GoalScored {
                 goal: collision.collider2,
@@ -1 +1,201 @@
+use crate::bundles::widgets::LabelBundle;
+use crate::components::ui::ReconnectStatusText;
+use crate::components::{MatchEntity, MatchVisual, PlayerConnection, PlayerIdentity, TeamTint};
+use crate::models::game::area::AreaSide;
+use crate::networking::client::GameClient;
+use crate::networking::protocol::{JoinRole, RemotePlayerId};
+use crate::resources::SpectatorAction;
+use avian2d::prelude::Position;
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use lightyear::prelude::Interpolated;
+
+const SPECTATOR_PAN_SPEED: f32 = 600.0;
+const SPECTATOR_FOLLOW_RATE: f32 = 5.0;
+const SPECTATOR_ZOOM_SPEED: f32 = 1.5;
+const SPECTATOR_MIN_ZOOM: f32 = 0.5;
+const SPECTATOR_MAX_ZOOM: f32 = 2.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CameraFollow {
+    #[default]
+    Free,
+    Team(AreaSide),
+}
+
+/// Present on the camera while watching a match as a spectator.
+#[derive(Component, Default)]
+pub struct SpectatorCamera {
+    pub follow: CameraFollow,
+}
+
 pub fn host_server() {}
+
+/// Shows who the match is waiting for, the banner goes away once every
+/// player is back or their slot was released.
+pub fn u_reconnect_status(
+    players: Query<(&RemotePlayerId, &PlayerConnection, Option<&PlayerIdentity>)>,
+    changed: Query<(), Changed<PlayerConnection>>,
+    mut removed: RemovedComponents<PlayerConnection>,
+    match_entities: Query<(), With<MatchEntity>>,
+    banner: Option<Single<(Entity, &mut Text), With<ReconnectStatusText>>>,
+    mut commands: Commands,
+) {
+    let any_removed = !removed.is_empty();
+    removed.clear();
+
+    if changed.is_empty() && !any_removed && banner.is_some() {
+        return;
+    }
+
+    let mut status = String::new();
+
+    for (id, connection, identity) in players.iter() {
+        if let PlayerConnection::Reconnecting { remaining_secs } = connection {
+            if !status.is_empty() {
+                status.push('\n');
+            }
+            status.push_str(&format!("{} reconnecting... {remaining_secs}s", id.name_with(identity)));
+        }
+    }
+
+    match banner {
+        Some(banner) if status.is_empty() => commands.entity(banner.0).despawn(),
+        Some(mut banner) => banner.1 .0 = status,
+        None if status.is_empty() || match_entities.is_empty() => {}
+        None => {
+            commands.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(20.0),
+                    left: Val::Px(20.0),
+                    ..default()
+                },
+                LabelBundle::custom(&status, Color::srgb(1.0, 0.8, 0.3), 32.0),
+                ReconnectStatusText,
+                MatchEntity,
+            ));
+        }
+    }
+}
+
+/// Replicated balls and paddles arrive with a position only, give them a mesh.
+pub fn t_spawn_match_visual(
+    added: On<Add, MatchVisual>,
+    visuals: Query<&MatchVisual, Without<Mesh2d>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    // the host already simulates these with full bundles
+    let Ok(visual) = visuals.get(added.entity) else { return };
+
+    let mesh = match *visual {
+        MatchVisual::Ball { radius } => meshes.add(Circle::new(radius)),
+        MatchVisual::Paddle { size, .. } => meshes.add(Rectangle::new(size.x, size.y)),
+    };
+
+    commands.entity(added.entity).insert((
+        Mesh2d(mesh),
+        MeshMaterial2d(materials.add(Color::WHITE)),
+        Transform::default(),
+        MatchEntity,
+    ));
+
+    if let MatchVisual::Paddle { side, .. } = *visual {
+        commands.entity(added.entity).insert(TeamTint::new(side));
+    }
+}
+
+pub fn u_sync_interpolated_transforms(
+    mut query: Query<(&Position, &mut Transform), (With<MatchVisual>, With<Interpolated>)>,
+) {
+    for (position, mut transform) in query.iter_mut() {
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+/// Free camera and per-team follow for spectators, the camera snaps back to
+/// the arena once they stop watching.
+pub fn u_spectator_camera(
+    client: Option<Single<&GameClient>>,
+    camera: Single<(Entity, &mut Transform, &mut Projection, Option<&mut SpectatorCamera>), With<Camera2d>>,
+    actions: Query<&ActionState<SpectatorAction>, With<Camera2d>>,
+    visuals: Query<(&Transform, &MatchVisual), Without<Camera2d>>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let spectating = client.is_some_and(|client| client.role == JoinRole::Spectator) && !visuals.is_empty();
+    let (entity, mut transform, mut projection, spectator) = camera.into_inner();
+
+    let Projection::Orthographic(ortho) = projection.as_mut() else { return };
+
+    let mut spectator = match (spectating, spectator) {
+        (true, Some(spectator)) => spectator,
+        (true, None) => {
+            commands
+                .entity(entity)
+                .insert((SpectatorCamera::default(), SpectatorAction::input_map()));
+            return;
+        }
+        (false, Some(_)) => {
+            commands
+                .entity(entity)
+                .remove::<(SpectatorCamera, InputMap<SpectatorAction>, ActionState<SpectatorAction>)>();
+            *transform = Transform::default();
+            ortho.scale = 1.0;
+            return;
+        }
+        (false, None) => return,
+    };
+
+    let Ok(action) = actions.get(entity) else { return };
+
+    if action.just_pressed(&SpectatorAction::CycleFollow) {
+        let mut sides: Vec<AreaSide> = visuals
+            .iter()
+            .filter_map(|(_, visual)| match visual {
+                MatchVisual::Paddle { side, .. } => Some(*side),
+                MatchVisual::Ball { .. } => None,
+            })
+            .collect();
+        sides.sort_by_key(|side| *side as u8);
+        sides.dedup();
+
+        spectator.follow = match spectator.follow {
+            CameraFollow::Free => sides.first().map_or(CameraFollow::Free, |side| CameraFollow::Team(*side)),
+            CameraFollow::Team(current) => sides
+                .iter()
+                .position(|side| *side == current)
+                .and_then(|i| sides.get(i + 1))
+                .map_or(CameraFollow::Free, |side| CameraFollow::Team(*side)),
+        };
+    }
+
+    let dt = time.delta_secs();
+
+    ortho.scale = (ortho.scale * (1.0 - action.value(&SpectatorAction::Zoom) * SPECTATOR_ZOOM_SPEED * dt))
+        .clamp(SPECTATOR_MIN_ZOOM, SPECTATOR_MAX_ZOOM);
+
+    match spectator.follow {
+        CameraFollow::Free => {
+            let pan = action.axis_pair(&SpectatorAction::Pan);
+            transform.translation += (pan * SPECTATOR_PAN_SPEED * ortho.scale * dt).extend(0.0);
+        }
+        CameraFollow::Team(followed) => {
+            let (sum, count) = visuals
+                .iter()
+                .filter(|(_, visual)| matches!(visual, MatchVisual::Paddle { side, .. } if *side == followed))
+                .fold((Vec2::ZERO, 0.0), |(sum, count), (paddle, _)| {
+                    (sum + paddle.translation.truncate(), count + 1.0)
+                });
+
+            if count > 0.0 {
+                let target = (sum / count).extend(transform.translation.z);
+                let blend = (SPECTATOR_FOLLOW_RATE * dt).min(1.0);
+                transform.translation = transform.translation.lerp(target, blend);
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::components::{GamepadNumber, Paddle, Player};
+use crate::events::gameplay::{BallBounced, GoalScored};
+use crate::models::game::area::LocalPlayerID;
+use crate::models::game::gameplay::PlayerId;
+use crate::resources::GameSettings;
+use bevy::input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest};
+use bevy::prelude::*;
+use std::time::Duration;
+
+const HIT_RUMBLE: GamepadRumbleIntensity = GamepadRumbleIntensity {
+    strong_motor: 0.0,
+    weak_motor: 0.5,
+};
+const HIT_RUMBLE_DURATION: Duration = Duration::from_millis(120);
+
+const CONCEDE_RUMBLE: GamepadRumbleIntensity = GamepadRumbleIntensity {
+    strong_motor: 0.9,
+    weak_motor: 0.6,
+};
+const CONCEDE_RUMBLE_DURATION: Duration = Duration::from_millis(400);
+
+/// A short buzz for the player whose paddle hit the ball.
+pub fn t_rumble_on_bounce(
+    bounce: On<BallBounced>,
+    paddles: Query<&Paddle>,
+    players: Query<(&Player, &GamepadNumber)>,
+    settings: Res<GameSettings>,
+    mut requests: MessageWriter<GamepadRumbleRequest>,
+) {
+    let Ok(paddle) = paddles.get(bounce.paddle) else { return };
+
+    request_rumble(paddle.id, HIT_RUMBLE, HIT_RUMBLE_DURATION, &players, &settings, &mut requests);
+}
+
+/// A longer, heavier pulse for every player of the team that conceded.
+pub fn t_rumble_on_goal(
+    goal: On<GoalScored>,
+    paddles: Query<&Paddle>,
+    players: Query<(&Player, &GamepadNumber)>,
+    settings: Res<GameSettings>,
+    mut requests: MessageWriter<GamepadRumbleRequest>,
+) {
+    for paddle in paddles.iter().filter(|paddle| paddle.goal == goal.goal) {
+        request_rumble(paddle.id, CONCEDE_RUMBLE, CONCEDE_RUMBLE_DURATION, &players, &settings, &mut requests);
+    }
+}
+
+/// Rumbles the gamepad of player `id`, scaled by their intensity setting.
+/// Players on other devices, and everyone while rumble is off, are skipped.
+fn request_rumble(
+    id: PlayerId,
+    intensity: GamepadRumbleIntensity,
+    duration: Duration,
+    players: &Query<(&Player, &GamepadNumber)>,
+    settings: &GameSettings,
+    requests: &mut MessageWriter<GamepadRumbleRequest>,
+) {
+    if !settings.rumble.enabled {
+        return;
+    }
+
+    let PlayerId::Local(LocalPlayerID::Gamepad(gamepad)) = id else { return };
+    let Some((_, number)) = players.iter().find(|(player, _)| player.id == id) else { return };
+
+    let scale = settings.rumble.intensity_for(number.0);
+    if scale <= 0.0 {
+        return;
+    }
+
+    requests.write(GamepadRumbleRequest::Add {
+        gamepad,
+        duration,
+        intensity: GamepadRumbleIntensity {
+            strong_motor: intensity.strong_motor * scale,
+            weak_motor: intensity.weak_motor * scale,
+        },
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Goal;
+    use crate::models::game::area::AreaSide;
+
+    struct Rig {
+        app: App,
+        goal: Entity,
+        paddle: Entity,
+        gamepad: Entity,
+    }
+
+    /// One gamepad player on the left, defending the left goal.
+    fn rig() -> Rig {
+        let mut app = App::new();
+        app.add_message::<GamepadRumbleRequest>()
+            .insert_resource(GameSettings::default())
+            .add_observer(t_rumble_on_bounce)
+            .add_observer(t_rumble_on_goal);
+
+        let world = app.world_mut();
+        let gamepad = world.spawn_empty().id();
+        let id = PlayerId::Local(LocalPlayerID::Gamepad(gamepad));
+        let goal = world.spawn(Goal { side: AreaSide::Left }).id();
+        let paddle = world.spawn(Paddle { goal, id }).id();
+        world.spawn((Player { id }, GamepadNumber(1)));
+
+        Rig { app, goal, paddle, gamepad }
+    }
+
+    fn requests(app: &App) -> Vec<GamepadRumbleRequest> {
+        app.world()
+            .resource::<Messages<GamepadRumbleRequest>>()
+            .iter_current_update_messages()
+            .cloned()
+            .collect()
+    }
+
+    fn added(request: &GamepadRumbleRequest) -> (Entity, Duration, GamepadRumbleIntensity) {
+        match request {
+            GamepadRumbleRequest::Add { gamepad, duration, intensity } => (*gamepad, *duration, *intensity),
+            _ => panic!("expected a rumble to be added"),
+        }
+    }
+
+    #[test]
+    fn paddle_hit_rumbles_its_gamepad() {
+        let mut rig = rig();
+        let ball = rig.app.world_mut().spawn_empty().id();
+
+        rig.app.world_mut().trigger(BallBounced { paddle: rig.paddle, ball });
+
+        let requests = requests(&rig.app);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(added(&requests[0]), (rig.gamepad, HIT_RUMBLE_DURATION, HIT_RUMBLE));
+    }
+
+    #[test]
+    fn conceding_rumbles_the_defending_team() {
+        let mut rig = rig();
+        let ball = rig.app.world_mut().spawn_empty().id();
+
+        rig.app.world_mut().trigger(GoalScored { goal: rig.goal, ball });
+
+        let requests = requests(&rig.app);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(added(&requests[0]), (rig.gamepad, CONCEDE_RUMBLE_DURATION, CONCEDE_RUMBLE));
+    }
+
+    #[test]
+    fn other_goals_leave_the_pad_alone() {
+        let mut rig = rig();
+        let ball = rig.app.world_mut().spawn_empty().id();
+        let other_goal = rig.app.world_mut().spawn(Goal { side: AreaSide::Right }).id();
+
+        rig.app.world_mut().trigger(GoalScored { goal: other_goal, ball });
+
+        assert!(requests(&rig.app).is_empty());
+    }
+
+    #[test]
+    fn intensity_setting_scales_the_motors() {
+        let mut rig = rig();
+        let ball = rig.app.world_mut().spawn_empty().id();
+        *rig.app.world_mut().resource_mut::<GameSettings>().rumble.intensity_for_mut(1) = 0.5;
+
+        rig.app.world_mut().trigger(BallBounced { paddle: rig.paddle, ball });
+
+        let (_, _, intensity) = added(&requests(&rig.app)[0]);
+        assert_eq!(intensity.weak_motor, HIT_RUMBLE.weak_motor * 0.5);
+        assert_eq!(intensity.strong_motor, HIT_RUMBLE.strong_motor * 0.5);
+    }
+
+    #[test]
+    fn disabled_rumble_sends_nothing() {
+        let mut rig = rig();
+        let ball = rig.app.world_mut().spawn_empty().id();
+        rig.app.world_mut().resource_mut::<GameSettings>().rumble.enabled = false;
+
+        rig.app.world_mut().trigger(BallBounced { paddle: rig.paddle, ball });
+
+        assert!(requests(&rig.app).is_empty());
+    }
+
+    #[test]
+    fn zero_intensity_sends_nothing() {
+        let mut rig = rig();
+        let ball = rig.app.world_mut().spawn_empty().id();
+        *rig.app.world_mut().resource_mut::<GameSettings>().rumble.intensity_for_mut(1) = 0.0;
+
+        rig.app.world_mut().trigger(BallBounced { paddle: rig.paddle, ball });
+
+        assert!(requests(&rig.app).is_empty());
+    }
+
+    #[test]
+    fn keyboard_paddles_never_rumble() {
+        let mut rig = rig();
+        let ball = rig.app.world_mut().spawn_empty().id();
+        let goal = rig.goal;
+        let keyboard = rig.app.world_mut().spawn(Paddle { goal, id: PlayerId::Local(LocalPlayerID::Keyboard(1)) }).id();
+
+        rig.app.world_mut().trigger(BallBounced { paddle: keyboard, ball });
+
+        assert!(requests(&rig.app).is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::components::*;
+use crate::events::gameplay::{BallBounced, BallServed};
+use crate::models::game::area::AreaSide;
+use crate::resources::{GameSettings, TeamMaterials, TeamPalette};
+use crate::utils::{FIXED_DIMENSIONS, HALF_HEIGHT, HALF_WIDTH};
+use bevy::prelude::*;
+
+/// Width of the bright line along a goal.
+const GOAL_GLOW_WIDTH: f32 = 4.0;
+/// Width of the faint halo around it.
+const GOAL_HALO_WIDTH: f32 = 18.0;
+const GOAL_HALO_ALPHA: f32 = 0.25;
+
+/// The simulating peer remembers which team touched the ball last.
+pub fn t_last_touch(
+    bounce: On<BallBounced>,
+    paddles: Query<&Paddle>,
+    goals: Query<&Goal>,
+    mut commands: Commands,
+) {
+    let Ok(paddle) = paddles.get(bounce.paddle) else { return };
+    let Ok(goal) = goals.get(paddle.goal) else { return };
+
+    // the ball may already be gone into a goal
+    commands.entity(bounce.ball).try_insert(LastTouch(goal.side));
+}
+
+/// A served ball belongs to nobody until a paddle touches it.
+pub fn t_serve_clears_last_touch(
+    _: On<BallServed>,
+    balls: Query<Entity, (With<Ball>, With<LastTouch>)>,
+    mut commands: Commands,
+) {
+    for ball in balls.iter() {
+        commands.entity(ball).remove::<LastTouch>();
+    }
+}
+
+/// Lines the goal of every side in the match with a glowing strip. Goals
+/// are never replicated, so clients build these from the score too.
+pub fn t_spawn_goal_glow(
+    added: On<Add, MatchScore>,
+    scores: Query<&MatchScore>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    let Ok(score) = scores.get(added.entity) else { return };
+
+    for (side, _) in &score.scores {
+        for (width, alpha, z) in [(GOAL_HALO_WIDTH, GOAL_HALO_ALPHA, -0.6), (GOAL_GLOW_WIDTH, 1.0, -0.5)] {
+            let (size, center) = goal_strip(*side, width);
+
+            commands.spawn((
+                Mesh2d(meshes.add(Rectangle::new(size.x, size.y))),
+                MeshMaterial2d(materials.add(Color::WHITE.with_alpha(alpha))),
+                Transform::from_translation(center.extend(z)),
+                TeamTint::new(*side).with_alpha(alpha),
+                MatchEntity,
+            ));
+        }
+    }
+}
+
+/// Hands the ball's last touch to its tint, and takes it back with it.
+pub fn u_ball_last_touch(
+    balls: Query<(Entity, &LastTouch), Changed<LastTouch>>,
+    mut cleared: RemovedComponents<LastTouch>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    for (entity, touch) in balls.iter() {
+        commands.entity(entity).insert(TeamTint::new(touch.0));
+    }
+
+    for entity in cleared.read() {
+        if let Ok(mut ball) = commands.get_entity(entity) {
+            ball.try_remove::<TeamTint>()
+                .try_insert(MeshMaterial2d(materials.add(Color::WHITE)));
+        }
+    }
+}
+
+/// Paints everything with a [`TeamTint`] from the palette picked in the
+/// settings, all of it again when another palette is picked. Meshes swap
+/// between the shared [`TeamMaterials`].
+pub fn u_team_tint(
+    settings: Res<GameSettings>,
+    mut tinted_meshes: Query<(Ref<TeamTint>, Ref<Mesh2d>, &mut MeshMaterial2d<ColorMaterial>)>,
+    mut texts: Query<(Ref<TeamTint>, &mut TextColor)>,
+    mut shown: Local<Option<TeamPalette>>,
+    mut team_materials: ResMut<TeamMaterials>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let palette = settings.team_palette;
+    let repaint = *shown != Some(palette);
+    *shown = Some(palette);
+
+    for (tint, mesh, mut material) in tinted_meshes.iter_mut() {
+        if repaint || tint.is_changed() || mesh.is_added() {
+            material.0 = team_materials.get(palette, tint.side, tint.alpha, &mut materials);
+        }
+    }
+
+    for (tint, mut color) in texts.iter_mut() {
+        if repaint || tint.is_changed() {
+            color.0 = palette.color(tint.side);
+        }
+    }
+}
+
+/// Size and middle of a strip `width` wide, lying inside the arena along
+/// the goal line of `side`.
+fn goal_strip(side: AreaSide, width: f32) -> (Vec2, Vec2) {
+    let half = width / 2.0;
+
+    match side {
+        AreaSide::Left => (Vec2::new(width, FIXED_DIMENSIONS.y), Vec2::new(-HALF_WIDTH + half, 0.0)),
+        AreaSide::Right => (Vec2::new(width, FIXED_DIMENSIONS.y), Vec2::new(HALF_WIDTH - half, 0.0)),
+        AreaSide::Top => (Vec2::new(FIXED_DIMENSIONS.x, width), Vec2::new(0.0, HALF_HEIGHT - half)),
+        AreaSide::Bottom => (Vec2::new(FIXED_DIMENSIONS.x, width), Vec2::new(0.0, -HALF_HEIGHT + half)),
+    }
+}
@@ -1,6 +1,8 @@
+pub mod audio;
 pub mod gameplay;
 pub mod settings;
 pub mod ui;
 
+pub use audio::*;
 pub use gameplay::*;
 pub use ui::*;
@@ -1,14 +1,27 @@
-﻿use crate::resources::GameSettings;
+﻿use crate::resources::{generate_client_id, GameSettings, Profiles};
 use bevy::prelude::*;
 use std::fs;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 const SETTINGS_FILE: &str = "settings.json";
+const PROFILES_FILE: &str = "profiles.json";
+/// Where an unreadable profiles file is kept before it is saved over.
+const PROFILES_BACKUP_FILE: &str = "profiles.json.bak";
+
+/// Set when an unreadable profiles file could not be backed up, it is then
+/// left alone for the rest of the session rather than lost.
+static PROFILES_READ_ONLY: AtomicBool = AtomicBool::new(false);
 
 pub fn save_settings(settings: &Res<GameSettings>) {
     if settings.is_changed() {
-        if let Ok(json) = serde_json::to_string_pretty(settings.as_ref()) {
-            let _ = fs::write(SETTINGS_FILE, json);
-        }
+        write_settings(settings);
+    }
+}
+
+pub fn write_settings(settings: &GameSettings) {
+    if let Ok(json) = serde_json::to_string_pretty(settings) {
+        let _ = fs::write(SETTINGS_FILE, json);
     }
 }
 
@@ -16,14 +29,68 @@ pub fn load_settings() -> GameSettings {
     let settings: GameSettings;
 
     if let Ok(contents) = fs::read_to_string(SETTINGS_FILE) {
-        if let Ok(loaded) = serde_json::from_str::<GameSettings>(&contents) {
+        if let Ok(mut loaded) = serde_json::from_str::<GameSettings>(&contents) {
+            // older files have no identity yet, keep the generated one stable
+            if loaded.client_id.is_none() {
+                loaded.client_id = Some(generate_client_id());
+                write_settings(&loaded);
+            }
+
             settings = loaded;
         } else {
             settings = GameSettings::default();
         }
     } else {
         settings = GameSettings::default();
+        write_settings(&settings);
     }
 
     settings
 }
+
+pub fn write_profiles(profiles: &Profiles) {
+    if PROFILES_READ_ONLY.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if let Ok(json) = serde_json::to_string_pretty(profiles) {
+        let _ = fs::write(PROFILES_FILE, json);
+    }
+}
+
+/// A missing or unreadable file starts over with the default profiles,
+/// only a missing one is written back. An unreadable one is copied aside
+/// first, since the profiles are saved over it after the next match.
+pub fn load_profiles() -> Profiles {
+    match fs::read_to_string(PROFILES_FILE) {
+        Ok(contents) => match serde_json::from_str::<Profiles>(&contents) {
+            Ok(profiles) if !profiles.profiles.is_empty() => profiles,
+            Ok(_) => Profiles::default(),
+            Err(e) => {
+                back_up_profiles(&e.to_string());
+                Profiles::default()
+            }
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            let profiles = Profiles::default();
+            write_profiles(&profiles);
+            profiles
+        }
+        Err(e) => {
+            back_up_profiles(&e.to_string());
+            Profiles::default()
+        }
+    }
+}
+
+fn back_up_profiles(reason: &str) {
+    warn!("Could not read {PROFILES_FILE} ({reason}), starting over with the default profiles");
+
+    match fs::copy(PROFILES_FILE, PROFILES_BACKUP_FILE) {
+        Ok(_) => info!("Kept the old profiles in {PROFILES_BACKUP_FILE}"),
+        Err(e) => {
+            error!("Could not back up {PROFILES_FILE} ({e}), profile changes will not be saved");
+            PROFILES_READ_ONLY.store(true, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::bundles::widgets::LabelBundle;
+use crate::components::ui::{ChatLogNode, ChatOverlay, Menu};
+use crate::components::MatchEntity;
+use crate::events::widgets::TextInputSubmitted;
+use crate::networking::chat::{send_chat, ChatLog, ChatSend};
+use crate::networking::client::GameClient;
+use crate::components::Player;
+use crate::models::game::gameplay::PlayerId;
+use crate::resources::{MenuAction, PlayerAction};
+use crate::systems::widgets::*;
+use crate::utils::MODERN_THEME;
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use lightyear::prelude::server::Server;
+use lightyear::prelude::MessageSender;
+
+const CHAT_FONT_SIZE: f32 = 24.0;
+const OVERLAY_LINES: usize = 8;
+
+pub fn u_chat_log(
+    log: Res<ChatLog>,
+    nodes: Query<(Entity, &ChatLogNode)>,
+    added: Query<(), Added<ChatLogNode>>,
+    mut commands: Commands,
+) {
+    if !log.is_changed() && added.is_empty() {
+        return;
+    }
+
+    for (entity, node) in nodes.iter() {
+        commands.entity(entity).despawn_related::<Children>();
+        commands.entity(entity).with_children(|parent| {
+            let skip = log.lines.len().saturating_sub(node.0);
+
+            for line in log.lines.iter().skip(skip) {
+                match &line.author {
+                    Some(author) => parent.spawn(LabelBundle::custom(
+                        &format!("{author}: {}", line.text),
+                        MODERN_THEME.text_normal,
+                        CHAT_FONT_SIZE,
+                    )),
+                    None => parent.spawn(LabelBundle::custom(
+                        &line.text,
+                        Color::srgb(1.0, 0.8, 0.3),
+                        CHAT_FONT_SIZE,
+                    )),
+                };
+            }
+        });
+    }
+}
+
+pub fn t_submit_chat(
+    submit: On<TextInputSubmitted>,
+    client: Option<Single<&mut MessageSender<ChatSend>, With<GameClient>>>,
+    server: Option<Single<(), With<Server>>>,
+    overlay: Option<Single<Entity, With<ChatOverlay>>>,
+    mut commands: Commands,
+) {
+    send_chat(
+        &submit.value,
+        client.map(|client| client.into_inner()),
+        server.is_some(),
+        &mut commands,
+    );
+
+    if let Some(overlay) = overlay {
+        commands.entity(*overlay).despawn();
+    }
+}
+
+/// In-game chat, opened with [`MenuAction::OpenChat`] during online matches
+/// and closed by sending a line or cancelling.
+pub fn u_chat_overlay(
+    state: Single<&ActionState<MenuAction>>,
+    overlay: Option<Single<Entity, With<ChatOverlay>>>,
+    online: Query<(), Or<(With<GameClient>, With<Server>)>>,
+    match_entities: Query<(), With<MatchEntity>>,
+    menus: Query<(), With<Menu>>,
+    mut commands: Commands,
+) {
+    if let Some(overlay) = overlay {
+        if state.just_pressed(&MenuAction::Cancel) || match_entities.is_empty() {
+            commands.entity(*overlay).despawn();
+        }
+        return;
+    }
+
+    if online.is_empty() || match_entities.is_empty() || !menus.is_empty() {
+        return;
+    }
+
+    if !state.just_pressed(&MenuAction::OpenChat) {
+        return;
+    }
+
+    commands
+        .spawn((
+            ChatOverlay,
+            MatchEntity,
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(20.0),
+                bottom: Val::Px(20.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(MODERN_THEME.panel_bg.with_alpha(0.8)),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ChatLogNode(OVERLAY_LINES),
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+            ));
+            parent.spawn_input("Say: ").input.observe(t_submit_chat);
+        });
+}
+
+/// Keeps keys typed into the chat from also moving the local paddles.
+pub fn u_chat_blocks_input(
+    overlay: Query<(), With<ChatOverlay>>,
+    mut players: Query<(&Player, &mut ActionState<PlayerAction>)>,
+) {
+    let chatting = !overlay.is_empty();
+
+    for (player, mut state) in players.iter_mut() {
+        if !matches!(player.id, PlayerId::Local(_)) || state.disabled() == chatting {
+            continue;
+        }
+
+        if chatting {
+            state.disable();
+        } else {
+            state.enable();
+        }
+    }
+}
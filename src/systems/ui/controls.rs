@@ -0,0 +1,483 @@
+use crate::bundles::widgets::LabelBundle;
+use crate::components::ui::{AnalogMenu, ControlsMenu, RebindButton, RebindStatusText, SourceHandle, UIOptionProvider};
+use crate::components::{GamepadNumber, Player};
+use crate::events::widgets::{ButtonPressed, OptionChanged, SliderValueChanged};
+use crate::models::game::gameplay::PlayerId;
+use crate::resources::{BindingSlot, BindingTarget, BoundInput, GameSettings, MenuAction, Monitors, MouseControl, PlayerAction, RebindCapture, ResponseCurve, StickSide, AnalogMode, MAX_ANALOG_DEADZONE, MAX_ANALOG_SENSITIVITY, MAX_GAMEPAD_PLAYERS, MAX_MOUSE_SENSITIVITY, MIN_ANALOG_SENSITIVITY, MIN_MOUSE_SENSITIVITY};
+use crate::systems::menu::{index_of_option, spawn_m_base, spawn_m_settings};
+use crate::systems::settings::persistence::write_settings;
+use crate::systems::widgets::*;
+use crate::utils::MODERN_THEME;
+use bevy::input_focus::directional_navigation::DirectionalNavigationMap;
+use bevy::math::CompassOctant;
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::{ActionState, InputMap};
+
+pub const MOUSE_CONTROL_OPTIONS: SourceHandle<dyn UIOptionProvider> = SourceHandle::Static(&MOUSE_CONTROL_OPTIONS_RAW);
+
+pub const MOUSE_CONTROL_OPTIONS_RAW: [MouseControl; 2] = [MouseControl::Relative, MouseControl::Cursor];
+
+pub const GAMEPAD_NUMBER_OPTIONS: SourceHandle<dyn UIOptionProvider> = SourceHandle::Static(&GAMEPAD_NUMBER_OPTIONS_RAW);
+
+pub const GAMEPAD_NUMBER_OPTIONS_RAW: [u16; MAX_GAMEPAD_PLAYERS as usize] = [1, 2, 3, 4];
+
+pub const RESPONSE_CURVE_OPTIONS: SourceHandle<dyn UIOptionProvider> = SourceHandle::Static(&RESPONSE_CURVE_OPTIONS_RAW);
+
+pub const RESPONSE_CURVE_OPTIONS_RAW: [ResponseCurve; 2] = [ResponseCurve::Linear, ResponseCurve::Exponential];
+
+pub const ANALOG_MODE_OPTIONS: SourceHandle<dyn UIOptionProvider> = SourceHandle::Static(&ANALOG_MODE_OPTIONS_RAW);
+
+pub const ANALOG_MODE_OPTIONS_RAW: [AnalogMode; 2] = [AnalogMode::Speed, AnalogMode::Position];
+
+const BINDING_BUTTON_SIZE: Val2 = Val2::new(Val::Px(220.0), Val::Px(40.0));
+/// How far a stick has to be pushed to be taken as the new binding.
+const STICK_CAPTURE_THRESHOLD: f32 = 0.6;
+
+/// Every keyboard layout in use and the layout of one gamepad player,
+/// picked with the selector atop its column.
+pub fn spawn_m_controls(settings: &GameSettings, gamepad: u8, commands: &mut Commands, nav_map: &mut DirectionalNavigationMap) {
+    let slots: Vec<BindingSlot> = BindingSlot::active(settings.keyboard_players)
+        .into_iter()
+        .filter(|slot| !matches!(slot, BindingSlot::Gamepad(number) if *number != gamepad))
+        .collect();
+    let mut columns: Vec<Vec<Entity>> = Vec::with_capacity(slots.len());
+    let mut bottom: Vec<Entity> = Vec::with_capacity(3);
+
+    spawn_m_base(commands, nav_map, ControlsMenu(gamepad)).with_children(|base| {
+        base.spawn(w_menu_title("Controls"));
+
+        base.spawn(w_row_container(Val::Px(10.0))).with_children(|row| {
+            for slot in slots {
+                let mut column = Vec::with_capacity(slot.targets().len());
+
+                row.spawn(w_menu_section()).with_children(|section| {
+                    if let BindingSlot::Gamepad(number) = slot {
+                        let mut selector = section.spawn_selector(
+                            GAMEPAD_NUMBER_OPTIONS,
+                            index_of_option(&GAMEPAD_NUMBER_OPTIONS_RAW, &(number as u16)),
+                            "Gamepad",
+                        );
+                        selector.root.observe(on_gamepad_changed);
+                        column.push(selector.bar);
+                    } else {
+                        section.spawn(LabelBundle::button_label(slot.name()));
+                    }
+
+                    for target in slot.targets() {
+                        section.spawn(LabelBundle::custom(target.label(), MODERN_THEME.text_normal, 24.0));
+
+                        let label = settings
+                            .bindings
+                            .get(slot, *target)
+                            .map_or("Unbound".to_owned(), BoundInput::label);
+
+                        column.push(
+                            section
+                                .spawn((
+                                    w_button(MODERN_THEME.button, label, BINDING_BUTTON_SIZE),
+                                    RebindButton { slot, target: *target },
+                                ))
+                                .observe(on_rebind)
+                                .id(),
+                        );
+                    }
+                });
+
+                columns.push(column);
+            }
+
+            row.spawn(w_menu_section()).with_children(|section| {
+                section.spawn(LabelBundle::button_label("Mouse"));
+
+                let mut control = section.spawn_selector(
+                    MOUSE_CONTROL_OPTIONS,
+                    index_of_option(&MOUSE_CONTROL_OPTIONS_RAW, &settings.mouse.control),
+                    "Movement",
+                );
+                control.root.observe(on_mouse_control_changed);
+                let mut column = vec![control.bar];
+
+                section.spawn(LabelBundle::custom("Sensitivity", MODERN_THEME.text_normal, 24.0));
+                let mut sensitivity = section.spawn_slider_stepped(
+                    MIN_MOUSE_SENSITIVITY,
+                    MAX_MOUSE_SENSITIVITY,
+                    settings.mouse.sensitivity,
+                    0.1,
+                );
+                sensitivity.root.observe(on_mouse_sensitivity_changed);
+                column.push(sensitivity.thumb);
+
+                columns.push(column);
+            });
+        });
+
+        base.spawn((
+            LabelBundle::custom("Select a binding to change it", MODERN_THEME.text_normal, 28.0),
+            RebindStatusText,
+        ));
+
+        base.spawn(w_row_container(Val::Px(10.0))).with_children(|row| {
+            const SIZE: Val2 = Val2::new(Val::Px(300.0), Val::Px(50.0));
+
+            bottom.push(
+                row.spawn(w_button(MODERN_THEME.button, "Back", SIZE))
+                    .observe(on_back)
+                    .id(),
+            );
+
+            bottom.push(
+                row.spawn(w_button(MODERN_THEME.button, "Restore Defaults", SIZE))
+                    .observe(on_restore_defaults)
+                    .id(),
+            );
+
+            bottom.push(
+                row.spawn(w_button(MODERN_THEME.button, "Analog", SIZE))
+                    .observe(on_analog)
+                    .id(),
+            );
+        });
+    });
+
+    for column in &columns {
+        let mut edges = column.clone();
+        edges.push(bottom[0]);
+        nav_map.add_looping_edges(&edges, CompassOctant::South);
+    }
+
+    let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+    for row in 0..rows {
+        let across: Vec<Entity> = columns.iter().filter_map(|column| column.get(row).copied()).collect();
+        nav_map.add_looping_edges(&across, CompassOctant::East);
+    }
+
+    nav_map.add_looping_edges(&bottom, CompassOctant::East);
+
+    fn on_rebind(
+        press: On<ButtonPressed>,
+        buttons: Query<&RebindButton>,
+        capture: Option<Res<RebindCapture>>,
+        mut menu_state: Single<&mut ActionState<MenuAction>>,
+        mut commands: Commands,
+    ) {
+        if capture.is_some_and(|capture| capture.captured) {
+            return;
+        }
+
+        let Ok(button) = buttons.get(press.event_target()) else { return };
+
+        // menu keys are fair game for bindings, so they stop navigating meanwhile
+        menu_state.disable();
+        commands.insert_resource(RebindCapture::new(button.slot, button.target));
+    }
+
+    fn on_gamepad_changed(
+        change: On<OptionChanged>,
+        menu: Single<Entity, With<ControlsMenu>>,
+        settings: Res<GameSettings>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        mut commands: Commands,
+    ) {
+        let number = GAMEPAD_NUMBER_OPTIONS_RAW[change.selected_index] as u8;
+
+        commands.remove_resource::<RebindCapture>();
+        commands.entity(*menu).despawn();
+        spawn_m_controls(&settings, number, &mut commands, &mut nav_map);
+    }
+
+    fn on_mouse_control_changed(change: On<OptionChanged>, mut settings: ResMut<GameSettings>) {
+        settings.mouse.control = MOUSE_CONTROL_OPTIONS_RAW[change.selected_index];
+    }
+
+    fn on_mouse_sensitivity_changed(change: On<SliderValueChanged>, mut settings: ResMut<GameSettings>) {
+        settings.mouse.sensitivity = change.value;
+    }
+
+    fn on_restore_defaults(
+        _: On<ButtonPressed>,
+        mut settings: ResMut<GameSettings>,
+        mut players: Query<(&Player, Option<&GamepadNumber>, &mut InputMap<PlayerAction>)>,
+        mut status: Single<&mut Text, With<RebindStatusText>>,
+        mut commands: Commands,
+    ) {
+        commands.remove_resource::<RebindCapture>();
+
+        settings.bindings.restore_defaults();
+        apply_bindings(&settings, &mut players);
+        write_settings(&settings);
+
+        status.0 = "Restored the default controls".to_owned();
+    }
+
+    fn on_analog(
+        _: On<ButtonPressed>,
+        menu: Single<(Entity, &ControlsMenu)>,
+        settings: Res<GameSettings>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        mut commands: Commands,
+    ) {
+        commands.remove_resource::<RebindCapture>();
+        commands.entity(menu.0).despawn();
+        spawn_m_analog(&settings, menu.1 .0, &mut commands, &mut nav_map);
+    }
+
+    fn on_back(
+        _: On<ButtonPressed>,
+        menu: Single<Entity, With<ControlsMenu>>,
+        settings: Res<GameSettings>,
+        monitors: Res<Monitors>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        mut commands: Commands,
+    ) {
+        write_settings(&settings);
+        commands.remove_resource::<RebindCapture>();
+        commands.entity(*menu).despawn();
+        spawn_m_settings(&settings, &monitors, &mut commands, &mut nav_map);
+    }
+}
+
+/// Stick settings of one gamepad player, picked with the selector on top.
+fn spawn_m_analog(settings: &GameSettings, number: u8, commands: &mut Commands, nav_map: &mut DirectionalNavigationMap) {
+    let analog = settings.analog_for(number);
+    let mut entities: Vec<Entity> = Vec::with_capacity(7);
+
+    spawn_m_base(commands, nav_map, AnalogMenu(number)).with_children(|base| {
+        base.spawn(w_menu_title("Analog Controls"));
+
+        base.spawn(w_menu_section()).with_children(|section| {
+            let mut gamepad = section.spawn_selector(
+                GAMEPAD_NUMBER_OPTIONS,
+                index_of_option(&GAMEPAD_NUMBER_OPTIONS_RAW, &(number as u16)),
+                "Gamepad",
+            );
+            gamepad.root.observe(on_gamepad_changed);
+            entities.push(gamepad.bar);
+
+            section.spawn(LabelBundle::button_label("Deadzone"));
+            let mut deadzone = section.spawn_slider_stepped(0.0, MAX_ANALOG_DEADZONE, analog.deadzone, 0.01);
+            deadzone.root.observe(on_deadzone_changed);
+            entities.push(deadzone.thumb);
+
+            section.spawn(LabelBundle::button_label("Sensitivity"));
+            let mut sensitivity = section.spawn_slider_stepped(MIN_ANALOG_SENSITIVITY, MAX_ANALOG_SENSITIVITY, analog.sensitivity, 0.05);
+            sensitivity.root.observe(on_sensitivity_changed);
+            entities.push(sensitivity.thumb);
+
+            let mut curve = section.spawn_selector(
+                RESPONSE_CURVE_OPTIONS,
+                index_of_option(&RESPONSE_CURVE_OPTIONS_RAW, &analog.curve),
+                "Response Curve",
+            );
+            curve.root.observe(on_curve_changed);
+            entities.push(curve.bar);
+
+            let mut mode = section.spawn_selector(
+                ANALOG_MODE_OPTIONS,
+                index_of_option(&ANALOG_MODE_OPTIONS_RAW, &analog.mode),
+                "Stick Controls",
+            );
+            mode.root.observe(on_mode_changed);
+            entities.push(mode.bar);
+
+            section.spawn(LabelBundle::button_label("Rumble (%)"));
+            let mut rumble = section.spawn_slider(0.0, 100.0, settings.rumble.intensity_for(number) * 100.0);
+            rumble.root.observe(on_rumble_changed);
+            entities.push(rumble.thumb);
+        });
+
+        entities.push(
+            base.spawn(w_menu_button(MODERN_THEME.button, "Back"))
+                .observe(on_back)
+                .id(),
+        );
+    });
+
+    nav_map.add_looping_edges(&entities, CompassOctant::South);
+
+    fn on_gamepad_changed(
+        change: On<OptionChanged>,
+        menu: Single<Entity, With<AnalogMenu>>,
+        settings: Res<GameSettings>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        mut commands: Commands,
+    ) {
+        let number = GAMEPAD_NUMBER_OPTIONS_RAW[change.selected_index] as u8;
+
+        commands.entity(*menu).despawn();
+        spawn_m_analog(&settings, number, &mut commands, &mut nav_map);
+    }
+
+    fn on_deadzone_changed(change: On<SliderValueChanged>, menu: Single<&AnalogMenu>, mut settings: ResMut<GameSettings>) {
+        settings.analog_for_mut(menu.0).deadzone = change.value;
+    }
+
+    fn on_sensitivity_changed(change: On<SliderValueChanged>, menu: Single<&AnalogMenu>, mut settings: ResMut<GameSettings>) {
+        settings.analog_for_mut(menu.0).sensitivity = change.value;
+    }
+
+    fn on_curve_changed(change: On<OptionChanged>, menu: Single<&AnalogMenu>, mut settings: ResMut<GameSettings>) {
+        settings.analog_for_mut(menu.0).curve = RESPONSE_CURVE_OPTIONS_RAW[change.selected_index];
+    }
+
+    fn on_mode_changed(change: On<OptionChanged>, menu: Single<&AnalogMenu>, mut settings: ResMut<GameSettings>) {
+        settings.analog_for_mut(menu.0).mode = ANALOG_MODE_OPTIONS_RAW[change.selected_index];
+    }
+
+    fn on_rumble_changed(change: On<SliderValueChanged>, menu: Single<&AnalogMenu>, mut settings: ResMut<GameSettings>) {
+        *settings.rumble.intensity_for_mut(menu.0) = change.value / 100.0;
+    }
+
+    fn on_back(
+        _: On<ButtonPressed>,
+        menu: Single<(Entity, &AnalogMenu)>,
+        settings: Res<GameSettings>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        mut commands: Commands,
+    ) {
+        write_settings(&settings);
+        commands.entity(menu.0).despawn();
+        spawn_m_controls(&settings, menu.1 .0, &mut commands, &mut nav_map);
+    }
+}
+
+/// Takes the next press for the row waiting in [`RebindCapture`], swapping
+/// with whichever row already used it.
+pub fn u_capture_rebind(
+    capture: Option<ResMut<RebindCapture>>,
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut settings: ResMut<GameSettings>,
+    mut players: Query<(&Player, Option<&GamepadNumber>, &mut InputMap<PlayerAction>)>,
+    mut menu_state: Single<&mut ActionState<MenuAction>>,
+    status: Option<Single<&mut Text, With<RebindStatusText>>>,
+    mut commands: Commands,
+) {
+    let Some(mut capture) = capture else {
+        if menu_state.disabled() {
+            menu_state.enable();
+        }
+        return;
+    };
+
+    let timed_out = capture.timer.tick(time.delta()).just_finished();
+
+    if capture.captured || timed_out {
+        if timed_out {
+            commands.remove_resource::<RebindCapture>();
+        }
+
+        if timed_out && !capture.captured {
+            if let Some(mut status) = status {
+                status.0 = "Nothing pressed, binding unchanged".to_owned();
+            }
+        }
+        return;
+    }
+
+    // the press that opened the capture is not the one to bind
+    if capture.is_added() {
+        return;
+    }
+
+    let Some(input) = pressed_input(capture.slot, &keys, &gamepads).filter(|input| capture.target.accepts(*input)) else {
+        return;
+    };
+
+    let (slot, target) = (capture.slot, capture.target);
+    let slots = BindingSlot::active(settings.keyboard_players);
+    let swapped = settings.bindings.rebind(&slots, slot, target, input);
+
+    apply_bindings(&settings, &mut players);
+    write_settings(&settings);
+    capture.finish();
+
+    if let Some(mut status) = status {
+        status.0 = match swapped {
+            Some((other_slot, other_target)) => format!(
+                "{} was used by {} {}, the two were swapped",
+                input.label(),
+                other_slot.name(),
+                other_target.label(),
+            ),
+            None => format!("{} {} set to {}", slot.name(), target.label(), input.label()),
+        };
+    }
+}
+
+/// Keeps every binding button showing its current input, or a prompt while
+/// it waits for one.
+pub fn u_rebind_labels(
+    settings: Res<GameSettings>,
+    capture: Option<Res<RebindCapture>>,
+    buttons: Query<(&RebindButton, &Children)>,
+    status: Option<Single<&mut Text, (With<RebindStatusText>, Without<RebindButton>)>>,
+    mut texts: Query<&mut Text, Without<RebindStatusText>>,
+) {
+    let waiting = capture.as_ref().filter(|capture| !capture.captured);
+
+    for (button, children) in buttons.iter() {
+        let label = if waiting.is_some_and(|capture| capture.slot == button.slot && capture.target == button.target) {
+            "...".to_owned()
+        } else {
+            settings
+                .bindings
+                .get(button.slot, button.target)
+                .map_or("Unbound".to_owned(), BoundInput::label)
+        };
+
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                if text.0 != label {
+                    text.0 = label.clone();
+                }
+            }
+        }
+    }
+
+    if let (Some(capture), Some(mut status)) = (waiting, status) {
+        let device = match (capture.slot, capture.target) {
+            (BindingSlot::Keyboard(_), _) => "key",
+            (BindingSlot::Gamepad(_), BindingTarget::Stick(_)) => "stick",
+            (BindingSlot::Gamepad(_), _) => "gamepad button",
+        };
+
+        let prompt = format!(
+            "Press a {device} for {} {} ({:.0})",
+            capture.slot.name(),
+            capture.target.label(),
+            capture.timer.remaining_secs().ceil(),
+        );
+
+        if status.0 != prompt {
+            status.0 = prompt;
+        }
+    }
+}
+
+fn pressed_input(slot: BindingSlot, keys: &ButtonInput<KeyCode>, gamepads: &Query<&Gamepad>) -> Option<BoundInput> {
+    match slot {
+        BindingSlot::Keyboard(_) => keys.get_just_pressed().next().map(|key| BoundInput::Key(*key)),
+        BindingSlot::Gamepad(_) => gamepads.iter().find_map(|gamepad| {
+            if let Some(button) = gamepad.get_just_pressed().next() {
+                return Some(BoundInput::Button(*button));
+            }
+
+            [StickSide::Left, StickSide::Right].into_iter().find_map(|side| {
+                side.axes()
+                    .into_iter()
+                    .any(|axis| gamepad.get(axis).is_some_and(|value| value.abs() > STICK_CAPTURE_THRESHOLD))
+                    .then_some(BoundInput::Stick(side))
+            })
+        }),
+    }
+}
+
+/// Hands the saved layouts to the local players already spawned.
+fn apply_bindings(settings: &GameSettings, players: &mut Query<(&Player, Option<&GamepadNumber>, &mut InputMap<PlayerAction>)>) {
+    for (player, number, mut map) in players.iter_mut() {
+        if let PlayerId::Local(id) = player.id {
+            *map = Player::get_input_map(id, number.copied(), &settings.bindings);
+        }
+    }
+}
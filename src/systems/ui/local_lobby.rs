@@ -0,0 +1,305 @@
+use crate::bundles::area::AreaBundle;
+use crate::bundles::widgets::LabelBundle;
+use crate::components::ui::{LocalLobbyMenu, LocalLobbyPlayer, LocalLobbyStatusText, LocalLobbyTeamList, UIOptionString};
+use crate::components::{ActiveProfile, GamepadNumber, Player, PlayerIdentity, PLAYER_COLORS};
+use crate::models::game::area::{AreaShape, LocalPlayerID};
+use crate::networking::lobby::{lobby_start_blocker, LobbySeat, SeatRequest};
+use crate::resources::{GameModeConfig, PlayerAction, Profiles};
+use crate::systems::menu::{spawn_m_base, spawn_m_offline};
+use crate::systems::settings::persistence::write_profiles;
+use crate::systems::widgets::*;
+use crate::utils::MODERN_THEME;
+use bevy::input_focus::directional_navigation::DirectionalNavigationMap;
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+
+/// How far an axis has to be pushed to switch team or profile.
+const LOBBY_AXIS_THRESHOLD: f32 = 0.5;
+
+const LOBBY_HINT: &str = "Tilt: team   Move: profile   Dash: colour   Push: ready   Pause: leave";
+
+/// One screen for every local player. Nothing on it takes menu focus, the
+/// players drive it with their own controls in [`u_local_lobby_input`].
+pub fn spawn_m_local_lobby<'a>(
+    commands: &'a mut Commands,
+    nav_map: &'a mut DirectionalNavigationMap,
+    config: &GameModeConfig,
+) -> EntityCommands<'a> {
+    let mut base = spawn_m_base(commands, nav_map, LocalLobbyMenu);
+
+    base.with_children(|base| {
+        base.spawn(w_menu_title("Local Lobby"));
+
+        base.spawn(w_row_container(Val::Px(10.0))).with_children(|row| {
+            for side in config.area_shape.kind().sides() {
+                let mut header = String::new();
+                side.push_ui_option_string(&mut header);
+
+                row.spawn(w_menu_section()).with_children(|section| {
+                    section.spawn(LabelBundle::button_label(header));
+                    section.spawn((
+                        LocalLobbyTeamList(*side),
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            min_width: Val::Px(260.0),
+                            ..default()
+                        },
+                    ));
+                });
+            }
+        });
+
+        base.spawn(LabelBundle::custom(LOBBY_HINT, MODERN_THEME.text_normal, 24.0));
+        base.spawn((
+            LabelBundle::custom("", MODERN_THEME.text_normal, 28.0),
+            LocalLobbyStatusText,
+        ));
+    });
+
+    base
+}
+
+/// Joins, seats and readies local players. A press from anyone not in the
+/// lobby joins them on the smallest team as the first free profile that
+/// prefers their device, and the match starts as soon as
+/// [`lobby_start_blocker`] has nothing left to say.
+pub fn u_local_lobby_input(
+    menu: Option<Single<(Entity, Ref<LocalLobbyMenu>)>>,
+    mut joined: Query<(Entity, &Player, &ActionState<PlayerAction>, &mut LocalLobbyPlayer, &mut ActiveProfile)>,
+    waiting: Query<(Entity, &Player, &ActionState<PlayerAction>, Option<&ActiveProfile>), Without<LocalLobbyPlayer>>,
+    mut config: ResMut<GameModeConfig>,
+    mut profiles: ResMut<Profiles>,
+    mut nav_map: ResMut<DirectionalNavigationMap>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    let Some(menu) = menu else { return };
+    let (menu_entity, menu) = menu.into_inner();
+
+    // the press that opened the lobby should not join anyone
+    if menu.is_added() {
+        return;
+    }
+
+    let kind = config.area_shape.kind();
+    let sides = kind.sides();
+
+    let mut taken_profiles: Vec<usize> = joined.iter().map(|(.., active)| active.0).collect();
+    let mut taken_colors: Vec<usize> = taken_profiles
+        .iter()
+        .filter_map(|index| profiles.get(*index))
+        .map(|profile| profile.color as usize)
+        .collect();
+    let mut team_sizes: Vec<usize> = sides
+        .iter()
+        .map(|side| joined.iter().filter(|(_, _, _, lobby, _)| lobby.seat.side == Some(*side)).count())
+        .collect();
+    let mut roster_changed = false;
+
+    for (entity, player, action, active) in waiting.iter() {
+        let pressed = action.get_just_pressed();
+        if pressed.is_empty() {
+            continue;
+        }
+
+        if pressed.contains(&PlayerAction::Pause) {
+            // backing out is only for when nobody has joined yet
+            if joined.is_empty() && !roster_changed {
+                commands.entity(menu_entity).despawn();
+                spawn_m_offline(&mut commands, &mut nav_map, &config);
+                return;
+            }
+
+            continue;
+        }
+
+        let team = team_sizes
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, size)| **size)
+            .map_or(0, |(i, _)| i);
+        team_sizes[team] += 1;
+
+        // the last match's pick is kept unless someone else has it by now,
+        // and everyone gets a fresh profile once all are taken
+        let count = profiles.profiles.len();
+        let free = |index: &usize| *index < count && !taken_profiles.contains(index);
+        let device = player.id.local();
+        let index = active
+            .map(|active| active.0)
+            .filter(free)
+            .or_else(|| (0..count).filter(free).find(|i| profiles.profiles[*i].controls.prefers(device)))
+            .or_else(|| (0..count).find(free))
+            .unwrap_or_else(|| profiles.add());
+        taken_profiles.push(index);
+        taken_colors.push(profiles.profiles[index].color as usize);
+
+        commands.entity(entity).insert((
+            LocalLobbyPlayer {
+                seat: LobbySeat {
+                    side: Some(sides[team]),
+                    ready: false,
+                },
+                tilt_held: action.value(&PlayerAction::Tilt).abs() >= LOBBY_AXIS_THRESHOLD,
+                move_held: action.value(&PlayerAction::Move).abs() >= LOBBY_AXIS_THRESHOLD,
+            },
+            ActiveProfile(index),
+        ));
+        roster_changed = true;
+    }
+
+    for (entity, _, action, mut lobby, mut active) in joined.iter_mut() {
+        if action.just_pressed(&PlayerAction::Pause) {
+            commands.entity(entity).remove::<LocalLobbyPlayer>();
+            roster_changed = true;
+            continue;
+        }
+
+        if action.just_pressed(&PlayerAction::Push) {
+            lobby.seat.apply(SeatRequest::ToggleReady, kind);
+        }
+
+        if action.just_pressed(&PlayerAction::Dash) {
+            if let Some(profile) = profiles.get_mut(active.0) {
+                profile.color = next_free(profile.color as usize, true, &mut taken_colors, PLAYER_COLORS.len()) as u8;
+            }
+        }
+
+        // latches are not shown anywhere, so holding a stick does not
+        // rebuild the lists every frame
+        let tilt = action.value(&PlayerAction::Tilt);
+        if let Some(forward) = axis_edge(tilt, &mut lobby.bypass_change_detection().tilt_held) {
+            lobby.seat.step_side(forward, kind);
+        }
+
+        let movement = action.value(&PlayerAction::Move);
+        if let Some(forward) = axis_edge(movement, &mut lobby.bypass_change_detection().move_held) {
+            let before = profiles.get(active.0).map(|profile| profile.color as usize);
+            active.0 = next_free(active.0, forward, &mut taken_profiles, profiles.profiles.len());
+            let after = profiles.get(active.0).map(|profile| profile.color as usize);
+
+            if let (Some(before), Some(after)) = (before, after) {
+                if let Some(slot) = taken_colors.iter_mut().find(|color| **color == before) {
+                    *slot = after;
+                }
+            }
+        }
+    }
+
+    if roster_changed || joined.is_empty() {
+        return;
+    }
+
+    if lobby_start_blocker(joined.iter().map(|(_, _, _, lobby, _)| &lobby.seat), kind).is_some() {
+        return;
+    }
+
+    let mut area_shape = AreaShape::new(kind);
+
+    for (entity, player, _, lobby, active) in joined.iter() {
+        let Some(team) = lobby.seat.side.and_then(|side| area_shape.get_team_mut(side)) else { continue };
+        let Some(profile) = profiles.get(active.0) else { continue };
+
+        team.players.push(player.id);
+        commands
+            .entity(entity)
+            .remove::<LocalLobbyPlayer>()
+            .insert(profile.identity());
+    }
+
+    config.area_shape = area_shape;
+    write_profiles(&profiles);
+
+    commands.entity(menu_entity).despawn();
+    AreaBundle::spawn(&config, &mut commands, &mut meshes, &mut materials);
+}
+
+/// Rebuilds the team lists and the status line whenever someone joins,
+/// leaves or changes their seat, profile or colour.
+pub fn u_local_lobby_list(
+    lists: Query<(Entity, &LocalLobbyTeamList)>,
+    added_lists: Query<(), Added<LocalLobbyTeamList>>,
+    players: Query<(&Player, &LocalLobbyPlayer, &ActiveProfile, Option<&GamepadNumber>)>,
+    changed: Query<(), Or<(Changed<LocalLobbyPlayer>, Changed<ActiveProfile>)>>,
+    profiles: Res<Profiles>,
+    mut removed: RemovedComponents<LocalLobbyPlayer>,
+    status: Option<Single<&mut Text, With<LocalLobbyStatusText>>>,
+    config: Res<GameModeConfig>,
+    mut commands: Commands,
+) {
+    let any_removed = !removed.is_empty();
+    removed.clear();
+
+    let Some(mut status) = status else { return };
+    if changed.is_empty() && !any_removed && added_lists.is_empty() && !profiles.is_changed() {
+        return;
+    }
+
+    for (list_entity, list) in lists.iter() {
+        commands.entity(list_entity).despawn_related::<Children>();
+        commands.entity(list_entity).with_children(|parent| {
+            for (player, lobby, active, number) in players.iter() {
+                let Some(identity) = profiles.get(active.0).map(|profile| profile.identity()) else { continue };
+
+                if lobby.seat.side == Some(list.0) {
+                    parent.spawn(LabelBundle::custom(&local_player_label(player, lobby, &identity, number), identity.color(), 28.0));
+                }
+            }
+        });
+    }
+
+    status.0 = if players.is_empty() {
+        "Press any button to join, or Pause to go back".to_owned()
+    } else {
+        let seats = players.iter().map(|(_, lobby, ..)| &lobby.seat);
+        lobby_start_blocker(seats, config.area_shape.kind()).unwrap_or("Starting").to_owned()
+    };
+}
+
+fn local_player_label(player: &Player, lobby: &LocalLobbyPlayer, identity: &PlayerIdentity, number: Option<&GamepadNumber>) -> String {
+    let device = match (player.id.local(), number) {
+        (LocalPlayerID::Keyboard(id), _) => format!("Keyboard {id}"),
+        (LocalPlayerID::Gamepad(_), Some(number)) => format!("Gamepad {}", number.0),
+        (LocalPlayerID::Gamepad(_), None) => "Gamepad".to_owned(),
+        (LocalPlayerID::Mouse, _) => "Mouse".to_owned(),
+    };
+
+    let mut label = format!("{} ({device})", identity.name);
+    if lobby.seat.ready {
+        label.push_str(" - Ready");
+    }
+
+    label
+}
+
+/// Which way an axis was just pushed past the threshold, if it was.
+fn axis_edge(value: f32, held: &mut bool) -> Option<bool> {
+    if value.abs() < LOBBY_AXIS_THRESHOLD {
+        *held = false;
+        return None;
+    }
+
+    if *held {
+        return None;
+    }
+
+    *held = true;
+    Some(value > 0.0)
+}
+
+/// The next index from `current` in either direction that nobody else
+/// holds, handing `current` back to the pool. Stays put when all are taken.
+fn next_free(current: usize, forward: bool, taken: &mut [usize], len: usize) -> usize {
+    let next = (1..len)
+        .map(|step| if forward { (current + step) % len } else { (current + len - step) % len })
+        .find(|i| !taken.contains(i))
+        .unwrap_or(current);
+
+    if let Some(slot) = taken.iter_mut().find(|i| **i == current) {
+        *slot = next;
+    }
+
+    next
+}
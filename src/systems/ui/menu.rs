@@ -1,15 +1,26 @@
-use crate::bundles::area::AreaBundle;
 use crate::bundles::widgets::LabelBundle;
-use crate::components::ui::{LobbyMenu, LobbyPlayerListNode, LobbySettingsDisplay, MainMenu, Menu, MonitorSelector, OfflinePlayMenu, OnlineCreateMenu, OnlinePlayMenu, PlayerJoinInMenu, RefreshRateSelector, RemoveInteractionDisabledTimer, ResolutionSelector, Selector, ServerEntry, ServerList, SettingsMenu, SourceHandle, UIOptionProvider, UIOptionString, VSyncSelector, WindowModeSelector};
-use crate::components::Player;
+use crate::components::ui::effects::HoverLight;
+use crate::components::MatchEntity;
+use crate::components::ui::{ChatLogNode, ConnectingMenu, ConnectionErrorMenu, DirectConnectMenu, DirectConnectStatus, RecentAddress, LobbyMenu, LobbyPlayerListNode, LobbySettingsDisplay, LobbyStartButton, LobbyStatusText, LobbyTeamButton, HostCommandButton, MainMenu, Menu, MonitorSelector, OfflinePlayMenu, OnlineCreateMenu, OnlinePlayMenu, RefreshRateSelector, RemoveInteractionDisabledTimer, ResolutionSelector, Selector, ServerEntry, ServerPasswordMenu, ServerFilterSelector, ServerList, ServerPingText, ServerSortSelector, JoinRoleSelector, SettingsMenu, SourceHandle, UIOptionProvider, UIOptionString, VSyncSelector, WindowModeSelector};
+use crate::components::{PlayerConnection, PlayerIdentity};
+use crate::events::gameplay::MatchEnded;
 use crate::events::widgets::{ButtonPressed, OptionChanged, SliderValueChanged, TextInputSubmitted};
 use crate::models::game::gameplay::GameMode;
-use crate::models::ui::option::{VSYNC_OPTIONS, VSYNC_OPTIONS_RAW};
-use crate::networking::client::{connect_to_server, send_discovery_message, ClientDiscoverySocket, DiscoveredServers};
-use crate::networking::server::start_server;
-use crate::resources::{GameModeConfig, GameSettings, MonitorInfo, Monitors, OnlineGameConfig, PendingSettings, PlayerAction, RefreshRate, Resolution};
-use crate::systems::settings::persistence::save_settings;
+use crate::models::ui::option::{TEAM_PALETTE_OPTIONS, TEAM_PALETTE_OPTIONS_RAW, TOGGLE_OPTIONS, TOGGLE_OPTIONS_RAW, VSYNC_OPTIONS, VSYNC_OPTIONS_RAW};
+use crate::events::network::{AddressResolved, ConnectionEstablished, ConnectionFailed, ConnectionFailure};
+use crate::networking::client::{connect_to_server, disconnect_from_server, request_role, resolve_server_address, GameClient, send_discovery_message, ClientDiscoverySocket, DiscoveredServer, DiscoveredServers};
+use crate::networking::server::{start_server, LobbyEntity};
+use crate::networking::lobby::{auto_balance, lobby_start_blocker, request_seat, start_online_match, HostSeat, LobbySeat, SeatRequest};
+use crate::components::MatchScore;
+use crate::resources::{GameModeConfig, GameSettings, MonitorInfo, Monitors, OnlineGameConfig, PendingSettings, Profiles, MAX_KEYBOARD_PLAYERS, RefreshRate, Resolution, ServerBrowserConfig, ServerFilter, ServerSortOrder};
+use crate::systems::settings::persistence::{save_settings, write_settings};
 use crate::systems::widgets::*;
+use crate::systems::chat::t_submit_chat;
+use crate::systems::controls::spawn_m_controls;
+use crate::systems::profiles::spawn_m_profiles;
+use crate::systems::local_lobby::spawn_m_local_lobby;
+use crate::networking::chat::HOST_CHAT_NAME;
+use crate::networking::moderation::{send_host_command, HostCommand, RoomHost};
 use crate::utils::MODERN_THEME;
 use bevy::ecs::query::Spawned;
 use bevy::input_focus::directional_navigation::DirectionalNavigationMap;
@@ -19,8 +30,34 @@ use bevy::reflect::Array;
 use bevy::render::render_resource::encase::private::RuntimeSizedArray;
 use bevy::ui::InteractionDisabled;
 use bevy::window::{PresentMode, PrimaryWindow, VideoMode, WindowMode};
-use leafwing_input_manager::action_state::ActionState;
-use crate::networking::protocol::LobbyConfig;
+use crate::networking::protocol::{peer_display_name, JoinRequest, JoinRole, LobbyConfig, RemotePlayerId, Spectator};
+use lightyear::prelude::{LocalId, MessageSender};
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+pub const SERVER_SORT_OPTIONS: SourceHandle<dyn UIOptionProvider> = SourceHandle::Static(&SERVER_SORT_OPTIONS_RAW);
+
+pub const SERVER_SORT_OPTIONS_RAW: [ServerSortOrder; 3] = [
+    ServerSortOrder::Ping,
+    ServerSortOrder::Name,
+    ServerSortOrder::Players,
+];
+
+pub const SERVER_FILTER_OPTIONS: SourceHandle<dyn UIOptionProvider> = SourceHandle::Static(&SERVER_FILTER_OPTIONS_RAW);
+
+pub const SERVER_FILTER_OPTIONS_RAW: [ServerFilter; 3] = [
+    ServerFilter::All,
+    ServerFilter::Joinable,
+    ServerFilter::Unlocked,
+];
+
+pub const JOIN_ROLE_OPTIONS: SourceHandle<dyn UIOptionProvider> = SourceHandle::Static(&JOIN_ROLE_OPTIONS_RAW);
+
+pub const JOIN_ROLE_OPTIONS_RAW: [JoinRole; 2] = [
+    JoinRole::Player,
+    JoinRole::Spectator,
+];
 
 pub const GAMEMODE_OPTIONS: SourceHandle<dyn UIOptionProvider> = SourceHandle::Static(&GAMEMODE_OPTIONS_RAW);
 
@@ -32,6 +69,11 @@ pub const GAMEMODE_OPTIONS_RAW: [GameMode; 5] = [
     GameMode::Twisted,
 ];
 
+#[inline]
+pub(crate) fn index_of_option<T: PartialEq>(options: &[T], value: &T) -> usize {
+    options.iter().position(|r| r == value).unwrap_or(0)
+}
+
 #[inline]
 fn index_of_game_mode(game_mode: &GameMode) -> usize {
     GAMEMODE_OPTIONS_RAW.iter().position(|r| r == game_mode).unwrap_or(0)
@@ -166,7 +208,7 @@ macro_rules! boxed_vec {
 pub fn spawn_m_offline<'a>(
     commands: &'a mut Commands,
     nav_map: &'a mut DirectionalNavigationMap,
-    config: &'a Res<GameModeConfig>
+    config: &GameModeConfig,
 ) -> EntityCommands<'a> {
 
     let mut base = spawn_m_base(commands, nav_map, OfflinePlayMenu);
@@ -215,9 +257,10 @@ pub fn spawn_m_offline<'a>(
         mut commands: Commands,
         mut nav_map: ResMut<DirectionalNavigationMap>,
         menu: Single<Entity, With<OfflinePlayMenu>>,
+        config: Res<GameModeConfig>,
     ) {
         commands.entity(*menu).despawn();
-        spawn_m_player_join_in(&mut commands, &mut nav_map, 1);
+        spawn_m_local_lobby(&mut commands, &mut nav_map, &config);
     }
     fn on_back(
         _: On<ButtonPressed>,
@@ -238,7 +281,7 @@ pub fn spawn_m_offline<'a>(
                 if let Some(change) = selector.current::<GameMode>() {
                     config.game_mode = *change;
 
-                    println!("Game mode changed to {change:?}");
+                    debug!("Game mode changed to {change:?}");
                 }
 
                 break;
@@ -261,21 +304,6 @@ fn on_settings_back_main(
     save_settings(&settings);
 }
 
-fn spawn_m_player_join_in<'a>(commands: &'a mut Commands, nav_map: &'a mut DirectionalNavigationMap, player_num: u8) -> EntityCommands<'a> {
-
-    let mut base = spawn_m_base(commands, nav_map, PlayerJoinInMenu(player_num));
-
-    base.with_children(| parent |{
-
-        parent.spawn(w_menu_title(format!("Player {} Join In", player_num)));
-        parent.spawn((
-            w_menu_section(),
-        )).with_child(LabelBundle::button_label("Press any button to join..."));
-    });
-
-    base
-}
-
 pub fn spawn_m_online<'a>(
     commands: &'a mut Commands,
     nav_map: &'a mut DirectionalNavigationMap,
@@ -315,7 +343,7 @@ pub fn spawn_m_online<'a>(
 
 
     fn on_quick_match(_press: On<ButtonPressed>) {
-        println!("Searching for quick match...");
+        debug!("Searching for quick match...");
     }
 
     fn on_create_room(
@@ -333,13 +361,14 @@ pub fn spawn_m_online<'a>(
         menu: Single<Entity, With<OnlinePlayMenu>>,
         mut commands: Commands,
         mut nav_map: ResMut<DirectionalNavigationMap>,
+        browser: Res<ServerBrowserConfig>,
     ) {
         commands.entity(*menu).despawn();
-        spawn_m_online_join(&mut commands, &mut nav_map);
+        spawn_m_online_join(&mut commands, &mut nav_map, &browser, None);
     }
 
     fn on_friends_list(_press: On<ButtonPressed>) {
-        println!("Opening friends list...");
+        debug!("Opening friends list...");
     }
 
     fn on_back(
@@ -354,12 +383,50 @@ pub fn spawn_m_online<'a>(
 
 }
 
-fn spawn_m_online_join<'a>(commands: &'a mut Commands, nav_map: &'a mut DirectionalNavigationMap) -> EntityCommands<'a> {
+fn spawn_m_online_join<'a>(
+    commands: &'a mut Commands,
+    nav_map: &'a mut DirectionalNavigationMap,
+    browser: &ServerBrowserConfig,
+    notice: Option<&str>,
+) -> EntityCommands<'a> {
     let mut base = spawn_m_base(commands, nav_map, OnlinePlayMenu);
+    let mut entities: Vec<Entity> = Vec::new();
 
     base.with_children(|parent| {
         parent.spawn(w_menu_title("Join Room"));
+
+        if let Some(notice) = notice {
+            parent.spawn(LabelBundle::custom(notice, Color::srgb(0.9, 0.4, 0.3), 32.0));
+        }
+
+        parent.spawn(w_row_container(Val::Px(10.0))).with_children(|parent| {
+            let mut sort = parent.spawn_selector_interactable(
+                SERVER_SORT_OPTIONS,
+                index_of_option(&SERVER_SORT_OPTIONS_RAW, &browser.sort),
+                "Sort",
+                &mut entities,
+            );
+            sort.root.insert(ServerSortSelector).observe(on_sort_changed);
+
+            let mut filter = parent.spawn_selector_interactable(
+                SERVER_FILTER_OPTIONS,
+                index_of_option(&SERVER_FILTER_OPTIONS_RAW, &browser.filter),
+                "Show",
+                &mut entities,
+            );
+            filter.root.insert(ServerFilterSelector).observe(on_filter_changed);
+
+            let mut role = parent.spawn_selector_interactable(
+                JOIN_ROLE_OPTIONS,
+                index_of_option(&JOIN_ROLE_OPTIONS_RAW, &browser.role),
+                "Join As",
+                &mut entities,
+            );
+            role.root.insert(JoinRoleSelector).observe(on_role_changed);
+        });
+
         parent.spawn(w_menu_section()).with_children(|parent| {
+            parent.spawn(w_server_list_header());
             parent.spawn((
                 ServerList,
                 Node {
@@ -368,18 +435,27 @@ fn spawn_m_online_join<'a>(commands: &'a mut Commands, nav_map: &'a mut Directio
                 },
             ));
         });
-        
+
         parent.spawn(w_row_container(Val::Px(10.0)))
               .with_children(| parent |{
-                  
-              parent.spawn(w_menu_button(Color::srgb(0.6, 0.6, 0.6), "Back"))
-                  .observe(on_back);
-              
-              parent.spawn(w_menu_button(Color::srgb(0.1,0.1, 0.7), "Refresh"))
-                    .observe(on_refresh);
+
+              entities.push(parent.spawn(w_menu_button(Color::srgb(0.6, 0.6, 0.6), "Back"))
+                  .observe(on_back)
+                  .id());
+
+              entities.push(parent.spawn(w_menu_button(Color::srgb(0.1,0.1, 0.7), "Refresh"))
+                    .observe(on_refresh)
+                    .id());
+
+              entities.push(parent.spawn(w_menu_button(Color::srgb(0.9, 0.5, 0.3), "Direct Connect"))
+                    .observe(on_direct_connect)
+                    .id());
           });
     });
 
+    nav_map.add_looping_edges(&entities[..=3], CompassOctant::South);
+    nav_map.add_looping_edges(&entities[3..=5], CompassOctant::East);
+
     return base;
 
     fn on_back(
@@ -391,10 +467,10 @@ fn spawn_m_online_join<'a>(commands: &'a mut Commands, nav_map: &'a mut Directio
         commands.entity(*menu).despawn();
         spawn_m_online(&mut commands, &mut nav_map);
     }
-    
+
     fn on_refresh(
         press: On<ButtonPressed>,
-        socket: Res<ClientDiscoverySocket>,
+        mut socket: ResMut<ClientDiscoverySocket>,
         mut commands: Commands,
     ) {
         commands.entity(press.0)
@@ -403,48 +479,378 @@ fn spawn_m_online_join<'a>(commands: &'a mut Commands, nav_map: &'a mut Directio
                 Timer::from_seconds(5.0, TimerMode::Once)
             ));
 
-        send_discovery_message(&socket);
+        send_discovery_message(&mut socket);
+    }
+
+    fn on_direct_connect(
+        _: On<ButtonPressed>,
+        mut commands: Commands,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        menu: Single<Entity, With<OnlinePlayMenu>>,
+        settings: Res<GameSettings>,
+    ) {
+        commands.entity(*menu).despawn();
+        spawn_m_direct_connect(&mut commands, &mut nav_map, &settings);
+    }
+
+    fn on_sort_changed(
+        _: On<OptionChanged>,
+        selector: Single<&Selector, With<ServerSortSelector>>,
+        mut browser: ResMut<ServerBrowserConfig>,
+    ) {
+        if let Some(sort) = selector.current::<ServerSortOrder>() {
+            browser.sort = *sort;
+        }
+    }
+
+    fn on_filter_changed(
+        _: On<OptionChanged>,
+        selector: Single<&Selector, With<ServerFilterSelector>>,
+        mut browser: ResMut<ServerBrowserConfig>,
+    ) {
+        if let Some(filter) = selector.current::<ServerFilter>() {
+            browser.filter = *filter;
+        }
+    }
+
+    fn on_role_changed(
+        _: On<OptionChanged>,
+        selector: Single<&Selector, With<JoinRoleSelector>>,
+        mut browser: ResMut<ServerBrowserConfig>,
+    ) {
+        if let Some(role) = selector.current::<JoinRole>() {
+            browser.role = *role;
+        }
+    }
+}
+
+fn spawn_m_direct_connect<'a>(
+    commands: &'a mut Commands,
+    nav_map: &'a mut DirectionalNavigationMap,
+    settings: &GameSettings,
+) -> EntityCommands<'a> {
+    let mut base = spawn_m_base(commands, nav_map, DirectConnectMenu);
+    let mut entities: Vec<Entity> = Vec::new();
+
+    base.with_children(|parent| {
+        parent.spawn(w_menu_title("Direct Connect"));
+
+        parent.spawn(w_menu_section()).with_children(|parent| {
+            parent.spawn_input("Address: ").input.observe(on_submit);
+
+            parent.spawn((
+                LabelBundle::custom("host:port, [ipv6]:port", MODERN_THEME.text_normal, 28.0),
+                DirectConnectStatus,
+            ));
+        });
+
+        if !settings.recent_addresses.is_empty() {
+            parent.spawn(w_menu_section()).with_children(|parent| {
+                parent.spawn(LabelBundle::button_label("Recent"));
+
+                for address in &settings.recent_addresses {
+                    entities.push(
+                        parent
+                            .spawn((
+                                w_menu_button(MODERN_THEME.button, address.clone()),
+                                RecentAddress(address.clone()),
+                            ))
+                            .observe(on_recent)
+                            .id(),
+                    );
+                }
+            });
+        }
+
+        entities.push(
+            parent
+                .spawn(w_menu_button(Color::srgb(0.6, 0.6, 0.6), "Back"))
+                .observe(on_back)
+                .id(),
+        );
+    });
+
+    nav_map.add_looping_edges(&entities, CompassOctant::South);
+
+    return base;
+
+    fn on_submit(
+        submit: On<TextInputSubmitted>,
+        status: Single<&mut Text, With<DirectConnectStatus>>,
+        mut commands: Commands,
+    ) {
+        direct_connect(&submit.value, status, &mut commands);
+    }
+
+    fn on_recent(
+        press: On<ButtonPressed>,
+        recents: Query<&RecentAddress>,
+        status: Single<&mut Text, With<DirectConnectStatus>>,
+        mut commands: Commands,
+    ) {
+        if let Ok(recent) = recents.get(press.event_target()) {
+            direct_connect(&recent.0, status, &mut commands);
+        }
+    }
+
+    fn on_back(
+        _: On<ButtonPressed>,
+        mut commands: Commands,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        menu: Single<Entity, With<DirectConnectMenu>>,
+        browser: Res<ServerBrowserConfig>,
+    ) {
+        commands.entity(*menu).despawn();
+        spawn_m_online_join(&mut commands, &mut nav_map, &browser, None);
+    }
+
+    fn direct_connect(input: &str, mut status: Single<&mut Text, With<DirectConnectStatus>>, commands: &mut Commands) {
+        status.0.clear();
+        status.0.push_str("Resolving...");
+
+        resolve_server_address(input, commands);
+    }
+}
+
+/// Connects once the direct connect address is known. Lookups finishing
+/// after the player left the page are dropped.
+pub fn t_address_resolved(
+    resolved: On<AddressResolved>,
+    menu: Option<Single<Entity, With<DirectConnectMenu>>>,
+    mut status: Query<&mut Text, With<DirectConnectStatus>>,
+    browser: Res<ServerBrowserConfig>,
+    mut settings: ResMut<GameSettings>,
+    mut commands: Commands,
+    mut nav_map: ResMut<DirectionalNavigationMap>,
+) {
+    let Some(menu) = menu else { return };
+
+    match resolved.result {
+        Ok(address) => {
+            info!("Direct connecting to {address}");
+
+            settings.remember_address(&resolved.input);
+            write_settings(&settings);
+
+            commands.entity(*menu).despawn();
+            connect_to_server(address, settings.client_id(), browser.role, None, &mut commands);
+            spawn_m_connecting(&mut commands, &mut nav_map, address);
+        }
+        Err(e) => {
+            for mut status in status.iter_mut() {
+                status.0.clear();
+                status.0.push_str(e.message());
+            }
+        }
+    }
+}
+
+const SERVER_COLUMNS: [f32; 5] = [360.0, 130.0, 190.0, 130.0, 110.0];
+const SERVER_ENTRY_SIZE: Val2 = Val2::new(Val::Px(960.0), Val::Px(50.0));
+
+fn w_server_column(column: usize, text: impl Into<String>) -> impl Bundle {
+    (
+        Node {
+            width: Val::Px(SERVER_COLUMNS[column]),
+            ..default()
+        },
+        LabelBundle::button_label(text),
+    )
+}
+
+fn w_server_list_header() -> impl Bundle {
+    (
+        Node {
+            width: SERVER_ENTRY_SIZE.x,
+            padding: UiRect::horizontal(Val::Px(15.0)),
+            margin: UiRect::bottom(Val::Px(10.0)),
+            ..default()
+        },
+        children![
+            w_server_column(0, "Name"),
+            w_server_column(1, "Players"),
+            w_server_column(2, "Mode"),
+            w_server_column(3, "Access"),
+            w_server_column(4, "Ping"),
+        ],
+    )
+}
+
+fn w_server_entry(server: &DiscoveredServer, role: JoinRole) -> impl Bundle {
+    let color = if server.is_joinable_as(role) {
+        Color::srgb(0.3, 0.7, 0.5)
+    } else {
+        MODERN_THEME.button
+    };
+
+    (
+        ServerEntry(server.address),
+        Button,
+        Node {
+            width: SERVER_ENTRY_SIZE.x,
+            height: SERVER_ENTRY_SIZE.y,
+            align_items: AlignItems::Center,
+            padding: UiRect::horizontal(Val::Px(15.0)),
+            margin: UiRect::bottom(Val::Px(10.0)),
+            border: PIXEL_BORDER,
+            border_radius: BorderRadius::ZERO,
+            ..default()
+        },
+        BackgroundColor(color),
+        BorderColor::from(MODERN_THEME.border),
+        Outline::new(PIXEL_BORDER.bottom, Val::ZERO, MODERN_THEME.outline),
+        HoverLight(color),
+    )
+}
+
+fn format_ping(ping: Option<Duration>, string: &mut String) {
+    string.clear();
+
+    match ping {
+        Some(ping) => write!(string, "{} ms", ping.as_millis()).unwrap(),
+        None => string.push('-'),
     }
 }
 
 pub fn u_server_list(
     mut commands: Commands,
     servers: Res<DiscoveredServers>,
+    browser: Res<ServerBrowserConfig>,
     list: Option<Single<Entity, With<ServerList>>>,
     added_list: Query<(), Added<ServerList>>,
 ) {
     let list_just_opened = !added_list.is_empty();
-    if !servers.is_changed() && !list_just_opened { return; }
+    if !servers.is_changed() && !browser.is_changed() && !list_just_opened { return; }
 
     let Some(list_entity) = list else { return };
 
+    let mut visible: Vec<&DiscoveredServer> = servers
+        .servers
+        .values()
+        .filter(|server| match browser.filter {
+            ServerFilter::All => true,
+            ServerFilter::Joinable => server.is_joinable_as(browser.role),
+            ServerFilter::Unlocked => server.is_joinable_as(browser.role) && !server.is_locked(),
+        })
+        .collect();
+
+    visible.sort_by(|a, b| {
+        let order = match browser.sort {
+            ServerSortOrder::Ping => a
+                .ping
+                .unwrap_or(Duration::MAX)
+                .cmp(&b.ping.unwrap_or(Duration::MAX)),
+            ServerSortOrder::Name => {
+                let name = |s: &DiscoveredServer| s.info.as_ref().map(|i| i.name.to_lowercase());
+                name(a).cmp(&name(b))
+            }
+            ServerSortOrder::Players => {
+                let players = |s: &DiscoveredServer| s.info.as_ref().map_or(0, |i| i.current_players);
+                players(b).cmp(&players(a))
+            }
+        };
+
+        // incompatible servers always sink to the bottom
+        b.is_compatible()
+            .cmp(&a.is_compatible())
+            .then(order)
+            .then_with(|| a.address.cmp(&b.address))
+    });
 
     commands.entity(*list_entity).despawn_related::<Children>();
     commands.entity(*list_entity).with_children(|parent| {
-        if servers.servers.is_empty() {
+        if visible.is_empty() {
             parent.spawn(LabelBundle::button_label("Searching for servers..."));
             return;
         }
 
-        for server in &servers.servers {
-            parent.spawn((
-                ServerEntry(server.address),
-                w_menu_button(Color::srgb(0.3, 0.7, 0.5), format!("Name {} | {}", server.name, server.address.to_string())),
-            )).observe(on_server_selected);
+        for server in visible {
+            let mut entry = parent.spawn(w_server_entry(server, browser.role));
+
+            if !server.is_joinable_as(browser.role) {
+                entry.insert(InteractionDisabled);
+            }
+
+            entry.observe(on_server_selected).with_children(|row| {
+                let mut ping = String::new();
+                format_ping(server.ping, &mut ping);
+
+                match &server.info {
+                    Some(info) => {
+                        let mut mode = String::new();
+                        info.game_mode.push_ui_option_string(&mut mode);
+
+                        let mut players = if server.is_full() {
+                            format!("{}/{} Full", info.current_players, info.max_players)
+                        } else {
+                            format!("{}/{}", info.current_players, info.max_players)
+                        };
+
+                        if info.spectators > 0 {
+                            write!(players, " +{}", info.spectators).unwrap();
+                        }
+
+                        row.spawn(w_server_column(0, info.name.clone()));
+                        row.spawn(w_server_column(1, players));
+                        row.spawn(w_server_column(2, mode));
+                        row.spawn(w_server_column(3, if info.closed {
+                            "Closed"
+                        } else if info.locked {
+                            "Locked"
+                        } else {
+                            "Open"
+                        }));
+                    }
+                    None => {
+                        row.spawn(w_server_column(0, server.address.to_string()));
+                        row.spawn(w_server_column(1, "-"));
+                        row.spawn(w_server_column(2, format!("Version {}", server.version)));
+                        row.spawn(w_server_column(3, "Incompatible"));
+                    }
+                }
+
+                row.spawn((w_server_column(4, ping), ServerPingText(server.address)));
+            });
         }
     });
 
     fn on_server_selected(
         press: On<ButtonPressed>,
-        entries: Query<&ServerEntry>,
+        entries: Query<&ServerEntry, Without<InteractionDisabled>>,
+        menu: Single<Entity, With<OnlinePlayMenu>>,
+        servers: Res<DiscoveredServers>,
+        settings: Res<GameSettings>,
+        browser: Res<ServerBrowserConfig>,
         mut nav_map: ResMut<DirectionalNavigationMap>,
         mut commands: Commands
     ) {
         if let Ok(entry) = entries.get(press.event_target()) {
-            println!("Selected server: {}", entry.0);
+            debug!("Selected server: {}", entry.0);
+
+            commands.entity(*menu).despawn();
+
+            if servers.get(&entry.0).is_some_and(DiscoveredServer::is_locked) {
+                spawn_m_server_password(&mut commands, &mut nav_map, entry.0, None);
+            } else {
+                connect_to_server(entry.0, settings.client_id(), browser.role, None, &mut commands);
+                spawn_m_connecting(&mut commands, &mut nav_map, entry.0);
+            }
+        }
+    }
+}
+
+pub fn u_server_ping_text(
+    servers: Res<DiscoveredServers>,
+    mut texts: Query<(&mut Text, &ServerPingText)>,
+    mut buffer: Local<String>,
+) {
+    for (mut text, ping_text) in texts.iter_mut() {
+        let Some(server) = servers.servers.get(&ping_text.0) else { continue };
 
-            connect_to_server(entry.0, &mut commands);
-            spawn_m_lobby(&mut commands, &mut nav_map, false);
+        format_ping(server.ping, &mut buffer);
+
+        if text.0 != *buffer {
+            text.0.clone_from(&buffer);
         }
     }
 }
@@ -465,7 +871,7 @@ pub fn spawn_m_settings(
     nav_map: &mut DirectionalNavigationMap,
 ) {
     let cur_window_mode = index_for_window_mode(&settings.window_mode);
-    let mut entities: Vec<Entity> = Vec::with_capacity(9);
+    let mut entities: Vec<Entity> = Vec::with_capacity(15);
     commands.insert_resource(PendingSettings::from(settings));
 
 
@@ -503,6 +909,14 @@ pub fn spawn_m_settings(
                 entities.push(mas.thumb);
             }
 
+            {
+                section.spawn(LabelBundle::button_label("Music volume"));
+                let mut music = section.spawn_slider(0.0, 100.0, settings.music_volume);
+
+                music.root.observe(on_music_changed);
+                entities.push(music.thumb);
+            }
+
             {
                 let monitor_index = monitors.selected_monitor;
                 let monitor = monitors.get_current_monitor();
@@ -585,6 +999,36 @@ pub fn spawn_m_settings(
 
                 entities.push(v_sel.bar);
             }
+
+            {
+                section.spawn(LabelBundle::button_label("Keyboard Players"));
+                let mut players = section.spawn_slider(1.0, MAX_KEYBOARD_PLAYERS as f32, settings.keyboard_players as f32);
+
+                players.root.observe(on_keyboard_players_changed);
+                entities.push(players.thumb);
+            }
+
+            {
+                let mut rumble = section.spawn_selector(
+                    TOGGLE_OPTIONS,
+                    index_of_option(&TOGGLE_OPTIONS_RAW, &settings.rumble.enabled),
+                    "Rumble",
+                );
+
+                rumble.root.observe(on_rumble_changed);
+                entities.push(rumble.bar);
+            }
+
+            {
+                let mut palette = section.spawn_selector(
+                    TEAM_PALETTE_OPTIONS,
+                    index_of_option(&TEAM_PALETTE_OPTIONS_RAW, &settings.team_palette),
+                    "Team Colours",
+                );
+
+                palette.root.observe(on_team_palette_changed);
+                entities.push(palette.bar);
+            }
         });
 
         base.spawn(w_row_container(Val::Px(10.0)))
@@ -604,35 +1048,79 @@ pub fn spawn_m_settings(
                         .observe(on_settings_apply)
                         .id(),
                 );
+
+                entities.push(
+                    container
+                        .spawn(w_button(MODERN_THEME.button, "Controls", SIZE))
+                        .observe(on_controls)
+                        .id(),
+                );
+
+                entities.push(
+                    container
+                        .spawn(w_button(MODERN_THEME.button, "Profiles", SIZE))
+                        .observe(on_profiles)
+                        .id(),
+                );
             });
     });
 
-    nav_map.add_looping_edges(&entities[..=7], CompassOctant::South);
-    nav_map.add_looping_edges(
-        &[
-            entities[0],
-            entities[1],
-            entities[2],
-            entities[3],
-            entities[4],
-            entities[5],
-            entities[6],
-            entities[8],
-        ],
-        CompassOctant::South,
-    );
+    nav_map.add_looping_edges(&entities[..=11], CompassOctant::South);
+    nav_map.add_looping_edges(&[&entities[..=10], &entities[12..=12]].concat(), CompassOctant::South);
+    nav_map.add_looping_edges(&[&entities[..=10], &entities[13..=13]].concat(), CompassOctant::South);
+    nav_map.add_looping_edges(&[&entities[..=10], &entities[14..=14]].concat(), CompassOctant::South);
+
+    nav_map.add_looping_edges(&entities[11..=14], CompassOctant::East);
+
+    fn on_controls(
+        _: On<ButtonPressed>,
+        mut commands: Commands,
+        settings_menu: Single<Entity, With<SettingsMenu>>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        settings: Res<GameSettings>,
+    ) {
+        commands.entity(*settings_menu).despawn();
+        spawn_m_controls(&settings, 1, &mut commands, &mut nav_map);
+    }
 
-    nav_map.add_looping_edges(&entities[7..=8], CompassOctant::East);
+    fn on_profiles(
+        _: On<ButtonPressed>,
+        mut commands: Commands,
+        settings_menu: Single<Entity, With<SettingsMenu>>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        profiles: Res<Profiles>,
+    ) {
+        commands.entity(*settings_menu).despawn();
+        spawn_m_profiles(&profiles, profiles.online, &mut commands, &mut nav_map);
+    }
 
 
     fn on_sfx_changed(change: On<SliderValueChanged>, mut settings: ResMut<GameSettings>) {
         settings.sfx_volume = change.value;
-        println!("Changed SFX volume to {}", change.value);
     }
 
     fn on_master_changed(change: On<SliderValueChanged>, mut settings: ResMut<GameSettings>) {
         settings.master_volume = change.value;
-        println!("Changed MASTER volume to {}", change.value);
+    }
+
+    fn on_music_changed(change: On<SliderValueChanged>, mut settings: ResMut<GameSettings>) {
+        settings.music_volume = change.value;
+    }
+
+    fn on_keyboard_players_changed(change: On<SliderValueChanged>, mut settings: ResMut<GameSettings>) {
+        let players = change.value.round() as u8;
+
+        if settings.keyboard_players != players {
+            settings.keyboard_players = players;
+        }
+    }
+
+    fn on_rumble_changed(change: On<OptionChanged>, mut settings: ResMut<GameSettings>) {
+        settings.rumble.enabled = TOGGLE_OPTIONS_RAW[change.selected_index];
+    }
+
+    fn on_team_palette_changed(change: On<OptionChanged>, mut settings: ResMut<GameSettings>) {
+        settings.team_palette = TEAM_PALETTE_OPTIONS_RAW[change.selected_index];
     }
 
 
@@ -854,17 +1342,19 @@ fn spawn_m_online_create_pass<'a>(
         mut commands: Commands,
         mut config: ResMut<OnlineGameConfig>,
         mut nav_map: ResMut<DirectionalNavigationMap>,
-        lobby_config: Option<Single<&LobbyConfig>>,
+        lobby_config: Option<Single<(Entity, &LobbyConfig)>>,
     ) {
         config.pass = Some(submit.value.clone());
 
+        let lobby = lobby_config.as_deref().map_or_else(LobbyConfig::default, |(_, lobby)| LobbyConfig::clone(lobby));
+
         commands.entity(*menu).despawn();
-        start_server(&mut commands, &config, &lobby_config);
-        spawn_m_lobby(&mut commands, &mut nav_map, true);
+        start_server(&mut commands, &config, lobby_config.map(|lobby| lobby.0));
+        spawn_m_lobby(&mut commands, &mut nav_map, Some(&lobby));
     }
 }
 
-fn spawn_m_base<'a>(commands: &'a mut Commands, nav_map: &mut DirectionalNavigationMap, menu_type: impl Component) -> EntityCommands<'a> {
+pub(crate) fn spawn_m_base<'a>(commands: &'a mut Commands, nav_map: &mut DirectionalNavigationMap, menu_type: impl Component) -> EntityCommands<'a> {
 
     nav_map.clear();
 
@@ -922,45 +1412,17 @@ fn change_selector_visibility(
     }
 }
 
-pub fn u_join_in(
-    menus: Single<(Entity, &PlayerJoinInMenu)>,
-    player_query: Query<(&ActionState<PlayerAction>, &Player)>,
-    mut commands: Commands,
-    mut nav_map: ResMut<DirectionalNavigationMap>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut game_settings: ResMut<GameModeConfig>,
-) {
-    let player_num = menus.1.0 as usize;
-
-    for (action, player) in player_query {
-        let area = &mut game_settings.area_shape;
-        if !action.get_just_pressed().is_empty() && !area.contains_player(player.id) {
-            let teams_len = area.get_teams().len();
-
-            area.get_teams_mut()[player_num - 1].players.push(player.id);
-
-            commands.entity(menus.0).despawn();
-
-            if player_num < teams_len {
-                spawn_m_player_join_in(&mut commands, &mut nav_map, (player_num + 1) as u8);
-            } else {
-                AreaBundle::spawn(
-                    &game_settings,
-                    &mut commands,
-                    &mut meshes,
-                    &mut materials,
-                );
-            }
-        }
-    }
-}
+const LOBBY_CHAT_LINES: usize = 8;
 
+/// `hosted` is the live lobby config when this machine runs the server,
+/// the host's controls start out on its values.
 pub fn spawn_m_lobby(
     commands: &mut Commands,
     nav_map: &mut DirectionalNavigationMap,
-    is_host: bool,
+    hosted: Option<&LobbyConfig>,
 ) {
+    let is_host = hosted.is_some();
+    let lobby = hosted.cloned().unwrap_or_default();
     let mut entities: Vec<Entity> = Vec::new();
 
     spawn_m_base(commands, nav_map, LobbyMenu).with_children(|base| {
@@ -975,6 +1437,27 @@ pub fn spawn_m_lobby(
                     ..default()
                 },
             ));
+
+            sec.spawn(w_row_container(Val::Px(10.0))).with_children(|row| {
+                entities.push(
+                    row.spawn((w_menu_button(MODERN_THEME.button, "Switch Team"), LobbyTeamButton))
+                        .observe(on_cycle_side)
+                        .id(),
+                );
+                entities.push(
+                    row.spawn(w_menu_button(MODERN_THEME.button, "Ready"))
+                        .observe(on_toggle_ready)
+                        .id(),
+                );
+
+                if is_host {
+                    entities.push(
+                        row.spawn(w_menu_button(MODERN_THEME.button, "Auto Balance"))
+                            .observe(on_auto_balance)
+                            .id(),
+                    );
+                }
+            });
         });
 
         base.spawn(w_menu_section()).with_children(|sec| {
@@ -983,16 +1466,21 @@ pub fn spawn_m_lobby(
             if is_host {
                 let mut g_sel = sec.spawn_selector(
                     GAMEMODE_OPTIONS,
-                    0,
+                    index_of_game_mode(&lobby.game_mode),
                     "Game Mode",
                 );
                 g_sel.root.observe(on_gamemode_changed);
                 entities.push(g_sel.bar);
 
-                let mut pts = sec.spawn_slider(1.0, 50.0, 10.0);
+                let mut pts = sec.spawn_slider(1.0, 50.0, lobby.points_to_win as f32);
                 pts.root.observe(on_points_changed);
                 entities.push(pts.thumb);
 
+                sec.spawn(LabelBundle::button_label("Reconnect Grace (s)"));
+                let mut grace = sec.spawn_slider(0.0, 180.0, lobby.reconnect_grace_secs as f32);
+                grace.root.observe(on_grace_changed);
+                entities.push(grace.thumb);
+
             } else {
                 sec.spawn((
                     LabelBundle::button_label("Waiting for host..."),
@@ -1001,11 +1489,71 @@ pub fn spawn_m_lobby(
             }
         });
 
-        base.spawn(w_row_container(Val::Px(10.0))).with_children(|row| {
-            if is_host {
-                entities.push(
-                    row.spawn(w_menu_button(Color::srgb(0.2, 0.7, 0.3), "Start Game"))
-                        .observe(on_host_start)
+        if is_host {
+            base.spawn(w_menu_section()).with_children(|sec| {
+                sec.spawn(LabelBundle::button_label("Netcode"));
+                let netcode = lobby.netcode;
+
+                sec.spawn(LabelBundle::button_label("Input Delay (ticks)"));
+                let mut delay = sec.spawn_slider(0.0, 10.0, netcode.input_delay_ticks as f32);
+                delay.root.observe(on_input_delay_changed);
+                entities.push(delay.thumb);
+
+                sec.spawn(LabelBundle::button_label("Max Prediction (ticks)"));
+                let mut prediction = sec.spawn_slider(0.0, 200.0, netcode.max_prediction_ticks as f32);
+                prediction.root.observe(on_max_prediction_changed);
+                entities.push(prediction.thumb);
+
+                sec.spawn(LabelBundle::button_label("Position Rollback Threshold"));
+                let mut position = sec.spawn_slider_stepped(0.01, 5.0, netcode.position_rollback_threshold, 0.01);
+                position.root.observe(on_position_threshold_changed);
+                entities.push(position.thumb);
+
+                sec.spawn(LabelBundle::button_label("Rotation Rollback Threshold"));
+                let mut rotation = sec.spawn_slider_stepped(0.01, 0.5, netcode.rotation_rollback_threshold, 0.01);
+                rotation.root.observe(on_rotation_threshold_changed);
+                entities.push(rotation.thumb);
+
+                sec.spawn(LabelBundle::button_label("Interpolation Delay (ms)"));
+                let mut interpolation = sec.spawn_slider(0.0, 300.0, netcode.interpolation_delay_ms as f32);
+                interpolation.root.observe(on_interpolation_delay_changed);
+                entities.push(interpolation.thumb);
+            });
+        }
+
+        base.spawn(w_menu_section()).with_children(|sec| {
+            sec.spawn(LabelBundle::button_label("Chat"));
+            sec.spawn((
+                ChatLogNode(LOBBY_CHAT_LINES),
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+            ));
+            sec.spawn_input("Say: ").input.observe(t_submit_chat);
+        });
+
+        base.spawn((
+            LabelBundle::custom("", MODERN_THEME.text_normal, 28.0),
+            LobbyStatusText,
+        ));
+
+        base.spawn(w_row_container(Val::Px(10.0))).with_children(|row| {
+            if is_host {
+                entities.push(
+                    row.spawn((
+                        w_menu_button(Color::srgb(0.2, 0.7, 0.3), "Start Game"),
+                        LobbyStartButton,
+                        InteractionDisabled,
+                    ))
+                    .observe(on_host_start)
+                    .id(),
+                );
+            }
+            if !is_host {
+                entities.push(
+                    row.spawn(w_menu_button(MODERN_THEME.button, "Play / Spectate"))
+                        .observe(on_switch_role)
                         .id(),
                 );
             }
@@ -1019,6 +1567,8 @@ pub fn spawn_m_lobby(
 
     if is_host {
         nav_map.add_looping_edges(&entities, CompassOctant::South);
+    } else {
+        nav_map.add_looping_edges(&entities, CompassOctant::East);
     }
 
     fn on_gamemode_changed(
@@ -1043,21 +1593,600 @@ pub fn spawn_m_lobby(
         config.points_to_win = change.value as u32;
     }
 
-    fn on_host_start(_: On<ButtonPressed>, mut commands: Commands) {
+    fn on_grace_changed(
+        change: On<SliderValueChanged>,
+        mut config: Single<&mut LobbyConfig>,
+    ) {
+        config.reconnect_grace_secs = change.value as u32;
+    }
+
+    fn on_input_delay_changed(
+        change: On<SliderValueChanged>,
+        mut config: Single<&mut LobbyConfig>,
+    ) {
+        config.netcode.input_delay_ticks = change.value.round() as u16;
+    }
+
+    fn on_max_prediction_changed(
+        change: On<SliderValueChanged>,
+        mut config: Single<&mut LobbyConfig>,
+    ) {
+        config.netcode.max_prediction_ticks = change.value.round() as u16;
+    }
+
+    fn on_position_threshold_changed(
+        change: On<SliderValueChanged>,
+        mut config: Single<&mut LobbyConfig>,
+    ) {
+        config.netcode.position_rollback_threshold = change.value;
+    }
+
+    fn on_rotation_threshold_changed(
+        change: On<SliderValueChanged>,
+        mut config: Single<&mut LobbyConfig>,
+    ) {
+        config.netcode.rotation_rollback_threshold = change.value;
+    }
+
+    fn on_interpolation_delay_changed(
+        change: On<SliderValueChanged>,
+        mut config: Single<&mut LobbyConfig>,
+    ) {
+        config.netcode.interpolation_delay_ms = change.value.round() as u32;
+    }
 
+    fn on_cycle_side(
+        _: On<ButtonPressed>,
+        client: Option<Single<&mut MessageSender<SeatRequest>, With<GameClient>>>,
+        host_seat: Option<Single<&mut LobbySeat, With<HostSeat>>>,
+        lobby: Option<Single<&LobbyConfig>>,
+    ) {
+        request_seat(
+            SeatRequest::CycleSide,
+            client.map(|client| client.into_inner()),
+            host_seat.map(|seat| seat.into_inner().into_inner()),
+            lobby.map(|lobby| lobby.area_shape).unwrap_or_default(),
+        );
+    }
+
+    fn on_toggle_ready(
+        _: On<ButtonPressed>,
+        client: Option<Single<&mut MessageSender<SeatRequest>, With<GameClient>>>,
+        host_seat: Option<Single<&mut LobbySeat, With<HostSeat>>>,
+        lobby: Option<Single<&LobbyConfig>>,
+    ) {
+        request_seat(
+            SeatRequest::ToggleReady,
+            client.map(|client| client.into_inner()),
+            host_seat.map(|seat| seat.into_inner().into_inner()),
+            lobby.map(|lobby| lobby.area_shape).unwrap_or_default(),
+        );
+    }
+
+    fn on_auto_balance(
+        _: On<ButtonPressed>,
+        mut seats: Query<&mut LobbySeat>,
+        lobby: Single<&LobbyConfig>,
+    ) {
+        auto_balance(seats.iter_mut(), lobby.area_shape);
+    }
+
+    fn on_host_start(
+        press: On<ButtonPressed>,
+        buttons: Query<(), (With<LobbyStartButton>, Without<InteractionDisabled>)>,
+        menu: Single<Entity, With<LobbyMenu>>,
+        lobby: Single<&LobbyConfig>,
+        mut seats: Query<(&mut LobbySeat, Option<&RemotePlayerId>)>,
+        mut game_config: ResMut<GameModeConfig>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<ColorMaterial>>,
+        mut commands: Commands,
+    ) {
+        if buttons.get(press.event_target()).is_err() {
+            return;
+        }
+
+        if start_online_match(
+            &lobby,
+            seats.iter_mut(),
+            &mut game_config,
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+        ) {
+            commands.entity(*menu).despawn();
+        }
+    }
+
+    fn on_switch_role(
+        _: On<ButtonPressed>,
+        client: Single<(&mut GameClient, &mut MessageSender<JoinRequest>)>,
+        profiles: Res<Profiles>,
+    ) {
+        let (mut client, mut sender) = client.into_inner();
+
+        let role = match client.role {
+            JoinRole::Player => JoinRole::Spectator,
+            JoinRole::Spectator => JoinRole::Player,
+        };
+
+        request_role(&mut client, &mut sender, role, profiles.online_identity());
     }
 
     fn on_leave(
         _: On<ButtonPressed>,
         menu: Single<Entity, With<LobbyMenu>>,
+        clients: Query<Entity, With<GameClient>>,
         mut commands: Commands,
         mut nav_map: ResMut<DirectionalNavigationMap>,
     ) {
         commands.entity(*menu).despawn();
+        disconnect_from_server(&mut commands, &clients);
         spawn_m_main(&mut commands, &mut nav_map);
     }
 }
 
+/// Lists everyone in the room, with moderation buttons for whoever holds
+/// [`RoomHost`]. The lock state comes from the replicated lobby config, so a
+/// remote host sees it as well and toggles it through a [`HostCommand`].
+pub fn u_lobby_player_list(
+    mut commands: Commands,
+    list: Option<Single<Entity, With<LobbyPlayerListNode>>>,
+    added_list: Query<(), Added<LobbyPlayerListNode>>,
+    players: Query<(&RemotePlayerId, &PlayerConnection, Option<&LobbySeat>, Has<RoomHost>, Option<&PlayerIdentity>)>,
+    host: Option<Single<(&LobbySeat, Has<RoomHost>), With<HostSeat>>>,
+    spectators: Query<&Spectator>,
+    client: Option<Single<&LocalId, With<GameClient>>>,
+    lobby: Option<Single<&LobbyConfig, With<LobbyEntity>>>,
+    changed: Query<(), Or<(Changed<PlayerConnection>, Changed<LobbySeat>, Changed<PlayerIdentity>, Added<Spectator>, Added<RoomHost>)>>,
+    mut removed: RemovedComponents<PlayerConnection>,
+    mut removed_spectators: RemovedComponents<Spectator>,
+    mut removed_hosts: RemovedComponents<RoomHost>,
+    mut shown_locked: Local<bool>,
+    old_buttons: Query<Entity, With<HostCommandButton>>,
+    team_button: Option<Single<Entity, With<LobbyTeamButton>>>,
+    mut nav_map: ResMut<DirectionalNavigationMap>,
+) {
+    let any_removed = !removed.is_empty() || !removed_spectators.is_empty() || !removed_hosts.is_empty();
+    removed.clear();
+    removed_spectators.clear();
+    removed_hosts.clear();
+
+    let locked = lobby.is_some_and(|lobby| lobby.locked);
+    let lock_changed = *shown_locked != locked;
+    *shown_locked = locked;
+
+    let Some(list_entity) = list else { return };
+    if changed.is_empty() && !any_removed && !lock_changed && added_list.is_empty() { return; }
+
+    // a client holds host rights through its own player, this machine
+    // otherwise only when it runs the listen server
+    let own_peer = client.map(|id| id.0);
+    let moderating = match own_peer {
+        Some(peer) => players.iter().any(|(id, _, _, room_host, _)| id.0 == peer && room_host),
+        None => host.as_ref().is_some_and(|host| host.1),
+    };
+
+    let mut players: Vec<_> = players
+        .iter()
+        .map(|(id, connection, seat, room_host, identity)| (id, connection, seat, room_host, id.name_with(identity)))
+        .collect();
+    players.sort_by(|a, b| a.4.cmp(&b.4));
+
+    for button in old_buttons.iter() {
+        nav_map.remove(button);
+    }
+
+    // one row of buttons per player or spectator, then the lock button
+    let mut rows: Vec<Vec<Entity>> = Vec::new();
+
+    commands.entity(*list_entity).despawn_related::<Children>();
+    commands.entity(*list_entity).with_children(|parent| {
+        if let Some((seat, room_host)) = host.as_deref() {
+            parent.spawn(LabelBundle::button_label(seat_label(HOST_CHAT_NAME, seat, *room_host)));
+        }
+
+        for (id, connection, seat, room_host, name) in players {
+            match connection {
+                PlayerConnection::Connected => {
+                    let seat = seat.copied().unwrap_or_default();
+                    let label = seat_label(&name, &seat, room_host);
+
+                    if !moderating || own_peer == Some(id.0) {
+                        parent.spawn(LabelBundle::button_label(label));
+                        continue;
+                    }
+
+                    rows.push(w_moderation_row(parent, label, &[
+                        ("Kick", HostCommand::Kick(id.0)),
+                        ("Ban", HostCommand::Ban(id.0)),
+                        ("Make Host", HostCommand::TransferHost(id.0)),
+                    ]));
+                }
+                PlayerConnection::Reconnecting { remaining_secs } => {
+                    parent.spawn(LabelBundle::custom(
+                        &format!("{name} (reconnecting, {remaining_secs}s)"),
+                        Color::srgb(1.0, 0.8, 0.3),
+                        32.0,
+                    ));
+                }
+            }
+        }
+
+        if moderating {
+            for spectator in spectators.iter() {
+                let label = format!("{} - Watching", peer_display_name(spectator.0));
+
+                rows.push(w_moderation_row(parent, label, &[
+                    ("Kick", HostCommand::Kick(spectator.0)),
+                    ("Ban", HostCommand::Ban(spectator.0)),
+                ]));
+            }
+        } else {
+            let spectators = spectators.iter().count();
+            if spectators > 0 {
+                parent.spawn(LabelBundle::custom(
+                    &format!("{spectators} watching"),
+                    MODERN_THEME.text_normal,
+                    28.0,
+                ));
+            }
+        }
+
+        if moderating {
+            let text = if locked { "Unlock Room" } else { "Lock Room" };
+            let lock = parent
+                .spawn((w_button(MODERN_THEME.button, text, MODERATION_BUTTON_SIZE), HostCommandButton(HostCommand::SetLocked(!locked))))
+                .observe(on_host_command_button)
+                .id();
+            rows.push(vec![lock]);
+        } else if locked {
+            parent.spawn(LabelBundle::custom("Room locked", MODERN_THEME.text_normal, 28.0));
+        }
+    });
+
+    // rows loop sideways, their first buttons lead down into the lobby's own
+    for row in &rows {
+        nav_map.add_looping_edges(row, CompassOctant::East);
+    }
+
+    let mut column: Vec<Entity> = rows.iter().map(|row| row[0]).collect();
+    if let Some(team_button) = team_button {
+        column.push(*team_button);
+    }
+    nav_map.add_edges(&column, CompassOctant::South);
+
+    /// A label followed by the host's buttons for one occupant of the room.
+    fn w_moderation_row(parent: &mut ChildSpawnerCommands, label: String, buttons: &[(&str, HostCommand)]) -> Vec<Entity> {
+        let mut entities = Vec::with_capacity(buttons.len());
+
+        parent.spawn(w_row_container(Val::Px(10.0))).with_children(|row| {
+            row.spawn(LabelBundle::button_label(label));
+
+            for (text, command) in buttons {
+                entities.push(
+                    row.spawn((w_button(MODERN_THEME.button, *text, MODERATION_BUTTON_SIZE), HostCommandButton(*command)))
+                        .observe(on_host_command_button)
+                        .id(),
+                );
+            }
+        });
+
+        entities
+    }
+
+    fn on_host_command_button(
+        press: On<ButtonPressed>,
+        buttons: Query<&HostCommandButton>,
+        client: Option<Single<&mut MessageSender<HostCommand>, With<GameClient>>>,
+        mut commands: Commands,
+    ) {
+        if let Ok(button) = buttons.get(press.event_target()) {
+            send_host_command(button.0, client.map(|client| client.into_inner()), &mut commands);
+        }
+    }
+}
+
+const MODERATION_BUTTON_SIZE: Val2 = Val2::new(Val::Px(160.0), Val::Px(40.0));
+
+fn seat_label(name: &str, seat: &LobbySeat, room_host: bool) -> String {
+    let mut label = if room_host {
+        format!("{name} (Host) - ")
+    } else {
+        format!("{name} - ")
+    };
+
+    match seat.side {
+        Some(side) => side.push_ui_option_string(&mut label),
+        None => label.push_str("No team"),
+    }
+
+    if seat.ready {
+        label.push_str(" - Ready");
+    }
+
+    label
+}
+
+/// Shows clients the settings picked by the host, which only reach them
+/// through the replicated lobby config.
+pub fn u_lobby_settings_display(
+    lobby: Option<Single<Ref<LobbyConfig>>>,
+    mut labels: Query<(Ref<LobbySettingsDisplay>, &mut Text)>,
+) {
+    let Some(lobby) = lobby else { return };
+
+    for (label, mut text) in labels.iter_mut() {
+        if !lobby.is_changed() && !label.is_added() {
+            continue;
+        }
+
+        text.0.clear();
+        lobby.game_mode.push_ui_option_string(&mut text.0);
+        text.0.push_str(" - ");
+        lobby.area_shape.push_ui_option_string(&mut text.0);
+        write!(text.0, " - First to {} - Up to {} players", lobby.points_to_win, lobby.max_players).unwrap();
+    }
+}
+
+/// Only lets the host start once [`lobby_start_blocker`] has nothing left
+/// to say, and shows everyone what is still missing.
+pub fn u_lobby_start_state(
+    lobby: Option<Single<&LobbyConfig>>,
+    seats: Query<&LobbySeat>,
+    status: Option<Single<&mut Text, With<LobbyStatusText>>>,
+    buttons: Query<(Entity, Has<InteractionDisabled>), With<LobbyStartButton>>,
+    mut commands: Commands,
+) {
+    let Some(lobby) = lobby else { return };
+    let blocker = lobby_start_blocker(seats.iter(), lobby.area_shape);
+
+    if let Some(mut status) = status {
+        let text = blocker.unwrap_or("Ready to start");
+        if status.0 != text {
+            status.0 = text.to_owned();
+        }
+    }
+
+    for (button, disabled) in buttons.iter() {
+        if blocker.is_none() && disabled {
+            commands.entity(button).remove::<InteractionDisabled>();
+        } else if blocker.is_some() && !disabled {
+            commands.entity(button).insert(InteractionDisabled);
+        }
+    }
+}
+
+/// Clients leave the lobby once the server's match reaches them.
+pub fn t_close_lobby_on_match(
+    _: On<Add, MatchScore>,
+    menu: Option<Single<Entity, With<LobbyMenu>>>,
+    mut commands: Commands,
+) {
+    if let Some(menu) = menu {
+        commands.entity(*menu).despawn();
+    }
+}
+
+/// Back to the lobby the match was started from once it is over. Clients
+/// clear what the server does not replicate, like the score labels.
+pub fn t_return_to_lobby(
+    _: On<MatchEnded>,
+    match_entities: Query<Entity, With<MatchEntity>>,
+    clients: Query<(), With<GameClient>>,
+    lobby: Option<Single<&LobbyConfig>>,
+    config: Res<GameModeConfig>,
+    mut commands: Commands,
+    mut nav_map: ResMut<DirectionalNavigationMap>,
+) {
+    for entity in match_entities.iter() {
+        commands.entity(entity).try_despawn();
+    }
+
+    if !clients.is_empty() {
+        spawn_m_lobby(&mut commands, &mut nav_map, None);
+    } else if let Some(lobby) = lobby {
+        spawn_m_lobby(&mut commands, &mut nav_map, Some(*lobby));
+    } else {
+        spawn_m_local_lobby(&mut commands, &mut nav_map, &config);
+    }
+}
+
+fn spawn_m_server_password<'a>(
+    commands: &'a mut Commands,
+    nav_map: &'a mut DirectionalNavigationMap,
+    address: SocketAddr,
+    notice: Option<&str>,
+) -> EntityCommands<'a> {
+    let mut base = spawn_m_base(commands, nav_map, ServerPasswordMenu(address));
+    let mut entities: Vec<Entity> = Vec::new();
+
+    base.with_children(|parent| {
+        parent.spawn(w_menu_title("Password Required"));
+
+        parent.spawn(w_menu_section()).with_children(|parent| {
+            parent.spawn_input("Password: ").input.observe(on_submit);
+
+            if let Some(notice) = notice {
+                parent.spawn(LabelBundle::custom(notice, MODERN_THEME.text_normal, 28.0));
+            }
+        });
+
+        entities.push(
+            parent
+                .spawn(w_menu_button(Color::srgb(0.6, 0.6, 0.6), "Back"))
+                .observe(on_back)
+                .id(),
+        );
+    });
+
+    nav_map.add_looping_edges(&entities, CompassOctant::South);
+
+    return base;
+
+    fn on_submit(
+        submit: On<TextInputSubmitted>,
+        menu: Single<(Entity, &ServerPasswordMenu)>,
+        settings: Res<GameSettings>,
+        browser: Res<ServerBrowserConfig>,
+        mut commands: Commands,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+    ) {
+        let (entity, menu) = *menu;
+        let address = menu.0;
+
+        commands.entity(entity).despawn();
+        connect_to_server(address, settings.client_id(), browser.role, Some(submit.value.clone()), &mut commands);
+        spawn_m_connecting(&mut commands, &mut nav_map, address);
+    }
+
+    fn on_back(
+        _: On<ButtonPressed>,
+        menu: Single<Entity, With<ServerPasswordMenu>>,
+        browser: Res<ServerBrowserConfig>,
+        mut commands: Commands,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+    ) {
+        commands.entity(*menu).despawn();
+        spawn_m_online_join(&mut commands, &mut nav_map, &browser, None);
+    }
+}
+
+fn spawn_m_connecting<'a>(
+    commands: &'a mut Commands,
+    nav_map: &'a mut DirectionalNavigationMap,
+    address: SocketAddr,
+) -> EntityCommands<'a> {
+    let mut base = spawn_m_base(commands, nav_map, ConnectingMenu);
+
+    base.with_children(|parent| {
+        parent.spawn(w_menu_title("Connecting..."));
+        parent
+            .spawn(w_menu_section())
+            .with_child(LabelBundle::button_label(format!("Connecting to {address}")));
+
+        parent
+            .spawn(w_menu_button(Color::srgb(0.6, 0.6, 0.6), "Cancel"))
+            .observe(on_cancel);
+    });
+
+    return base;
+
+    fn on_cancel(
+        _: On<ButtonPressed>,
+        menu: Single<Entity, With<ConnectingMenu>>,
+        clients: Query<Entity, With<GameClient>>,
+        browser: Res<ServerBrowserConfig>,
+        mut commands: Commands,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+    ) {
+        commands.entity(*menu).despawn();
+        disconnect_from_server(&mut commands, &clients);
+        spawn_m_online_join(&mut commands, &mut nav_map, &browser, None);
+    }
+}
+
+fn spawn_m_connection_error<'a>(
+    commands: &'a mut Commands,
+    nav_map: &'a mut DirectionalNavigationMap,
+    address: SocketAddr,
+    failure: &ConnectionFailure,
+) -> EntityCommands<'a> {
+    let mut entities: Vec<Entity> = Vec::new();
+    let mut base = spawn_m_base(commands, nav_map, ConnectionErrorMenu(address));
+
+    base.with_children(|parent| {
+        parent.spawn(w_menu_title(failure.title()));
+        parent
+            .spawn(w_menu_section())
+            .with_child(LabelBundle::button_label(failure.description()));
+
+        parent.spawn(w_row_container(Val::Px(10.0))).with_children(|row| {
+            entities.push(
+                row.spawn(w_menu_button(Color::srgb(0.2, 0.7, 0.3), "Retry"))
+                    .observe(on_retry)
+                    .id(),
+            );
+            entities.push(
+                row.spawn(w_menu_button(Color::srgb(0.6, 0.6, 0.6), "Back"))
+                    .observe(on_back)
+                    .id(),
+            );
+        });
+    });
+
+    nav_map.add_looping_edges(&entities, CompassOctant::East);
+
+    return base;
+
+    fn on_retry(
+        _: On<ButtonPressed>,
+        menu: Single<(Entity, &ConnectionErrorMenu)>,
+        settings: Res<GameSettings>,
+        browser: Res<ServerBrowserConfig>,
+        mut commands: Commands,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+    ) {
+        let (entity, menu) = *menu;
+        let address = menu.0;
+
+        commands.entity(entity).despawn();
+        connect_to_server(address, settings.client_id(), browser.role, None, &mut commands);
+        spawn_m_connecting(&mut commands, &mut nav_map, address);
+    }
+
+    fn on_back(
+        _: On<ButtonPressed>,
+        menu: Single<Entity, With<ConnectionErrorMenu>>,
+        browser: Res<ServerBrowserConfig>,
+        mut commands: Commands,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+    ) {
+        commands.entity(*menu).despawn();
+        spawn_m_online_join(&mut commands, &mut nav_map, &browser, None);
+    }
+}
+
+pub fn t_connection_established(
+    _: On<ConnectionEstablished>,
+    menu: Option<Single<Entity, With<ConnectingMenu>>>,
+    mut commands: Commands,
+    mut nav_map: ResMut<DirectionalNavigationMap>,
+) {
+    // the player may have backed out of the connecting screen already
+    let Some(menu) = menu else { return };
+
+    commands.entity(*menu).despawn();
+    spawn_m_lobby(&mut commands, &mut nav_map, None);
+}
+
+pub fn t_connection_failed(
+    failed: On<ConnectionFailed>,
+    menus: Query<Entity, With<Menu>>,
+    match_entities: Query<Entity, With<MatchEntity>>,
+    browser: Res<ServerBrowserConfig>,
+    mut commands: Commands,
+    mut nav_map: ResMut<DirectionalNavigationMap>,
+) {
+    for entity in menus.iter().chain(match_entities.iter()) {
+        commands.entity(entity).despawn();
+    }
+
+    match &failed.failure {
+        ConnectionFailure::Disconnected { .. } | ConnectionFailure::Removed { .. } => {
+            let notice = failed.failure.description();
+            spawn_m_online_join(&mut commands, &mut nav_map, &browser, Some(&notice));
+        }
+        ConnectionFailure::WrongPassword { .. } => {
+            let notice = failed.failure.description();
+            spawn_m_server_password(&mut commands, &mut nav_map, failed.address, Some(&notice));
+        }
+        failure => {
+            spawn_m_connection_error(&mut commands, &mut nav_map, failed.address, failure);
+        }
+    }
+}
+
 impl UIOptionString for WindowMode {
     fn push_ui_option_string(&self, string: &mut String) {
         let s = match self {
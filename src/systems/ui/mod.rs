@@ -1,3 +1,8 @@
+pub mod chat;
+pub mod controls;
+pub mod local_lobby;
 pub mod menu;
+pub mod net_stats;
+pub mod profiles;
 pub mod selectors;
 pub mod widgets;
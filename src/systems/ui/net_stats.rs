@@ -0,0 +1,140 @@
+use crate::bundles::widgets::LabelBundle;
+use crate::components::ui::{NetStatsBar, NetStatsOverlay, NetStatsText};
+use crate::networking::stats::{NetSample, NetStats, NET_STATS_HISTORY};
+use crate::resources::MenuAction;
+use crate::utils::MODERN_THEME;
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use std::fmt::Write;
+
+const GRAPH_HEIGHT: f32 = 60.0;
+const BAR_WIDTH: f32 = 8.0;
+/// The graph never scales below this, so a calm link does not look spiky.
+const GRAPH_MIN_RTT_MS: f32 = 100.0;
+
+pub fn u_toggle_net_stats(
+    state: Single<&ActionState<MenuAction>>,
+    overlay: Option<Single<Entity, With<NetStatsOverlay>>>,
+    mut commands: Commands,
+) {
+    if !state.just_pressed(&MenuAction::ToggleNetStats) {
+        return;
+    }
+
+    if let Some(overlay) = overlay {
+        commands.entity(*overlay).despawn();
+        return;
+    }
+
+    commands
+        .spawn((
+            NetStatsOverlay,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(20.0),
+                right: Val::Px(20.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(MODERN_THEME.panel_bg.with_alpha(0.8)),
+            GlobalZIndex(10),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                LabelBundle::custom("Not connected", MODERN_THEME.text_normal, 20.0),
+                NetStatsText,
+            ));
+
+            parent
+                .spawn(Node {
+                    height: Val::Px(GRAPH_HEIGHT),
+                    align_items: AlignItems::FlexEnd,
+                    column_gap: Val::Px(2.0),
+                    ..default()
+                })
+                .with_children(|graph| {
+                    for i in 0..NET_STATS_HISTORY {
+                        graph.spawn((
+                            NetStatsBar(i),
+                            Node {
+                                width: Val::Px(BAR_WIDTH),
+                                height: Val::Px(0.0),
+                                ..default()
+                            },
+                            BackgroundColor(rtt_color(0.0)),
+                        ));
+                    }
+                });
+        });
+}
+
+pub fn u_net_stats_overlay(
+    stats: Res<NetStats>,
+    text: Option<Single<&mut Text, With<NetStatsText>>>,
+    mut bars: Query<(&NetStatsBar, &mut Node, &mut BackgroundColor)>,
+    added: Query<(), Added<NetStatsOverlay>>,
+    mut shown: Local<u64>,
+) {
+    let Some(mut text) = text else { return };
+
+    if *shown == stats.samples_taken && added.is_empty() {
+        return;
+    }
+    *shown = stats.samples_taken;
+
+    text.0.clear();
+    match stats.latest() {
+        Some(sample) => write_sample(&mut text.0, sample),
+        None => text.0.push_str("Not connected"),
+    }
+
+    let scale = stats
+        .history
+        .iter()
+        .map(|sample| sample.rtt_ms)
+        .fold(GRAPH_MIN_RTT_MS, f32::max);
+
+    // newest sample on the right, missing older ones stay empty
+    let offset = NET_STATS_HISTORY - stats.history.len();
+
+    for (bar, mut node, mut color) in bars.iter_mut() {
+        let rtt = bar
+            .0
+            .checked_sub(offset)
+            .and_then(|i| stats.history.get(i))
+            .map_or(0.0, |sample| sample.rtt_ms);
+
+        node.height = Val::Px(rtt / scale * GRAPH_HEIGHT);
+        color.0 = rtt_color(rtt);
+    }
+}
+
+fn write_sample(text: &mut String, sample: &NetSample) {
+    let _ = write!(
+        text,
+        "RTT {:.0} ms  jitter {:.0} ms\n\
+         loss {:.1}%\n\
+         in {:.1} KB/s  out {:.1} KB/s\n\
+         input delay {} ticks\n\
+         rollbacks {:.1}/s",
+        sample.rtt_ms,
+        sample.jitter_ms,
+        sample.packet_loss * 100.0,
+        sample.bytes_in_per_sec / 1024.0,
+        sample.bytes_out_per_sec / 1024.0,
+        sample.input_delay_ticks,
+        sample.rollbacks_per_sec,
+    );
+}
+
+fn rtt_color(rtt_ms: f32) -> Color {
+    if rtt_ms < 80.0 {
+        Color::srgb(0.3, 0.8, 0.3)
+    } else if rtt_ms < 150.0 {
+        Color::srgb(1.0, 0.8, 0.3)
+    } else {
+        Color::srgb(0.9, 0.3, 0.3)
+    }
+}
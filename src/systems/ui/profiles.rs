@@ -0,0 +1,225 @@
+use crate::bundles::widgets::LabelBundle;
+use crate::components::ui::{ProfilesMenu, SourceHandle, UIOptionProvider};
+use crate::components::{ActiveProfile, PLAYER_COLOR_NAMES};
+use crate::events::widgets::{ButtonPressed, OptionChanged, TextInputSubmitted};
+use crate::resources::{sanitize_player_name, GameSettings, Monitors, PreferredControls, Profiles};
+use crate::systems::menu::{index_of_option, spawn_m_base, spawn_m_settings};
+use crate::systems::settings::persistence::write_profiles;
+use crate::systems::widgets::*;
+use crate::utils::MODERN_THEME;
+use bevy::input_focus::directional_navigation::DirectionalNavigationMap;
+use bevy::math::CompassOctant;
+use bevy::prelude::*;
+use bevy_simple_text_input::TextInputValue;
+
+pub const PLAYER_COLOR_OPTIONS: SourceHandle<dyn UIOptionProvider> = SourceHandle::Static(&PLAYER_COLOR_NAMES);
+
+pub const PREFERRED_CONTROLS_OPTIONS: SourceHandle<dyn UIOptionProvider> = SourceHandle::Static(&PREFERRED_CONTROLS_OPTIONS_RAW);
+
+pub const PREFERRED_CONTROLS_OPTIONS_RAW: [PreferredControls; 7] = [
+    PreferredControls::Any,
+    PreferredControls::Keyboard(1),
+    PreferredControls::Keyboard(2),
+    PreferredControls::Keyboard(3),
+    PreferredControls::Keyboard(4),
+    PreferredControls::Gamepad,
+    PreferredControls::Mouse,
+];
+
+/// Edits one profile at a time, picking another one respawns the page.
+pub fn spawn_m_profiles(profiles: &Profiles, index: usize, commands: &mut Commands, nav_map: &mut DirectionalNavigationMap) {
+    let index = index.min(profiles.profiles.len().saturating_sub(1));
+    let Some(profile) = profiles.get(index) else { return };
+
+    let mut column: Vec<Entity> = Vec::with_capacity(3);
+    let mut bottom: Vec<Entity> = Vec::with_capacity(4);
+
+    spawn_m_base(commands, nav_map, ProfilesMenu(index)).with_children(|base| {
+        base.spawn(w_menu_title("Profiles"));
+
+        base.spawn(w_menu_section()).with_children(|section| {
+            let names: Vec<String> = profiles.profiles.iter().map(|profile| profile.name.clone()).collect();
+            let mut selected = section.spawn_selector(SourceHandle::Unique(Box::new(names)), index, "Profile");
+            selected.root.observe(on_profile_changed);
+            column.push(selected.bar);
+
+            section
+                .spawn_input("Name: ")
+                .input
+                .insert(TextInputValue(profile.name.clone()))
+                .observe(on_name_submitted);
+
+            let mut color = section.spawn_selector(PLAYER_COLOR_OPTIONS, profile.color as usize, "Colour");
+            color.root.observe(on_color_changed);
+            column.push(color.bar);
+
+            let mut controls = section.spawn_selector(
+                PREFERRED_CONTROLS_OPTIONS,
+                index_of_option(&PREFERRED_CONTROLS_OPTIONS_RAW, &profile.controls),
+                "Preferred Controls",
+            );
+            controls.root.observe(on_controls_changed);
+            column.push(controls.bar);
+
+            let stats = profile.stats;
+            section.spawn(LabelBundle::custom(
+                &format!(
+                    "{} played, {} won, {} paddle hits",
+                    stats.matches_played, stats.matches_won, stats.paddle_hits,
+                ),
+                MODERN_THEME.text_normal,
+                28.0,
+            ));
+
+            if profiles.online == index {
+                section.spawn(LabelBundle::custom("Used for online play", MODERN_THEME.text_normal, 28.0));
+            }
+        });
+
+        base.spawn(w_row_container(Val::Px(10.0))).with_children(|row| {
+            const SIZE: Val2 = Val2::new(Val::Px(240.0), Val::Px(50.0));
+
+            bottom.push(
+                row.spawn(w_button(MODERN_THEME.button, "Back", SIZE))
+                    .observe(on_back)
+                    .id(),
+            );
+
+            bottom.push(
+                row.spawn(w_button(MODERN_THEME.button, "New", SIZE))
+                    .observe(on_new)
+                    .id(),
+            );
+
+            bottom.push(
+                row.spawn(w_button(MODERN_THEME.button, "Delete", SIZE))
+                    .observe(on_delete)
+                    .id(),
+            );
+
+            bottom.push(
+                row.spawn(w_button(MODERN_THEME.button, "Use Online", SIZE))
+                    .observe(on_use_online)
+                    .id(),
+            );
+        });
+    });
+
+    column.push(bottom[0]);
+    nav_map.add_looping_edges(&column, CompassOctant::South);
+    nav_map.add_looping_edges(&bottom, CompassOctant::East);
+
+    fn on_profile_changed(
+        change: On<OptionChanged>,
+        menu: Single<Entity, With<ProfilesMenu>>,
+        profiles: Res<Profiles>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        mut commands: Commands,
+    ) {
+        commands.entity(*menu).despawn();
+        spawn_m_profiles(&profiles, change.selected_index, &mut commands, &mut nav_map);
+    }
+
+    fn on_name_submitted(
+        submit: On<TextInputSubmitted>,
+        menu: Single<(Entity, &ProfilesMenu)>,
+        mut profiles: ResMut<Profiles>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        mut commands: Commands,
+    ) {
+        let Some(name) = sanitize_player_name(&submit.value) else { return };
+        let (menu_entity, menu) = menu.into_inner();
+        let Some(profile) = profiles.get_mut(menu.0) else { return };
+
+        profile.name = name;
+
+        // the profile selector lists the old name until the page is rebuilt
+        commands.entity(menu_entity).despawn();
+        spawn_m_profiles(&profiles, menu.0, &mut commands, &mut nav_map);
+    }
+
+    fn on_color_changed(change: On<OptionChanged>, menu: Single<&ProfilesMenu>, mut profiles: ResMut<Profiles>) {
+        if let Some(profile) = profiles.get_mut(menu.0) {
+            profile.color = change.selected_index as u8;
+        }
+    }
+
+    fn on_controls_changed(change: On<OptionChanged>, menu: Single<&ProfilesMenu>, mut profiles: ResMut<Profiles>) {
+        if let Some(profile) = profiles.get_mut(menu.0) {
+            profile.controls = PREFERRED_CONTROLS_OPTIONS_RAW[change.selected_index];
+        }
+    }
+
+    fn on_new(
+        _: On<ButtonPressed>,
+        menu: Single<Entity, With<ProfilesMenu>>,
+        mut profiles: ResMut<Profiles>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        mut commands: Commands,
+    ) {
+        let index = profiles.add();
+
+        commands.entity(*menu).despawn();
+        spawn_m_profiles(&profiles, index, &mut commands, &mut nav_map);
+    }
+
+    /// Local players who picked a later profile keep it, those on the
+    /// deleted one pick again the next time they join.
+    fn on_delete(
+        _: On<ButtonPressed>,
+        menu: Single<(Entity, &ProfilesMenu)>,
+        mut players: Query<(Entity, &mut ActiveProfile)>,
+        mut profiles: ResMut<Profiles>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        mut commands: Commands,
+    ) {
+        let (menu_entity, menu) = menu.into_inner();
+        let index = menu.0;
+
+        if profiles.profiles.len() <= 1 {
+            return;
+        }
+
+        profiles.remove(index);
+
+        for (entity, mut active) in players.iter_mut() {
+            if active.0 == index {
+                commands.entity(entity).remove::<ActiveProfile>();
+            } else if active.0 > index {
+                active.0 -= 1;
+            }
+        }
+
+        commands.entity(menu_entity).despawn();
+        spawn_m_profiles(&profiles, index, &mut commands, &mut nav_map);
+    }
+
+    fn on_use_online(
+        _: On<ButtonPressed>,
+        menu: Single<(Entity, &ProfilesMenu)>,
+        mut profiles: ResMut<Profiles>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        mut commands: Commands,
+    ) {
+        let (menu_entity, menu) = menu.into_inner();
+        profiles.online = menu.0;
+
+        commands.entity(menu_entity).despawn();
+        spawn_m_profiles(&profiles, menu.0, &mut commands, &mut nav_map);
+    }
+
+    fn on_back(
+        _: On<ButtonPressed>,
+        menu: Single<Entity, With<ProfilesMenu>>,
+        profiles: Res<Profiles>,
+        settings: Res<GameSettings>,
+        monitors: Res<Monitors>,
+        mut nav_map: ResMut<DirectionalNavigationMap>,
+        mut commands: Commands,
+    ) {
+        write_profiles(&profiles);
+
+        commands.entity(*menu).despawn();
+        spawn_m_settings(&settings, &monitors, &mut commands, &mut nav_map);
+    }
+}
@@ -15,7 +15,7 @@ use bevy::prelude::*;
 use bevy::text::FontSmoothing;
 use bevy::ui::{Checked, InteractionDisabled};
 use bevy::ui_widgets::{
-    Checkbox, Slider, SliderPrecision, SliderRange, SliderThumb, SliderValue, TrackClick,
+    Checkbox, Slider, SliderPrecision, SliderRange, SliderStep, SliderThumb, SliderValue, TrackClick,
 };
 use bevy_simple_text_input::{TextInput, TextInputTextColor, TextInputTextFont};
 use bevy_tweening::lens::UiBackgroundColorLens;
@@ -403,10 +403,12 @@ fn try_navigate(
                             let mut par = world.entity_mut(parent.0);
 
                             if let Some(value) = par.get::<SliderValue>() {
+                                let step = par.get::<SliderStep>().map_or(1.0, |step| step.0);
+
                                 if dir.eq(&CompassQuadrant::West) {
-                                    par.insert(SliderValue(value.0 - 1.0));
+                                    par.insert(SliderValue(value.0 - step));
                                 } else {
-                                    par.insert(SliderValue(value.0 + 1.0));
+                                    par.insert(SliderValue(value.0 + step));
                                 }
                             }
                         }
@@ -496,11 +498,14 @@ pub struct InputEntities<'a> {
 }
 
 pub trait WidgetsExtCommands {
+    /// `step` is how far one arrow press moves the value, dragging rounds
+    /// to as many decimals as it has.
     fn spawn_slider_custom(
         &mut self,
         min: f32,
         max: f32,
         cur: f32,
+        step: f32,
         size: Val2,
     ) -> SliderEntities<'_>;
 
@@ -521,9 +526,14 @@ pub trait WidgetsExtCommands {
 
     #[inline]
     fn spawn_slider(&mut self, min: f32, max: f32, cur: f32) -> SliderEntities<'_> {
+        self.spawn_slider_stepped(min, max, cur, 1.0)
+    }
+
+    #[inline]
+    fn spawn_slider_stepped(&mut self, min: f32, max: f32, cur: f32, step: f32) -> SliderEntities<'_> {
         const SIZE: Val2 = Val2::new(Val::Percent(100.0), Val::Px(50.0));
 
-        self.spawn_slider_custom(min, max, cur, SIZE)
+        self.spawn_slider_custom(min, max, cur, step, SIZE)
     }
 
     #[inline]
@@ -584,6 +594,7 @@ impl<'w, R: Relationship> WidgetsExtCommands for RelatedSpawnerCommands<'w, R> {
         min: f32,
         max: f32,
         cur: f32,
+        step: f32,
         size: Val2,
     ) -> SliderEntities<'_> {
         let mut root = self.spawn((
@@ -602,7 +613,8 @@ impl<'w, R: Relationship> WidgetsExtCommands for RelatedSpawnerCommands<'w, R> {
             Slider {
                 track_click: TrackClick::Snap,
             },
-            SliderPrecision(0),
+            SliderStep(step),
+            SliderPrecision((-step.log10()).ceil().max(0.0) as i32),
             SliderValue(cur),
             SliderRange::new(min, max),
         ));